@@ -0,0 +1,285 @@
+use std::collections::HashSet;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    spanned::Spanned, Attribute, Data, DeriveInput, Error, Expr, ExprLit, ExprUnary, Fields, Lit,
+    LitStr, Result, Type, UnOp,
+};
+
+struct Variant {
+    ident: syn::Ident,
+    name: String,
+    value: i16,
+}
+
+pub(crate) fn expand(input: DeriveInput) -> Result<TokenStream> {
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => {
+            return Err(Error::new(
+                input.ident.span(),
+                "`ClickHouseEnum` can be derived only for enums",
+            ))
+        }
+    };
+
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            input.generics.span(),
+            "`ClickHouseEnum` cannot be derived for generic enums",
+        ));
+    }
+
+    let mut force_enum16 = false;
+    for attr in ch_attrs(&input.attrs) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("enum16") {
+                force_enum16 = true;
+                Ok(())
+            } else {
+                Err(meta.error("unknown attribute, expected `enum16`"))
+            }
+        })?;
+    }
+
+    let mut variants = Vec::<Variant>::new();
+    let mut catch_all = None;
+    let mut next_value = 1i32;
+
+    for variant in &data.variants {
+        match &variant.fields {
+            Fields::Unit => {}
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                if catch_all.is_some() {
+                    return Err(Error::new(
+                        variant.span(),
+                        "only one variant can catch unknown values",
+                    ));
+                }
+                if let Some(attr) = ch_attrs(&variant.attrs).next() {
+                    return Err(Error::new(
+                        attr.span(),
+                        "a variant catching unknown values cannot have attributes",
+                    ));
+                }
+                let ty = &fields.unnamed[0].ty;
+                if !is_i16(ty) {
+                    return Err(Error::new(
+                        ty.span(),
+                        "a variant catching unknown values must contain `i16`",
+                    ));
+                }
+                catch_all = Some(variant.ident.clone());
+                continue;
+            }
+            _ => {
+                return Err(Error::new(
+                    variant.span(),
+                    "only unit variants and a single `Unknown(i16)` variant are supported",
+                ))
+            }
+        }
+
+        let mut value = None;
+        let mut name = None;
+
+        for attr in ch_attrs(&variant.attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("value") {
+                    value = Some(parse_value(&meta.value()?.parse()?)?);
+                    Ok(())
+                } else if meta.path.is_ident("name") {
+                    name = Some(meta.value()?.parse::<LitStr>()?.value());
+                    Ok(())
+                } else {
+                    Err(meta.error("unknown attribute, expected `value` or `name`"))
+                }
+            })?;
+        }
+
+        if value.is_none() {
+            if let Some((_, expr)) = &variant.discriminant {
+                value = Some(parse_value(expr)?);
+            }
+        }
+
+        let value = value.unwrap_or(next_value);
+        let value = i16::try_from(value)
+            .map_err(|_| Error::new(variant.span(), format!("{value} is out of range of i16")))?;
+        next_value = i32::from(value) + 1;
+
+        variants.push(Variant {
+            ident: variant.ident.clone(),
+            name: name.unwrap_or_else(|| variant.ident.to_string()),
+            value,
+        });
+    }
+
+    if variants.is_empty() {
+        return Err(Error::new(
+            input.ident.span(),
+            "`ClickHouseEnum` requires at least one unit variant",
+        ));
+    }
+
+    let mut names = HashSet::new();
+    let mut values = HashSet::new();
+    for variant in &variants {
+        if !names.insert(&variant.name) {
+            let msg = format!("duplicate name `{}`", variant.name);
+            return Err(Error::new(variant.ident.span(), msg));
+        }
+        if !values.insert(variant.value) {
+            let msg = format!("duplicate value `{}`", variant.value);
+            return Err(Error::new(variant.ident.span(), msg));
+        }
+    }
+
+    let is_enum8 = !force_enum16 && variants.iter().all(|v| i8::try_from(v.value).is_ok());
+    let data_type = format!(
+        "{}({})",
+        if is_enum8 { "Enum8" } else { "Enum16" },
+        variants
+            .iter()
+            .map(|v| format!("'{}' = {}", escape(&v.name), v.value))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    let ident = &input.ident;
+    let names = variants.iter().map(|v| &v.name);
+    let values = variants.iter().map(|v| v.value).collect::<Vec<_>>();
+    let idents = variants.iter().map(|v| &v.ident).collect::<Vec<_>>();
+
+    let (to_catch_all, from_catch_all) = match &catch_all {
+        Some(catch_all) => (
+            quote! { Self::#catch_all(value) => *value, },
+            quote! { value => ::core::option::Option::Some(Self::#catch_all(value)), },
+        ),
+        None => (quote! {}, quote! { _ => ::core::option::Option::None, }),
+    };
+
+    Ok(quote! {
+        #[automatically_derived]
+        impl clickhouse::ClickHouseEnum for #ident {
+            const DATA_TYPE: &'static str = #data_type;
+            const VARIANTS: &'static [(&'static str, i16)] = &[#( (#names, #values), )*];
+
+            fn to_value(&self) -> i16 {
+                match self {
+                    #( Self::#idents => #values, )*
+                    #to_catch_all
+                }
+            }
+
+            fn from_value(value: i16) -> ::core::option::Option<Self> {
+                match value {
+                    #( #values => ::core::option::Option::Some(Self::#idents), )*
+                    #from_catch_all
+                }
+            }
+        }
+
+        #[automatically_derived]
+        impl clickhouse::_priv::serde::Serialize for #ident {
+            fn serialize<S>(&self, serializer: S) -> ::core::result::Result<S::Ok, S::Error>
+            where
+                S: clickhouse::_priv::serde::Serializer,
+            {
+                clickhouse::_priv::serialize_enum(self, serializer)
+            }
+        }
+
+        #[automatically_derived]
+        impl<'de> clickhouse::_priv::serde::Deserialize<'de> for #ident {
+            fn deserialize<D>(deserializer: D) -> ::core::result::Result<Self, D::Error>
+            where
+                D: clickhouse::_priv::serde::Deserializer<'de>,
+            {
+                clickhouse::_priv::deserialize_enum(deserializer)
+            }
+        }
+    })
+}
+
+fn ch_attrs(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("ch"))
+}
+
+fn parse_value(expr: &Expr) -> Result<i32> {
+    let (negative, lit) = match expr {
+        Expr::Lit(ExprLit { lit, .. }) => (false, lit),
+        Expr::Unary(ExprUnary {
+            op: UnOp::Neg(_),
+            expr,
+            ..
+        }) => match &**expr {
+            Expr::Lit(ExprLit { lit, .. }) => (true, lit),
+            _ => return Err(Error::new(expr.span(), "expected an integer literal")),
+        },
+        _ => return Err(Error::new(expr.span(), "expected an integer literal")),
+    };
+
+    let value = match lit {
+        Lit::Int(int) => int.base10_parse::<i32>()?,
+        _ => return Err(Error::new(lit.span(), "expected an integer literal")),
+    };
+
+    Ok(if negative { -value } else { value })
+}
+
+fn escape(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+// `i16` and paths like `std::primitive::i16`.
+fn is_i16(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "i16" && segment.arguments.is_empty()),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_checks_catch_all() {
+        let error = |input: DeriveInput| expand(input).unwrap_err().to_string();
+
+        assert!(expand(syn::parse_quote!(
+            enum E {
+                A,
+                Unknown(i16),
+            }
+        ))
+        .is_ok());
+        assert!(expand(syn::parse_quote!(
+            enum E {
+                A,
+                Unknown(std::primitive::i16),
+            }
+        ))
+        .is_ok());
+        assert!(error(syn::parse_quote!(
+            enum E {
+                A,
+                Unknown(u8),
+            }
+        ))
+        .contains("must contain `i16`"));
+        assert!(error(syn::parse_quote!(
+            enum E {
+                A,
+                Unknown(String),
+            }
+        ))
+        .contains("must contain `i16`"));
+    }
+}
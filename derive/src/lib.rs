@@ -6,6 +6,9 @@ use serde_derive_internals::{
 };
 use syn::{parse_macro_input, Data, DataStruct, DeriveInput, Fields};
 
+mod clickhouse_enum;
+mod query;
+
 fn column_names(data: &DataStruct, cx: &Ctxt, container: &Container) -> TokenStream {
     match &data.fields {
         Fields::Named(fields) => {
@@ -64,3 +67,24 @@ pub fn row(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 
     proc_macro::TokenStream::from(expanded)
 }
+
+/// Derives `clickhouse::ClickHouseEnum`, `Serialize` and `Deserialize`
+/// for enums mapped to `Enum8` and `Enum16`.
+///
+/// See `clickhouse::ClickHouseEnum` for details.
+#[proc_macro_derive(ClickHouseEnum, attributes(ch))]
+pub fn clickhouse_enum(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    clickhouse_enum::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+// Documented in `clickhouse`, where it's re-exported.
+#[proc_macro]
+pub fn query(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    query::expand(input.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,152 @@
+use clickhouse_placeholders::Placeholder;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    Error, Expr, Ident, LitStr, Result, Token,
+};
+
+struct Input {
+    client: Expr,
+    sql: LitStr,
+    args: Vec<Expr>,
+    named: Vec<(Ident, Expr)>,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream<'_>) -> Result<Self> {
+        let client = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let sql = input.parse()?;
+
+        let mut args = Vec::new();
+        let mut named = Vec::new();
+
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+
+            if input.peek(Ident) && input.peek2(Token![=]) && !input.peek2(Token![==]) {
+                let name = input.parse()?;
+                input.parse::<Token![=]>()?;
+                named.push((name, input.parse()?));
+            } else {
+                args.push(input.parse()?);
+            }
+        }
+
+        Ok(Self {
+            client,
+            sql,
+            args,
+            named,
+        })
+    }
+}
+
+pub(crate) fn expand(input: TokenStream) -> Result<TokenStream> {
+    let Input {
+        client,
+        sql,
+        args,
+        named,
+    } = syn::parse2(input)?;
+
+    let placeholders = Placeholders::scan(&sql.value());
+
+    if args.len() != placeholders.args {
+        return Err(Error::new(
+            sql.span(),
+            format!(
+                "the query has {} `?` placeholder(s), but {} positional argument(s) are provided",
+                placeholders.args,
+                args.len(),
+            ),
+        ));
+    }
+
+    for (i, (name, _)) in named.iter().enumerate() {
+        let value = name.to_string();
+        if !placeholders.named.contains(&value) {
+            return Err(Error::new(
+                name.span(),
+                format!("`:{value}` isn't in the query"),
+            ));
+        }
+        if named[..i].iter().any(|(other, _)| *other == value) {
+            return Err(Error::new(
+                name.span(),
+                format!("`{value}` is bound more than once"),
+            ));
+        }
+    }
+
+    if let Some(missing) = placeholders
+        .named
+        .iter()
+        .find(|p| !named.iter().any(|(name, _)| name == p.as_str()))
+    {
+        return Err(Error::new(
+            sql.span(),
+            format!("`:{missing}` isn't bound, add `{missing} = value`"),
+        ));
+    }
+
+    let names = named.iter().map(|(name, _)| name.to_string());
+    let values = named.iter().map(|(_, value)| value);
+
+    // TODO: replace `clickhouse` with `::clickhouse` here.
+    Ok(quote! {
+        clickhouse::Client::query(&#client, #sql)
+            #( .bind(#args) )*
+            #( .bind_named(#names, #values) )*
+    })
+}
+
+/// Placeholders of the query, the same rules as `SqlBuilder` has.
+struct Placeholders {
+    args: usize,
+    named: Vec<String>,
+}
+
+impl Placeholders {
+    fn scan(template: &str) -> Self {
+        let mut placeholders = Self {
+            args: 0,
+            named: Vec::new(),
+        };
+
+        for (_, placeholder) in clickhouse_placeholders::scan(template) {
+            match placeholder {
+                Placeholder::Arg => placeholders.args += 1,
+                Placeholder::Named(name) if !placeholders.named.iter().any(|n| n == name) => {
+                    placeholders.named.push(name.into());
+                }
+                _ => {}
+            }
+        }
+
+        placeholders
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_checks_arguments() {
+        let error = |tokens: TokenStream| expand(tokens).unwrap_err().to_string();
+
+        assert!(expand(quote!(client, "SELECT ? + :a", 1, a = 2)).is_ok());
+        assert!(expand(quote!(client, "SELECT ?", x == 1,)).is_ok());
+        assert!(expand(quote!(client, "SELECT :a, ?{a}, '??' -- :b", a = 1)).is_ok());
+        assert!(error(quote!(client, "SELECT ?")).contains("1 `?` placeholder(s), but 0"));
+        assert!(error(quote!(client, "SELECT 1", 1)).contains("0 `?` placeholder(s), but 1"));
+        assert!(error(quote!(client, "SELECT 1", a = 1)).contains("`:a` isn't in the query"));
+        assert!(error(quote!(client, "SELECT :a")).contains("`:a` isn't bound"));
+        assert!(error(quote!(client, "SELECT :a", a = 1, a = 2)).contains("more than once"));
+    }
+}
@@ -17,12 +17,7 @@ struct Event {
 async fn main() -> Result<()> {
     let table_name = "chrs_async_insert";
 
-    let client = Client::default()
-        .with_url("http://localhost:8123")
-        // https://clickhouse.com/docs/en/operations/settings/settings#async-insert
-        .with_option("async_insert", "1")
-        // https://clickhouse.com/docs/en/operations/settings/settings#wait-for-async-insert
-        .with_option("wait_for_async_insert", "0");
+    let client = Client::default().with_url("http://localhost:8123");
 
     client
         .query(
@@ -39,14 +34,20 @@ async fn main() -> Result<()> {
         .execute()
         .await?;
 
-    let mut insert = client.insert(table_name)?;
-    insert
+    // Sets `async_insert=1` for every `INSERT`.
+    let inserter = client
+        .async_inserter::<Event>(table_name)
+        // https://clickhouse.com/docs/en/operations/settings/settings#wait-for-async-insert
+        .with_wait_for_async_insert(false)
+        .with_busy_timeout(Duration::from_millis(200));
+
+    let ack = inserter
         .write(&Event {
             timestamp: now(),
             message: "one".into(),
         })
         .await?;
-    insert.end().await?;
+    println!("Async insert was accepted, query_id: {}", ack.query_id);
 
     loop {
         let events = client
@@ -59,7 +60,7 @@ async fn main() -> Result<()> {
             println!("{events:?}");
             break;
         }
-        // If you change the `wait_for_async_insert` setting to 1, this line will never
+        // If you change `with_wait_for_async_insert` to `true`, this line will never
         // be printed; however, without waiting, you will see it in the console
         // output several times, as the data will remain in the server buffer
         // for a bit before the flush happens
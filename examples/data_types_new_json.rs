@@ -1,11 +1,13 @@
 use clickhouse_derive::Row;
 use serde::{Deserialize, Serialize};
 
-use clickhouse::{error::Result, sql::Identifier, Client};
+use clickhouse::{error::Result, sql::Identifier, types::Json, Client};
 
-// Requires ClickHouse 24.10+, as the `input_format_binary_read_json_as_string`
-// and `output_format_binary_write_json_as_string` settings were added in that
-// version. Inserting and selecting a row with a JSON column as a string.
+// Requires ClickHouse 24.10+ and the `json` feature for conversions from `serde_json`.
+// Inserting and selecting a row with a JSON column using its native RowBinary encoding.
+// JSON values can also be inserted and selected as strings, see the
+// `input_format_binary_read_json_as_string` and `output_format_binary_write_json_as_string`
+// settings.
 // See also: https://clickhouse.com/docs/en/sql-reference/data-types/newjson
 
 #[tokio::main]
@@ -13,13 +15,9 @@ async fn main() -> Result<()> {
     let table_name = "chrs_data_types_new_json";
     let client = Client::default()
         .with_url("http://localhost:8123")
-        // All these settings can instead be applied on the query or insert level with the same
+        // This setting can instead be applied on the query or insert level with the same
         // `with_option` method. Enable new JSON type usage
-        .with_option("allow_experimental_json_type", "1")
-        // Enable inserting JSON columns as a string
-        .with_option("input_format_binary_read_json_as_string", "1")
-        // Enable selecting JSON columns as a string
-        .with_option("output_format_binary_write_json_as_string", "1");
+        .with_option("allow_experimental_json_type", "1");
 
     client
         .query(
@@ -35,18 +33,18 @@ async fn main() -> Result<()> {
         .execute()
         .await?;
 
+    let data = serde_json::json!({
+        "name": "John Doe",
+        "age": 42,
+        "phones": [
+            "+123 456 789",
+            "+987 654 321"
+        ]
+    });
+
     let row = Row {
         id: 1,
-        data: r#"
-        {
-            "name": "John Doe",
-            "age": 42,
-            "phones": [
-                "+123 456 789",
-                "+987 654 321"
-            ]
-        }"#
-        .to_string(),
+        data: data.as_object().cloned().unwrap_or_default().into(),
     };
 
     let mut insert = client.insert(table_name)?;
@@ -61,9 +59,9 @@ async fn main() -> Result<()> {
 
     println!("{db_row:#?}");
 
-    // You can then use any JSON library to parse the JSON string, e.g., serde_json.
-    let json_value: serde_json::Value = serde_json::from_str(&db_row.data).expect("Invalid JSON");
-    println!("Extracted name from JSON: {}", json_value["name"]);
+    // Values are available by their paths, or can be converted back to `serde_json::Value`.
+    println!("Extracted name from JSON: {:?}", db_row.data.get("name"));
+    println!("{}", serde_json::Value::from(db_row.data));
 
     Ok(())
 }
@@ -71,5 +69,5 @@ async fn main() -> Result<()> {
 #[derive(Debug, Row, Serialize, Deserialize)]
 pub struct Row {
     id: u64,
-    data: String,
+    data: Json,
 }
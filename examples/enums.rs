@@ -1,9 +1,8 @@
 use std::time::UNIX_EPOCH;
 
 use serde::{Deserialize, Serialize};
-use serde_repr::{Deserialize_repr, Serialize_repr};
 
-use clickhouse::{error::Result, Client, Row};
+use clickhouse::{error::Result, ClickHouseEnum, Client, Row};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -14,22 +13,37 @@ async fn main() -> Result<()> {
         .execute()
         .await?;
 
+    // How to define enums that map to `Enum8`/`Enum16`.
+    // Alternatively, `serde_repr` can be used, but then unknown values
+    // lead to less descriptive errors.
+    #[derive(Debug, ClickHouseEnum)]
+    enum Level {
+        #[ch(value = 1, name = "debug")]
+        Debug,
+        #[ch(value = 2, name = "info")]
+        Info,
+        #[ch(value = 3, name = "warn")]
+        Warn,
+        #[ch(value = 4, name = "error")]
+        Error,
+        // Values that are added to the enum later are caught here.
+        Unknown(i16),
+    }
+
+    // The data type can be used in DDL:
+    // Enum8('debug' = 1, 'info' = 2, 'warn' = 3, 'error' = 4)
     client
-        .query(
+        .query(&format!(
             "
             CREATE TABLE event_log (
                 timestamp       DateTime64(9),
                 message         String,
-                level           Enum8(
-                                    'Debug' = 1,
-                                    'Info' = 2,
-                                    'Warn' = 3,
-                                    'Error' = 4
-                                )
+                level           {}
             )
             ENGINE = MergeTree
             ORDER BY timestamp",
-        )
+            Level::DATA_TYPE
+        ))
         .execute()
         .await?;
 
@@ -40,16 +54,6 @@ async fn main() -> Result<()> {
         level: Level,
     }
 
-    // How to define enums that map to `Enum8`/`Enum16`.
-    #[derive(Debug, Serialize_repr, Deserialize_repr)]
-    #[repr(u8)]
-    enum Level {
-        Debug = 1,
-        Info = 2,
-        Warn = 3,
-        Error = 4,
-    }
-
     let mut insert = client.insert("event_log")?;
     insert
         .write(&Event {
@@ -66,6 +70,14 @@ async fn main() -> Result<()> {
         .await?;
     println!("{events:?}");
 
+    // In `bind()`, enums are represented by their names.
+    let warnings = client
+        .query("SELECT count() FROM event_log WHERE level >= ?")
+        .bind(Level::Warn)
+        .fetch_one::<u64>()
+        .await?;
+    println!("{warnings} warnings or errors");
+
     Ok(())
 }
 
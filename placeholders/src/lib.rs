@@ -0,0 +1,248 @@
+//! Scans SQL templates of `clickhouse::Client::query()` for placeholders.
+//!
+//! Used by `clickhouse` to build queries and by `clickhouse-derive` to check
+//! arguments of `query!` at compile time, so both follow the same rules.
+//! Not intended to be used directly.
+
+use std::ops::Range;
+
+/// A placeholder of a template, see [`scan()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placeholder<'a> {
+    /// `?`, a positional argument.
+    Arg,
+    /// `??`, an escaped `?`.
+    Question,
+    /// `?fields`, a list of fields of a row.
+    Fields,
+    /// `?{name}` or `:name`, a named argument.
+    Named(&'a str),
+    /// `{name: Type}`, a server-side parameter.
+    ServerParam(&'a str),
+}
+
+/// Returns placeholders of the template along with their positions:
+/// * `?` is a positional argument, `??` is escaped `?`.
+/// * `?fields` is a list of fields of a row.
+/// * `?{name}` and `:name` are named arguments. Unlike `?`, the latter
+///   is recognized only outside of quotes and comments, and not in `::`
+///   and `.:` casts and in `{param:Type}` of server-side parameters.
+/// * `{name: Type}` is a server-side parameter, which is also recognized
+///   only outside of quotes and comments.
+pub fn scan(template: &str) -> Scanner<'_> {
+    Scanner {
+        template,
+        idx: 0,
+        context: Context::Code,
+    }
+}
+
+/// An iterator over placeholders of a template, see [`scan()`].
+pub struct Scanner<'a> {
+    template: &'a str,
+    idx: usize,
+    context: Context,
+}
+
+#[derive(Clone, Copy)]
+enum Context {
+    Code,
+    Quoted(u8),
+    // `--`, `# `, `#!` till the end of line and `/* .. */`.
+    Comment(&'static str),
+}
+
+impl<'a> Iterator for Scanner<'a> {
+    type Item = (Range<usize>, Placeholder<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let template = self.template;
+        let bytes = template.as_bytes();
+
+        while self.idx < bytes.len() {
+            let idx = self.idx;
+            // Empty if `idx` isn't followed by a char boundary, i.e. isn't ASCII.
+            let rest = template.get(idx + 1..).unwrap_or_default();
+
+            let (len, placeholder) = match (self.context, bytes[idx]) {
+                (_, b'?') if rest.starts_with('?') => (2, Some(Placeholder::Question)),
+                (_, b'?') if rest.starts_with("fields") => (7, Some(Placeholder::Fields)),
+                (_, b'?') => match braced_name(rest) {
+                    Some(name) => (name.len() + 3, Some(Placeholder::Named(name))),
+                    None => (1, Some(Placeholder::Arg)),
+                },
+                (Context::Quoted(_), b'\\') => (2, None),
+                (Context::Quoted(quote), c) if c == quote => {
+                    self.context = Context::Code;
+                    (1, None)
+                }
+                (Context::Comment(end), _) if bytes[idx..].starts_with(end.as_bytes()) => {
+                    self.context = Context::Code;
+                    (end.len(), None)
+                }
+                (Context::Code, c @ (b'\'' | b'"' | b'`')) => {
+                    self.context = Context::Quoted(c);
+                    (1, None)
+                }
+                (Context::Code, b'-') if rest.starts_with('-') => {
+                    self.context = Context::Comment("\n");
+                    (2, None)
+                }
+                (Context::Code, b'#') if rest.starts_with([' ', '!']) => {
+                    self.context = Context::Comment("\n");
+                    (2, None)
+                }
+                (Context::Code, b'/') if rest.starts_with('*') => {
+                    self.context = Context::Comment("*/");
+                    (2, None)
+                }
+                (Context::Code, b'{') => match server_param(rest) {
+                    Some((name, len)) => (len + 1, Some(Placeholder::ServerParam(name))),
+                    None => (1, None),
+                },
+                (Context::Code, b':') if idx == 0 || !is_name_char(bytes[idx - 1]) => {
+                    let len = name_len(rest);
+                    if len > 0 && !rest.as_bytes()[0].is_ascii_digit() {
+                        (len + 1, Some(Placeholder::Named(&rest[..len])))
+                    } else {
+                        (1, None)
+                    }
+                }
+                _ => (1, None),
+            };
+
+            self.idx += len;
+
+            if let Some(placeholder) = placeholder {
+                return Some((idx..self.idx, placeholder));
+            }
+        }
+
+        None
+    }
+}
+
+// `a::Type` and `json.a.:Type` are casts, not named arguments.
+fn is_name_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, b'_' | b':' | b'.')
+}
+
+fn name_len(s: &str) -> usize {
+    s.bytes()
+        .take_while(|&c| c.is_ascii_alphanumeric() || c == b'_')
+        .count()
+}
+
+// Returns `name` if `s` starts with `{name}`.
+fn braced_name(s: &str) -> Option<&str> {
+    let s = s.strip_prefix('{')?;
+    let len = name_len(s);
+    (len > 0 && s[len..].starts_with('}')).then(|| &s[..len])
+}
+
+// Returns `name` and the length till `}` inclusive if `s` follows `{` of `{name: Type}`.
+fn server_param(s: &str) -> Option<(&str, usize)> {
+    let trimmed = s.trim_start();
+    let len = name_len(trimmed);
+    if len == 0 || trimmed.as_bytes()[0].is_ascii_digit() {
+        return None;
+    }
+
+    if !trimmed[len..].trim_start().starts_with(':') {
+        return None;
+    }
+
+    let end = s.find('}')?;
+    Some((&trimmed[..len], end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placeholders(template: &str) -> Vec<(&str, Placeholder<'_>)> {
+        scan(template)
+            .map(|(range, placeholder)| (&template[range], placeholder))
+            .collect()
+    }
+
+    #[test]
+    fn it_finds_placeholders() {
+        assert_eq!(placeholders("SELECT 1"), []);
+        assert_eq!(
+            placeholders("SELECT ?fields FROM t WHERE a = ? AND b IN ?{b} AND c = :c_1 AND ?"),
+            [
+                ("?fields", Placeholder::Fields),
+                ("?", Placeholder::Arg),
+                ("?{b}", Placeholder::Named("b")),
+                (":c_1", Placeholder::Named("c_1")),
+                ("?", Placeholder::Arg),
+            ]
+        );
+        assert_eq!(
+            placeholders("SELECT 'a??b', ?{}, ?{a b}"),
+            [
+                ("??", Placeholder::Question),
+                ("?", Placeholder::Arg),
+                ("?", Placeholder::Arg),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_skips_named_in_quotes() {
+        assert_eq!(
+            placeholders("SELECT ':a', `:b`, \"\\\":c\", 'd'':e', '?'"),
+            [("?", Placeholder::Arg)]
+        );
+        assert_eq!(
+            placeholders("SELECT 'é', `ф:`, :x"),
+            [(":x", Placeholder::Named("x"))]
+        );
+    }
+
+    #[test]
+    fn it_skips_named_in_casts() {
+        assert_eq!(
+            placeholders("SELECT a::UInt8, json.a.:Int64, json.b.:`Array(JSON)`.c, '12:30'"),
+            []
+        );
+        assert_eq!(placeholders("SELECT :1, :, x:y"), []);
+    }
+
+    #[test]
+    fn it_finds_server_params() {
+        assert_eq!(
+            placeholders("SELECT {p:String}, { q : Map(String, UInt8) }, {1: x}, {d}, {e: x"),
+            [
+                ("{p:String}", Placeholder::ServerParam("p")),
+                ("{ q : Map(String, UInt8) }", Placeholder::ServerParam("q")),
+            ]
+        );
+        assert_eq!(
+            placeholders("SELECT '{a: UInt8}', `{b: UInt8}`, \"\\\"{c: UInt8}\", ?{d}"),
+            [("?{d}", Placeholder::Named("d"))]
+        );
+    }
+
+    #[test]
+    fn it_skips_named_in_comments() {
+        let template = "SELECT -- it's :a
+            :b /* :c
+            's */ # :d
+            #! :e
+            #:f
+            FROM t WHERE a = ? -- ? {x: UInt8}
+            /* {y: UInt8} */";
+
+        assert_eq!(
+            placeholders(template),
+            [
+                (":b", Placeholder::Named("b")),
+                (":f", Placeholder::Named("f")),
+                ("?", Placeholder::Arg),
+                ("?", Placeholder::Arg),
+            ]
+        );
+    }
+}
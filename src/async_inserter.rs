@@ -0,0 +1,198 @@
+//! Asynchronous inserts, batched by the server instead of the client.
+
+use std::{
+    iter,
+    marker::PhantomData,
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use crate::{error::Result, insert::Insert, row::Row, Client};
+
+/// Performs [asynchronous inserts], suitable for small and frequent writes.
+///
+/// Every call of [`AsyncInserter::write()`] or [`AsyncInserter::write_all()`]
+/// is a separate `INSERT` with `async_insert=1`, the server collects rows in
+/// a buffer and flushes it to the table according to its settings.
+///
+/// Use [`Inserter`] for client-side batching instead.
+///
+/// [asynchronous inserts]: https://clickhouse.com/docs/en/optimize/asynchronous-inserts
+/// [`Inserter`]: crate::inserter::Inserter
+#[must_use]
+pub struct AsyncInserter<T> {
+    client: Client,
+    table: String,
+    send_timeout: Option<Duration>,
+    end_timeout: Option<Duration>,
+    _marker: PhantomData<fn(&T)>,
+}
+
+/// Information about an `INSERT` accepted by the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsyncInsertAck {
+    /// The query ID, e.g. to look the insert up in `system.asynchronous_insert_log`.
+    pub query_id: String,
+    /// The number of sent rows.
+    pub rows: u64,
+    /// The number of sent uncompressed bytes.
+    pub bytes: u64,
+}
+
+impl<T> AsyncInserter<T> {
+    pub(crate) fn new(client: &Client, table: &str) -> Self {
+        let mut client = client.clone();
+        client.add_option("async_insert", "1");
+        client.add_option("wait_for_async_insert", "1");
+
+        Self {
+            client,
+            table: table.into(),
+            send_timeout: None,
+            end_timeout: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Whether to wait for the server to flush the buffer to the table
+    /// before acknowledging the `INSERT`.
+    ///
+    /// If `false`, the `INSERT` is acknowledged once data is in the buffer,
+    /// thus errors of flushing (e.g. type mismatches) aren't reported and
+    /// data can be lost if the server restarts.
+    ///
+    /// `true` by default, see [`wait_for_async_insert`].
+    ///
+    /// [`wait_for_async_insert`]: https://clickhouse.com/docs/en/operations/settings/settings#wait_for_async_insert
+    pub fn with_wait_for_async_insert(mut self, wait: bool) -> Self {
+        let value = if wait { "1" } else { "0" };
+        self.client.add_option("wait_for_async_insert", value);
+        self
+    }
+
+    /// The maximum time to wait before flushing the server's buffer since
+    /// the first insert into it.
+    ///
+    /// The server's default is used if unset, see
+    /// [`async_insert_busy_timeout_ms`].
+    ///
+    /// [`async_insert_busy_timeout_ms`]: https://clickhouse.com/docs/en/operations/settings/settings#async_insert_busy_timeout_ms
+    pub fn with_busy_timeout(mut self, timeout: Duration) -> Self {
+        let value = timeout.as_millis().to_string();
+        self.client
+            .add_option("async_insert_busy_timeout_ms", value);
+        self
+    }
+
+    /// The maximum size of the server's buffer in uncompressed bytes
+    /// before flushing it.
+    ///
+    /// The server's default is used if unset, see
+    /// [`async_insert_max_data_size`].
+    ///
+    /// [`async_insert_max_data_size`]: https://clickhouse.com/docs/en/operations/settings/settings#async_insert_max_data_size
+    pub fn with_max_data_size(mut self, size: u64) -> Self {
+        let value = size.to_string();
+        self.client.add_option("async_insert_max_data_size", value);
+        self
+    }
+
+    /// See [`Insert::with_timeouts()`].
+    ///
+    /// With [`AsyncInserter::with_wait_for_async_insert()`] enabled,
+    /// `end_timeout` includes waiting for the server's buffer to be flushed.
+    pub fn with_timeouts(
+        mut self,
+        send_timeout: Option<Duration>,
+        end_timeout: Option<Duration>,
+    ) -> Self {
+        self.send_timeout = send_timeout;
+        self.end_timeout = end_timeout;
+        self
+    }
+
+    /// Similar to [`Client::with_option`], but for the INSERT statements
+    /// generated by this [`AsyncInserter`] only.
+    pub fn with_option(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.client.add_option(name, value);
+        self
+    }
+
+    /// Inserts one row.
+    pub async fn write(&self, row: &T) -> Result<AsyncInsertAck>
+    where
+        T: Row + Serialize,
+    {
+        self.insert(iter::once(row)).await
+    }
+
+    /// Inserts rows in one `INSERT`.
+    ///
+    /// Returns `None` without sending any request if `rows` is empty.
+    pub async fn write_all<'a>(
+        &self,
+        rows: impl IntoIterator<Item = &'a T>,
+    ) -> Result<Option<AsyncInsertAck>>
+    where
+        T: Row + Serialize + 'a,
+    {
+        let mut rows = rows.into_iter().peekable();
+        if rows.peek().is_none() {
+            return Ok(None);
+        }
+
+        self.insert(rows).await.map(Some)
+    }
+
+    async fn insert<'a>(&self, rows: impl Iterator<Item = &'a T>) -> Result<AsyncInsertAck>
+    where
+        T: Row + Serialize + 'a,
+    {
+        let query_id = next_query_id();
+        let mut insert = Insert::<T>::new(&self.client, &self.table)?;
+        insert.set_option("query_id", query_id.clone());
+        insert.set_timeouts(self.send_timeout, self.end_timeout);
+
+        let mut ack = AsyncInsertAck {
+            query_id,
+            rows: 0,
+            bytes: 0,
+        };
+
+        for row in rows {
+            ack.bytes += insert.do_write(row)? as u64;
+            ack.rows += 1;
+            insert.send_if_full().await?;
+        }
+
+        insert.end().await?;
+        Ok(ack)
+    }
+}
+
+impl<T> Clone for AsyncInserter<T> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            table: self.table.clone(),
+            send_timeout: self.send_timeout,
+            end_timeout: self.end_timeout,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Generates a query ID unique across processes.
+fn next_query_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64);
+    let seq = COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    format!("{nanos:016x}-{:08x}-{seq}", process::id())
+}
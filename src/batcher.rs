@@ -0,0 +1,389 @@
+//! A shareable handle to insert rows from multiple tasks.
+
+use std::{
+    marker::PhantomData,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use serde::Serialize;
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::Duration,
+};
+
+use crate::{
+    error::{Error, Result},
+    executor::{Executor, Task},
+    inserter::{DeduplicationToken, Inserter, Quantities},
+    row::Row,
+    rowbinary, Client,
+};
+
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// Configures a background task performing `INSERT`s, see [`Client::batcher`].
+///
+/// Limits are the same as for [`Inserter`] and are checked after every row
+/// and on every tick of the period.
+#[must_use]
+pub struct Batcher<T> {
+    executor: Arc<dyn Executor>,
+    inserter: Inserter<T>,
+    capacity: usize,
+}
+
+/// A cloneable handle to send rows to a background task started by
+/// [`Batcher::spawn()`].
+///
+/// The task ends the current `INSERT` and stops either after
+/// [`BatchHandle::shutdown()`] or once all handles are dropped.
+pub struct BatchHandle<T> {
+    sender: mpsc::Sender<Message>,
+    stats: Arc<Mutex<BatchStats>>,
+    _marker: PhantomData<fn(&T)>,
+}
+
+/// Statistics about rows sent through a [`BatchHandle`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchStats {
+    /// Successfully inserted data.
+    pub inserted: Quantities,
+    /// Data lost because of failed `INSERT`s.
+    pub failed: Quantities,
+    /// Data written to the current `INSERT`, not yet inserted.
+    pub pending: Quantities,
+    /// The error of the last failed `INSERT`, if any.
+    pub last_error: Option<String>,
+}
+
+enum Message {
+    Row(Vec<u8>),
+    Flush(oneshot::Sender<Result<Quantities>>),
+    Shutdown(oneshot::Sender<Result<Quantities>>),
+}
+
+impl<T: Row> Batcher<T> {
+    pub(crate) fn new(client: &Client, table: &str) -> Result<Self> {
+        Ok(Self {
+            executor: client.executor.clone(),
+            inserter: Inserter::new(client, table)?,
+            capacity: DEFAULT_CAPACITY,
+        })
+    }
+
+    /// The maximum number of rows waiting in the channel. When it's full,
+    /// [`BatchHandle::send()`] waits for the background task.
+    ///
+    /// `1024` by default.
+    ///
+    /// # Panics
+    /// If `capacity` is zero.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0, "capacity must be positive");
+        self.capacity = capacity;
+        self
+    }
+
+    /// See [`Inserter::with_timeouts()`].
+    pub fn with_timeouts(
+        mut self,
+        send_timeout: Option<Duration>,
+        end_timeout: Option<Duration>,
+    ) -> Self {
+        self.inserter.set_timeouts(send_timeout, end_timeout);
+        self
+    }
+
+    /// See [`Inserter::with_max_bytes()`].
+    pub fn with_max_bytes(mut self, threshold: u64) -> Self {
+        self.inserter.set_max_bytes(threshold);
+        self
+    }
+
+    /// See [`Inserter::with_max_rows()`].
+    pub fn with_max_rows(mut self, threshold: u64) -> Self {
+        self.inserter.set_max_rows(threshold);
+        self
+    }
+
+    /// See [`Inserter::with_period()`].
+    ///
+    /// Unlike [`Inserter`], the background task checks the period on its own,
+    /// so the `INSERT` is ended in time even if no rows are sent.
+    pub fn with_period(mut self, period: Option<Duration>) -> Self {
+        self.inserter.set_period(period);
+        self
+    }
+
+    /// See [`Inserter::with_period_bias()`].
+    pub fn with_period_bias(mut self, bias: f64) -> Self {
+        self.inserter.set_period_bias(bias);
+        self
+    }
+
+    /// See [`Inserter::with_parallelism()`].
+    ///
+    /// Unlike [`Inserter`], commits of the task wait for responses to all
+    /// `INSERT`s, so [`BatchStats`] counts data of every commit exactly.
+    ///
+    /// # Panics
+    /// If `n` is zero.
+    pub fn with_parallelism(mut self, n: usize) -> Self {
+        self.inserter = self.inserter.with_parallelism(n);
+        self
+    }
+
+    /// See [`Inserter::with_option()`].
+    pub fn with_option(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.inserter = self.inserter.with_option(name, value);
+        self
+    }
+
+    /// See [`Inserter::with_deduplication_token()`].
+    pub fn with_deduplication_token(mut self, token: DeduplicationToken) -> Self {
+        self.inserter.set_deduplication_token(token);
+        self
+    }
+
+    /// See [`Inserter::with_spool()`].
+    pub fn with_spool(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.inserter.set_spool(dir);
+        self
+    }
+
+    /// Spawns the background task by the client's [`Executor`] and returns
+    /// a handle to it.
+    ///
+    /// # Panics
+    /// If called outside of the tokio runtime with the default executor.
+    pub fn spawn(self) -> BatchHandle<T>
+    where
+        T: 'static,
+    {
+        let (sender, receiver) = mpsc::channel(self.capacity);
+        let stats = Arc::new(Mutex::new(BatchStats {
+            inserted: Quantities::ZERO,
+            failed: Quantities::ZERO,
+            pending: Quantities::ZERO,
+            last_error: None,
+        }));
+
+        let task = run(self.inserter, receiver, stats.clone());
+        self.executor.spawn(Task::new("batcher", task));
+
+        BatchHandle {
+            sender,
+            stats,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> BatchHandle<T> {
+    /// Serializes the row and sends it to the background task.
+    ///
+    /// Waits if the channel is full, thus providing backpressure.
+    ///
+    /// Returns an error if the row cannot be serialized or the background
+    /// task is stopped. Errors of `INSERT`s aren't returned here, they're
+    /// reflected in [`BatchStats`] and returned by the next
+    /// [`BatchHandle::flush()`] or [`BatchHandle::shutdown()`].
+    pub async fn send(&self, row: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let mut buffer = Vec::new();
+        rowbinary::serialize_into(&mut buffer, row)?;
+        self.sender
+            .send(Message::Row(buffer))
+            .await
+            .map_err(|_| Error::BatcherClosed)
+    }
+
+    /// Ends the current `INSERT` after all rows sent before.
+    ///
+    /// Returns statistics about inserted data. If any `INSERT` ended in
+    /// the background since the previous flush has failed, the first
+    /// such error is returned instead.
+    pub async fn flush(&self) -> Result<Quantities> {
+        self.request(Message::Flush).await
+    }
+
+    /// Ends the current `INSERT` after all queued rows and stops the
+    /// background task. Rows sent by other handles after this call are
+    /// rejected with [`Error::BatcherClosed`].
+    ///
+    /// Returns statistics about data inserted by the last `INSERT` or,
+    /// like [`BatchHandle::flush()`], an error of a previous one.
+    pub async fn shutdown(self) -> Result<Quantities> {
+        self.request(Message::Shutdown).await
+    }
+
+    /// Returns statistics about data sent through all handles.
+    pub fn stats(&self) -> BatchStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Returns `true` if the background task is stopped.
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed()
+    }
+
+    async fn request(
+        &self,
+        make: fn(oneshot::Sender<Result<Quantities>>) -> Message,
+    ) -> Result<Quantities> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(make(tx))
+            .await
+            .map_err(|_| Error::BatcherClosed)?;
+        rx.await.map_err(|_| Error::BatcherClosed)?
+    }
+}
+
+impl<T> Clone for BatchHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            stats: self.stats.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+async fn run<T: Row>(
+    mut inserter: Inserter<T>,
+    mut receiver: mpsc::Receiver<Message>,
+    stats: Arc<Mutex<BatchStats>>,
+) {
+    let mut shutdowns = Vec::new();
+    // The first error of `INSERT`s ended in the background, which is
+    // returned by the next flush.
+    let mut background_error = None;
+
+    loop {
+        let message = match inserter.time_left() {
+            Some(time_left) => match tokio::time::timeout(time_left, receiver.recv()).await {
+                Ok(message) => message,
+                Err(_) => {
+                    let result = commit(&mut inserter, &stats, false).await;
+                    keep_error(&mut background_error, result);
+                    continue;
+                }
+            },
+            None => receiver.recv().await,
+        };
+
+        match message {
+            Some(Message::Row(row)) => {
+                let result = inserter.write_serialized(&row);
+                update_stats(&stats, |stats| {
+                    if let Err(err) = &result {
+                        // The inserter discards pending rows of the failed
+                        // shard only, other shards keep theirs.
+                        let mut failed = difference(&stats.pending, inserter.pending());
+                        failed.rows += 1;
+                        failed.bytes += row.len() as u64;
+                        stats.failed.add(&failed);
+                        stats.last_error = Some(err.to_string());
+                    }
+                    stats.pending = inserter.pending().clone();
+                });
+
+                if let Err(err) = result {
+                    keep_error(&mut background_error, Err(err));
+                } else {
+                    let result = commit(&mut inserter, &stats, false).await;
+                    keep_error(&mut background_error, result);
+                }
+            }
+            Some(Message::Flush(ack)) => {
+                let result = commit(&mut inserter, &stats, true).await;
+                let _ = ack.send(background_error.take().map_or(result, Err));
+            }
+            Some(Message::Shutdown(ack)) => {
+                // Reject new messages, but handle already queued ones.
+                receiver.close();
+                shutdowns.push(ack);
+            }
+            None => break,
+        }
+    }
+
+    let result = commit(&mut inserter, &stats, true).await;
+    let result = background_error.take().map_or(result, Err);
+    let mut acks = shutdowns.into_iter();
+
+    match result {
+        Ok(quantities) => {
+            for ack in acks {
+                let _ = ack.send(Ok(quantities.clone()));
+            }
+        }
+        Err(err) => {
+            let message = err.to_string();
+            if let Some(ack) = acks.next() {
+                let _ = ack.send(Err(err));
+            }
+            for ack in acks {
+                let _ = ack.send(Err(Error::Other(message.clone().into())));
+            }
+        }
+    }
+}
+
+async fn commit<T: Row>(
+    inserter: &mut Inserter<T>,
+    stats: &Mutex<BatchStats>,
+    force: bool,
+) -> Result<Quantities> {
+    // `Inserter::commit()` isn't used to count rows between checks as one
+    // transaction.
+    if !force && !inserter.limits_reached() {
+        return Ok(Quantities::ZERO);
+    }
+
+    let pending = inserter.pending().clone();
+    let result = inserter.force_commit_all().await;
+
+    update_stats(stats, |stats| {
+        match &result {
+            Ok(quantities) => stats.inserted.add(quantities),
+            Err(err) => {
+                // Other shards can succeed, their data is inserted.
+                let mut inserted = Quantities::ZERO;
+                for quantities in inserter.shard_quantities() {
+                    inserted.rows += quantities.rows;
+                    inserted.bytes += quantities.bytes;
+                }
+                stats.inserted.rows += inserted.rows;
+                stats.inserted.bytes += inserted.bytes;
+                stats.failed.add(&difference(&pending, &inserted));
+                stats.last_error = Some(err.to_string());
+            }
+        }
+        stats.pending = inserter.pending().clone();
+    });
+
+    result
+}
+
+fn difference(a: &Quantities, b: &Quantities) -> Quantities {
+    Quantities {
+        bytes: a.bytes - b.bytes,
+        rows: a.rows - b.rows,
+        transactions: a.transactions.saturating_sub(b.transactions),
+    }
+}
+
+fn keep_error(first: &mut Option<Error>, result: Result<Quantities>) {
+    if let Err(err) = result {
+        first.get_or_insert(err);
+    }
+}
+
+fn update_stats(stats: &Mutex<BatchStats>, f: impl FnOnce(&mut BatchStats)) {
+    f(&mut stats.lock().unwrap());
+}
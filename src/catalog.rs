@@ -0,0 +1,327 @@
+//! Contains [`Catalog`] to introspect the server using `system.*` tables.
+//!
+//! Only columns that are present in all supported server versions are
+//! fetched, so the same structs can be used against different servers.
+//! Timestamps (`DateTime` columns) are returned as unix seconds.
+//!
+//! # Examples
+//! ```
+//! # async fn example() -> clickhouse::error::Result<()> {
+//! let client = clickhouse::Client::default();
+//! let catalog = client.catalog();
+//!
+//! for table in catalog.tables("default").await? {
+//!     println!("{}: {:?} rows", table.name, table.total_rows);
+//!     for partition in catalog.partitions(&format!("default.{}", table.name)).await? {
+//!         println!("  {}: {} bytes", partition.partition, partition.bytes_on_disk);
+//!     }
+//! }
+//! # Ok(()) }
+//! ```
+
+use serde::{de::DeserializeOwned, Deserialize};
+
+// XXX: need for `derive(Row)`. Provide `row(crate = ..)` instead.
+use crate as clickhouse;
+
+use crate::{error::Result, query::Query, Client, Row};
+
+/// Provides typed access to `system.*` tables, see [`Client::catalog()`].
+///
+/// Methods accepting a `table` expect either `db.table` or `table`,
+/// the latter is looked up in the current database.
+#[derive(Clone)]
+pub struct Catalog {
+    client: Client,
+}
+
+/// A row of `system.databases`.
+#[derive(Debug, Clone, PartialEq, Row, Deserialize)]
+#[non_exhaustive]
+pub struct Database {
+    pub name: String,
+    pub engine: String,
+    pub data_path: String,
+    pub metadata_path: String,
+}
+
+/// A row of `system.tables`.
+///
+/// Named so to not be confused with [`Table`](crate::table::Table).
+#[derive(Debug, Clone, PartialEq, Row, Deserialize)]
+#[non_exhaustive]
+pub struct TableInfo {
+    pub database: String,
+    pub name: String,
+    pub engine: String,
+    pub engine_full: String,
+    pub is_temporary: bool,
+    pub metadata_modification_time: u32,
+    pub create_table_query: String,
+    pub partition_key: String,
+    pub sorting_key: String,
+    pub primary_key: String,
+    pub sampling_key: String,
+    /// `None` if unknown, e.g. for views.
+    pub total_rows: Option<u64>,
+    /// `None` if unknown, e.g. for views.
+    pub total_bytes: Option<u64>,
+    pub comment: String,
+}
+
+/// A row of `system.columns`.
+#[derive(Debug, Clone, PartialEq, Row, Deserialize)]
+#[non_exhaustive]
+pub struct Column {
+    pub database: String,
+    pub table: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub data_type: String,
+    /// Starting from 1.
+    pub position: u64,
+    /// `DEFAULT`, `MATERIALIZED`, `ALIAS` or empty.
+    pub default_kind: String,
+    pub default_expression: String,
+    pub data_compressed_bytes: u64,
+    pub data_uncompressed_bytes: u64,
+    pub comment: String,
+    pub is_in_partition_key: bool,
+    pub is_in_sorting_key: bool,
+    pub is_in_primary_key: bool,
+    pub is_in_sampling_key: bool,
+    pub compression_codec: String,
+}
+
+/// Active parts of `system.parts` aggregated by partition.
+#[derive(Debug, Clone, PartialEq, Row, Deserialize)]
+#[non_exhaustive]
+pub struct Partition {
+    pub database: String,
+    pub table: String,
+    /// The partition expression, e.g. `202401` or `('a', 1)`.
+    pub partition: String,
+    pub partition_id: String,
+    /// The number of active parts.
+    pub parts: u64,
+    pub rows: u64,
+    pub bytes_on_disk: u64,
+    pub data_compressed_bytes: u64,
+    pub data_uncompressed_bytes: u64,
+    /// The latest modification time of its parts.
+    pub modification_time: u32,
+}
+
+/// A row of `system.parts`.
+#[derive(Debug, Clone, PartialEq, Row, Deserialize)]
+#[non_exhaustive]
+pub struct Part {
+    pub database: String,
+    pub table: String,
+    pub partition: String,
+    pub partition_id: String,
+    pub name: String,
+    pub active: bool,
+    pub marks: u64,
+    pub rows: u64,
+    pub bytes_on_disk: u64,
+    pub data_compressed_bytes: u64,
+    pub data_uncompressed_bytes: u64,
+    pub modification_time: u32,
+    pub min_block_number: i64,
+    pub max_block_number: i64,
+    pub level: u32,
+    pub disk_name: String,
+    pub path: String,
+}
+
+/// A row of `system.mutations`.
+#[derive(Debug, Clone, PartialEq, Row, Deserialize)]
+#[non_exhaustive]
+pub struct Mutation {
+    pub database: String,
+    pub table: String,
+    pub mutation_id: String,
+    pub command: String,
+    pub create_time: u32,
+    pub parts_to_do: i64,
+    pub is_done: bool,
+    pub latest_failed_part: String,
+    pub latest_fail_time: u32,
+    pub latest_fail_reason: String,
+}
+
+/// A row of `system.replicas`.
+#[derive(Debug, Clone, PartialEq, Row, Deserialize)]
+#[non_exhaustive]
+pub struct Replica {
+    pub database: String,
+    pub table: String,
+    pub engine: String,
+    pub is_leader: bool,
+    pub is_readonly: bool,
+    pub is_session_expired: bool,
+    pub future_parts: u32,
+    pub parts_to_check: u32,
+    pub zookeeper_path: String,
+    pub replica_name: String,
+    pub replica_path: String,
+    pub queue_size: u32,
+    pub inserts_in_queue: u32,
+    pub merges_in_queue: u32,
+    pub log_max_index: u64,
+    pub log_pointer: u64,
+    pub absolute_delay: u64,
+    pub total_replicas: u8,
+    pub active_replicas: u8,
+}
+
+/// A row of `system.processes`.
+#[derive(Debug, Clone, PartialEq, Row, Deserialize)]
+#[non_exhaustive]
+pub struct Process {
+    pub is_initial_query: bool,
+    pub user: String,
+    pub query_id: String,
+    pub initial_query_id: String,
+    /// In seconds.
+    pub elapsed: f64,
+    pub read_rows: u64,
+    pub read_bytes: u64,
+    pub total_rows_approx: u64,
+    pub written_rows: u64,
+    pub written_bytes: u64,
+    pub memory_usage: i64,
+    pub peak_memory_usage: i64,
+    pub query: String,
+}
+
+impl Catalog {
+    pub(crate) fn new(client: &Client) -> Self {
+        Self {
+            client: client.clone(),
+        }
+    }
+
+    /// Returns all databases ordered by name.
+    pub async fn databases(&self) -> Result<Vec<Database>> {
+        self.client
+            .query("SELECT ?fields FROM system.databases ORDER BY name")
+            .fetch_all()
+            .await
+    }
+
+    /// Returns tables of the database ordered by name.
+    pub async fn tables(&self, database: &str) -> Result<Vec<TableInfo>> {
+        self.client
+            .query("SELECT ?fields FROM system.tables WHERE database = ? ORDER BY name")
+            .bind(database)
+            .fetch_all()
+            .await
+    }
+
+    /// Returns columns of the table in order of their positions.
+    pub async fn columns(&self, database: &str, table: &str) -> Result<Vec<Column>> {
+        self.client
+            .query(
+                "SELECT ?fields FROM system.columns
+                 WHERE database = ? AND table = ?
+                 ORDER BY position",
+            )
+            .bind(database)
+            .bind(table)
+            .fetch_all()
+            .await
+    }
+
+    /// Returns partitions of the table with at least one active part.
+    pub async fn partitions(&self, table: &str) -> Result<Vec<Partition>> {
+        self.fetch_by_table(
+            "SELECT ?fields FROM (
+                SELECT
+                    database, table, partition, partition_id,
+                    count() AS parts,
+                    sum(rows) AS rows,
+                    sum(bytes_on_disk) AS bytes_on_disk,
+                    sum(data_compressed_bytes) AS data_compressed_bytes,
+                    sum(data_uncompressed_bytes) AS data_uncompressed_bytes,
+                    toUInt32(max(modification_time)) AS modification_time
+                FROM system.parts
+                WHERE active AND ?table_filter
+                GROUP BY database, table, partition, partition_id
+            )
+            ORDER BY partition_id",
+            table,
+        )
+        .await
+    }
+
+    /// Returns active parts of the table.
+    pub async fn parts(&self, table: &str) -> Result<Vec<Part>> {
+        self.fetch_by_table(
+            "SELECT ?fields FROM system.parts
+             WHERE active AND ?table_filter
+             ORDER BY partition_id, min_block_number",
+            table,
+        )
+        .await
+    }
+
+    /// Returns mutations of the table, including finished ones.
+    pub async fn mutations(&self, table: &str) -> Result<Vec<Mutation>> {
+        self.fetch_by_table(
+            "SELECT ?fields FROM system.mutations
+             WHERE ?table_filter
+             ORDER BY create_time, mutation_id",
+            table,
+        )
+        .await
+    }
+
+    /// Returns all replicated tables of the server.
+    pub async fn replicas(&self) -> Result<Vec<Replica>> {
+        self.client
+            .query("SELECT ?fields FROM system.replicas ORDER BY database, table")
+            .fetch_all()
+            .await
+    }
+
+    /// Returns queries that are being executed, including this one.
+    pub async fn processes(&self) -> Result<Vec<Process>> {
+        self.client
+            .query("SELECT ?fields FROM system.processes ORDER BY elapsed DESC")
+            .fetch_all()
+            .await
+    }
+
+    async fn fetch_by_table<T>(&self, template: &str, table: &str) -> Result<Vec<T>>
+    where
+        T: Row + DeserializeOwned,
+    {
+        let (database, table) = split_table(table);
+        let sql = template.replace(
+            "?table_filter",
+            "database = coalesce(?, currentDatabase()) AND table = ?",
+        );
+
+        Query::new(&self.client, &sql)
+            .bind(database)
+            .bind(table)
+            .fetch_all()
+            .await
+    }
+}
+
+// `db.table` -> `(Some("db"), "table")`.
+pub(crate) fn split_table(table: &str) -> (Option<&str>, &str) {
+    match table.split_once('.') {
+        Some((database, table)) => (Some(database), table),
+        None => (None, table),
+    }
+}
+
+#[test]
+fn it_splits_table() {
+    assert_eq!(split_table("t"), (None, "t"));
+    assert_eq!(split_table("db.t"), (Some("db"), "t"));
+}
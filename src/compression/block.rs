@@ -0,0 +1,281 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use cityhash_rs::cityhash_102_128;
+use futures::{ready, stream::Stream};
+
+use crate::{
+    bytes_ext::BytesExt,
+    error::{Error, Result},
+    response::Chunk,
+};
+
+/// Decodes blocks of the native compression format, every block can be
+/// compressed by any supported method.
+pub(crate) struct BlockDecoder<S> {
+    stream: S,
+    bytes: BytesExt,
+    meta: Option<Meta>,
+    max_block_size: usize,
+}
+
+impl<S> Stream for BlockDecoder<S>
+where
+    S: Stream<Item = Result<Bytes>> + Unpin,
+{
+    type Item = Result<Chunk>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let meta = loop {
+            let size = self.bytes.remaining();
+            let required_size = self.meta.as_ref().map_or(META_SIZE, Meta::total_size);
+
+            if size < required_size {
+                let stream = Pin::new(&mut self.stream);
+                match ready!(stream.poll_next(cx)) {
+                    Some(Ok(chunk)) => {
+                        self.bytes.extend(chunk);
+                        continue;
+                    }
+                    Some(Err(err)) => return Some(Err(err)).into(),
+                    None if size > 0 => {
+                        let err = Error::Decompression("malformed data".into());
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                    None => return Poll::Ready(None),
+                }
+            }
+
+            debug_assert!(size >= required_size);
+
+            match self.meta.take() {
+                Some(meta) => break meta,
+                None => self.meta = Some(self.read_meta()?),
+            };
+        };
+
+        let data = self.read_data(&meta)?;
+        let net_size = meta.total_size();
+        self.bytes.advance(net_size);
+
+        Poll::Ready(Some(Ok(Chunk { data, net_size })))
+    }
+}
+
+// Meta = checksum + header
+// - [16b] checksum
+// - [ 1b] method (0x82 for LZ4, 0x90 for ZSTD)
+// - [ 4b] compressed size (data + header)
+// - [ 4b] uncompressed size
+const CHECKSUM_SIZE: usize = 16;
+const HEADER_SIZE: usize = 9;
+const META_SIZE: usize = CHECKSUM_SIZE + HEADER_SIZE;
+
+pub(crate) const LZ4_METHOD: u8 = 0x82;
+pub(crate) const ZSTD_METHOD: u8 = 0x90;
+
+struct Meta {
+    checksum: u128,
+    method: u8,
+    compressed_size: u32,
+    uncompressed_size: u32,
+}
+
+impl Meta {
+    fn total_size(&self) -> usize {
+        CHECKSUM_SIZE + self.compressed_size as usize
+    }
+
+    fn read(mut bytes: &[u8], max_block_size: usize) -> Result<Meta> {
+        let checksum = bytes.get_u128_le();
+        let method = bytes.get_u8();
+        let compressed_size = bytes.get_u32_le();
+        let uncompressed_size = bytes.get_u32_le();
+
+        if !is_supported(method) {
+            return Err(Error::Decompression("incorrect magic number".into()));
+        }
+
+        // Checked before the compressed data is buffered. Incompressible data
+        // is slightly larger after compression, so some overhead is allowed.
+        let max_compressed_size = max_block_size.saturating_add(max_block_size / 128 + 64);
+        if uncompressed_size as usize > max_block_size
+            || compressed_size as usize > max_compressed_size
+        {
+            return Err(Error::LimitExceeded(format!(
+                "decompressed block is larger than {max_block_size} bytes"
+            )));
+        }
+
+        if (compressed_size as usize) < HEADER_SIZE {
+            return Err(Error::Decompression("malformed data".into()));
+        }
+
+        Ok(Meta {
+            checksum,
+            method,
+            compressed_size,
+            uncompressed_size,
+        })
+    }
+
+    fn write_checksum(&self, mut buffer: &mut [u8]) {
+        buffer.put_u128_le(self.checksum);
+    }
+
+    fn write_header(&self, mut buffer: &mut [u8]) {
+        buffer.put_u8(self.method);
+        buffer.put_u32_le(self.compressed_size);
+        buffer.put_u32_le(self.uncompressed_size);
+    }
+}
+
+fn is_supported(method: u8) -> bool {
+    (cfg!(feature = "lz4") && method == LZ4_METHOD)
+        || (cfg!(feature = "zstd") && method == ZSTD_METHOD)
+}
+
+impl<S> BlockDecoder<S> {
+    pub(crate) fn new(stream: S, max_block_size: usize) -> Self {
+        Self {
+            stream,
+            bytes: BytesExt::default(),
+            meta: None,
+            max_block_size,
+        }
+    }
+
+    fn read_meta(&mut self) -> Result<Meta> {
+        Meta::read(self.bytes.slice(), self.max_block_size)
+    }
+
+    fn read_data(&mut self, meta: &Meta) -> Result<Bytes> {
+        let total_size = meta.total_size();
+        let bytes = &self.bytes.slice()[..total_size];
+
+        let actual_checksum = calc_checksum(&bytes[CHECKSUM_SIZE..]);
+        if actual_checksum != meta.checksum {
+            return Err(Error::Decompression("checksum mismatch".into()));
+        }
+
+        let compressed = &bytes[META_SIZE..];
+        let size = meta.uncompressed_size as usize;
+        let uncompressed = match meta.method {
+            #[cfg(feature = "lz4")]
+            LZ4_METHOD => super::lz4::decompress(compressed, size)?,
+            #[cfg(feature = "zstd")]
+            ZSTD_METHOD => super::zstd::decompress(compressed, size)?,
+            _ => unreachable!("checked in `Meta::read()`"),
+        };
+
+        if uncompressed.len() != size {
+            return Err(Error::Decompression("uncompressed size mismatch".into()));
+        }
+
+        Ok(uncompressed.into())
+    }
+}
+
+fn calc_checksum(buffer: &[u8]) -> u128 {
+    let hash = cityhash_102_128(buffer);
+    hash.rotate_right(64)
+}
+
+/// Compresses `uncompressed` into one block.
+///
+/// `compress_into` writes to the provided buffer of `max_compressed_size`
+/// bytes and returns the number of written bytes.
+pub(crate) fn compress(
+    method: u8,
+    uncompressed: &[u8],
+    max_compressed_size: usize,
+    compress_into: impl FnOnce(&[u8], &mut [u8]) -> Result<usize>,
+) -> Result<Bytes> {
+    let mut buffer = BytesMut::new();
+    buffer.resize(META_SIZE + max_compressed_size, 0);
+
+    let compressed_data_size = compress_into(uncompressed, &mut buffer[META_SIZE..])?;
+
+    buffer.truncate(META_SIZE + compressed_data_size);
+
+    let mut meta = Meta {
+        checksum: 0, // will be calculated below.
+        method,
+        compressed_size: (HEADER_SIZE + compressed_data_size) as u32,
+        uncompressed_size: uncompressed.len() as u32,
+    };
+
+    meta.write_header(&mut buffer[CHECKSUM_SIZE..]);
+    meta.checksum = calc_checksum(&buffer[CHECKSUM_SIZE..]);
+    meta.write_checksum(&mut buffer[..]);
+
+    Ok(buffer.freeze())
+}
+
+#[cfg(feature = "lz4")]
+#[tokio::test]
+async fn it_decompresses() {
+    use futures::stream::{self, TryStreamExt};
+
+    let expected = vec![
+        1u8, 0, 2, 255, 255, 255, 255, 0, 1, 1, 1, 115, 6, 83, 116, 114, 105, 110, 103, 3, 97, 98,
+        99,
+    ];
+
+    let source = vec![
+        245_u8, 5, 222, 235, 225, 158, 59, 108, 225, 31, 65, 215, 66, 66, 36, 92,   // checksum
+        0x82, // magic number
+        34, 0, 0, 0, // compressed size (data + header)
+        23, 0, 0, 0, // uncompressed size
+        240, 8, 1, 0, 2, 255, 255, 255, 255, 0, 1, 1, 1, 115, 6, 83, 116, 114, 105, 110, 103, 3,
+        97, 98, 99,
+    ];
+
+    async fn test(chunks: &[&[u8]], expected: &[u8]) {
+        let stream = stream::iter(
+            chunks
+                .iter()
+                .map(|s| Bytes::copy_from_slice(s))
+                .map(Ok::<_, Error>)
+                .collect::<Vec<_>>(),
+        );
+        let mut decoder = BlockDecoder::new(stream, usize::MAX);
+        let actual = decoder.try_next().await.unwrap().unwrap();
+        assert_eq!(actual.data, expected);
+        assert_eq!(
+            actual.net_size,
+            chunks.iter().map(|s| s.len()).sum::<usize>()
+        );
+    }
+
+    // 1 chunk.
+    test(&[&source], &expected).await;
+
+    // 2 chunks.
+    for i in 0..source.len() {
+        let (left, right) = source.split_at(i);
+        test(&[left, right], &expected).await;
+
+        // 3 chunks.
+        for j in i..source.len() {
+            let (right_a, right_b) = right.split_at(j - i);
+            test(&[left, right_a, right_b], &expected).await;
+        }
+    }
+}
+
+#[cfg(feature = "lz4")]
+#[tokio::test]
+async fn it_checks_block_size() {
+    use futures::stream::{self, TryStreamExt};
+
+    let block = super::lz4::compress(&[0; 1024]).unwrap();
+    let stream = stream::iter(vec![Ok::<_, Error>(block)]);
+
+    let mut decoder = BlockDecoder::new(stream, 1023);
+    let result = decoder.try_next().await;
+    assert!(matches!(result, Err(Error::LimitExceeded(_))));
+}
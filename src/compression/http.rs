@@ -0,0 +1,320 @@
+// Without any HTTP compression features, `HttpCompression` has no variants.
+#![cfg_attr(
+    not(any(feature = "gzip", feature = "brotli", feature = "zstd")),
+    allow(unreachable_code, unused_variables, unused_mut, dead_code)
+)]
+
+use std::{
+    io::{self, Write},
+    mem,
+};
+
+use bytes::Bytes;
+
+use crate::error::{Error, Result};
+
+/// Compression of HTTP bodies, an alternative to [`Compression`].
+///
+/// Requests are compressed according to `Content-Encoding` and responses
+/// according to `Accept-Encoding`. Responses are compressed by the server
+/// only if [`enable_http_compression`] is set, it's done automatically.
+///
+/// Useful if a proxy between the client and the server doesn't support
+/// the native format or to get better compression ratios.
+///
+/// [`Compression`]: crate::Compression
+/// [`enable_http_compression`]: https://clickhouse.com/docs/en/operations/settings/settings#enable_http_compression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HttpCompression {
+    /// Uses `gzip` with the provided level, `[0, 9]`.
+    #[cfg(feature = "gzip")]
+    Gzip(u32),
+    /// Uses `br` (Brotli) with the provided level, `[0, 11]`.
+    #[cfg(feature = "brotli")]
+    Brotli(u32),
+    /// Uses `zstd` with the provided level, `[1, 22]`.
+    #[cfg(feature = "zstd")]
+    Zstd(i32),
+}
+
+impl HttpCompression {
+    /// Returns a value for `Content-Encoding` and `Accept-Encoding` headers.
+    pub(crate) fn encoding(&self) -> &'static str {
+        match *self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip(_) => "gzip",
+            #[cfg(feature = "brotli")]
+            Self::Brotli(_) => "br",
+            #[cfg(feature = "zstd")]
+            Self::Zstd(_) => "zstd",
+        }
+    }
+}
+
+// === Encoder ===
+
+/// Compresses a request body chunk by chunk.
+pub(crate) enum Encoder {
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<Vec<u8>>),
+    #[cfg(feature = "brotli")]
+    Brotli(Box<brotli::CompressorWriter<Vec<u8>>>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::write::Encoder<'static, Vec<u8>>),
+}
+
+impl Encoder {
+    pub(crate) fn new(compression: HttpCompression) -> Result<Self> {
+        let encoder = match compression {
+            #[cfg(feature = "gzip")]
+            HttpCompression::Gzip(level) => Self::Gzip(flate2::write::GzEncoder::new(
+                Vec::new(),
+                flate2::Compression::new(level),
+            )),
+            #[cfg(feature = "brotli")]
+            HttpCompression::Brotli(level) => Self::Brotli(Box::new(
+                brotli::CompressorWriter::new(Vec::new(), 4096, level, 22),
+            )),
+            #[cfg(feature = "zstd")]
+            HttpCompression::Zstd(level) => Self::Zstd(
+                zstd::stream::write::Encoder::new(Vec::new(), level)
+                    .map_err(|err| Error::Compression(err.into()))?,
+            ),
+        };
+
+        Ok(encoder)
+    }
+
+    /// Compresses the chunk and returns all compressed data so far.
+    ///
+    /// The encoder is flushed, so the server can handle rows without waiting
+    /// for next chunks.
+    pub(crate) fn encode(&mut self, chunk: &[u8]) -> Result<Bytes> {
+        let result: io::Result<Bytes> = match *self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip(ref mut encoder) => encode(encoder, chunk).map(|_| take(encoder.get_mut())),
+            #[cfg(feature = "brotli")]
+            Self::Brotli(ref mut encoder) => {
+                encode(&mut **encoder, chunk).map(|_| take(encoder.get_mut()))
+            }
+            #[cfg(feature = "zstd")]
+            Self::Zstd(ref mut encoder) => encode(encoder, chunk).map(|_| take(encoder.get_mut())),
+        };
+
+        result.map_err(|err| Error::Compression(err.into()))
+    }
+
+    /// Finishes the stream and returns the rest of compressed data.
+    pub(crate) fn finish(self) -> Result<Bytes> {
+        let result: io::Result<Vec<u8>> = match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip(encoder) => encoder.finish(),
+            #[cfg(feature = "brotli")]
+            Self::Brotli(encoder) => Ok(encoder.into_inner()),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(encoder) => encoder.finish(),
+        };
+
+        result
+            .map(Bytes::from)
+            .map_err(|err| Error::Compression(err.into()))
+    }
+}
+
+fn encode(encoder: &mut impl Write, chunk: &[u8]) -> io::Result<()> {
+    encoder.write_all(chunk)?;
+    encoder.flush()
+}
+
+fn take(buffer: &mut Vec<u8>) -> Bytes {
+    mem::take(buffer).into()
+}
+
+// === Decoder ===
+
+/// Decompresses a response body chunk by chunk.
+pub(crate) enum Decoder {
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzDecoder<LimitedBuffer>),
+    #[cfg(feature = "brotli")]
+    Brotli(Box<brotli::DecompressorWriter<LimitedBuffer>>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::write::Decoder<'static, LimitedBuffer>),
+}
+
+impl Decoder {
+    /// Creates a decoder for the provided `Content-Encoding`, which fails if
+    /// one chunk is decompressed into more than `max_size` bytes.
+    ///
+    /// Returns `None` if the encoding is unsupported or disabled.
+    pub(crate) fn new(encoding: &str, max_size: usize) -> Option<Self> {
+        let buffer = LimitedBuffer::new(max_size);
+
+        match encoding.trim() {
+            #[cfg(feature = "gzip")]
+            "gzip" => Some(Self::Gzip(flate2::write::GzDecoder::new(buffer))),
+            #[cfg(feature = "brotli")]
+            "br" => Some(Self::Brotli(Box::new(brotli::DecompressorWriter::new(
+                buffer, 4096,
+            )))),
+            #[cfg(feature = "zstd")]
+            "zstd" => zstd::stream::write::Decoder::new(buffer)
+                .ok()
+                .map(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Decompresses the chunk and returns all decompressed data so far.
+    pub(crate) fn decode(&mut self, chunk: &[u8]) -> Result<Bytes> {
+        let result: io::Result<Bytes> = match *self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip(ref mut decoder) => {
+                encode(decoder, chunk).map(|_| take(&mut decoder.get_mut().data))
+            }
+            #[cfg(feature = "brotli")]
+            Self::Brotli(ref mut decoder) => {
+                encode(&mut **decoder, chunk).map(|_| take(&mut decoder.get_mut().data))
+            }
+            #[cfg(feature = "zstd")]
+            Self::Zstd(ref mut decoder) => {
+                encode(decoder, chunk).map(|_| take(&mut decoder.get_mut().data))
+            }
+        };
+
+        result.map_err(decompression_error)
+    }
+
+    /// Checks that the stream is complete and returns the rest of data.
+    pub(crate) fn finish(self) -> Result<Bytes> {
+        let result: io::Result<Vec<u8>> = match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip(decoder) => decoder.finish().map(|buffer| buffer.data),
+            #[cfg(feature = "brotli")]
+            Self::Brotli(decoder) => decoder
+                .into_inner()
+                .map(|buffer| buffer.data)
+                .map_err(|_| io::Error::new(io::ErrorKind::UnexpectedEof, "incomplete stream")),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(mut decoder) => decoder
+                .flush()
+                .map(|_| mem::take(&mut decoder.get_mut().data)),
+        };
+
+        result.map(Bytes::from).map_err(decompression_error)
+    }
+}
+
+// Keeps `Error::LimitExceeded` returned by `LimitedBuffer`.
+fn decompression_error(err: io::Error) -> Error {
+    if err.get_ref().is_some_and(|r| r.is::<Error>()) {
+        err.into()
+    } else {
+        Error::Decompression(err.into())
+    }
+}
+
+/// A buffer for decompressed data that cannot grow over the limit.
+///
+/// Checked on writes, so a small malicious chunk cannot be decompressed into
+/// gigabytes of data before the check.
+pub(crate) struct LimitedBuffer {
+    data: Vec<u8>,
+    limit: usize,
+}
+
+impl LimitedBuffer {
+    fn new(limit: usize) -> Self {
+        Self {
+            data: Vec::new(),
+            limit,
+        }
+    }
+}
+
+impl Write for LimitedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.data.len().saturating_add(buf.len()) > self.limit {
+            let err = Error::LimitExceeded(format!(
+                "decompressed block is larger than {} bytes",
+                self.limit
+            ));
+            return Err(err.into());
+        }
+
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(any(feature = "gzip", feature = "brotli", feature = "zstd"))]
+    fn roundtrip(compression: HttpCompression) {
+        let source = b"ClickHouse ClickHouse ClickHouse ClickHouse".repeat(100);
+
+        let mut encoder = Encoder::new(compression).unwrap();
+        let mut compressed = Vec::new();
+        for chunk in source.chunks(1000) {
+            compressed.extend_from_slice(&encoder.encode(chunk).unwrap());
+        }
+        compressed.extend_from_slice(&encoder.finish().unwrap());
+        assert!(compressed.len() < source.len());
+
+        for split in [1, 7, compressed.len()] {
+            let mut decoder = Decoder::new(compression.encoding(), usize::MAX).unwrap();
+            let mut actual = Vec::new();
+            for chunk in compressed.chunks(split) {
+                actual.extend_from_slice(&decoder.decode(chunk).unwrap());
+            }
+            actual.extend_from_slice(&decoder.finish().unwrap());
+            assert_eq!(actual, source);
+        }
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzip() {
+        roundtrip(HttpCompression::Gzip(6));
+    }
+
+    #[cfg(feature = "brotli")]
+    #[test]
+    fn brotli() {
+        roundtrip(HttpCompression::Brotli(6));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd() {
+        roundtrip(HttpCompression::Zstd(3));
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn limit_exceeded() {
+        let source = vec![0u8; 1024 * 1024];
+
+        let mut encoder = Encoder::new(HttpCompression::Gzip(6)).unwrap();
+        let mut compressed = encoder.encode(&source).unwrap().to_vec();
+        compressed.extend_from_slice(&encoder.finish().unwrap());
+        assert!(compressed.len() < 4096);
+
+        let mut decoder = Decoder::new("gzip", 64 * 1024).unwrap();
+        let err = decoder.decode(&compressed).unwrap_err();
+        assert!(matches!(err, Error::LimitExceeded(_)), "{err:?}");
+    }
+
+    #[test]
+    fn unknown() {
+        assert!(Decoder::new("identity", usize::MAX).is_none());
+        assert!(Decoder::new("compress", usize::MAX).is_none());
+    }
+}
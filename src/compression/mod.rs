@@ -1,5 +1,12 @@
+#[cfg(any(feature = "lz4", feature = "zstd"))]
+pub(crate) mod block;
+pub(crate) mod http;
 #[cfg(feature = "lz4")]
 pub(crate) mod lz4;
+#[cfg(feature = "zstd")]
+pub(crate) mod zstd;
+
+pub use self::http::HttpCompression;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[non_exhaustive]
@@ -22,6 +29,17 @@ pub enum Compression {
     #[cfg(feature = "lz4")]
     #[deprecated(note = "use `Compression::Lz4` instead")]
     Lz4Hc(i32),
+    /// Uses `ZSTD` codec with the provided level to (de)compress.
+    /// Provides better compression ratios than `LZ4` for the cost of CPU,
+    /// thus useful in networks with low bandwidth.
+    ///
+    /// The level is used both for `INSERT`s and for responses, see
+    /// [`network_zstd_compression_level`]. Possible levels: `[1, 22]`,
+    /// `0` means the default level (`3`).
+    ///
+    /// [`network_zstd_compression_level`]: https://clickhouse.com/docs/en/operations/settings/settings#network_zstd_compression_level
+    #[cfg(feature = "zstd")]
+    Zstd(i32),
 }
 
 impl Default for Compression {
@@ -43,7 +61,24 @@ impl Default for Compression {
 }
 
 impl Compression {
-    pub(crate) fn is_lz4(&self) -> bool {
+    /// Returns `true` if the native compression format is used.
+    pub(crate) fn is_native(&self) -> bool {
         *self != Compression::None
     }
 }
+
+/// Compresses `uncompressed` into one block of the native format.
+#[cfg(any(feature = "lz4", feature = "zstd"))]
+pub(crate) fn compress(
+    compression: Compression,
+    uncompressed: &[u8],
+) -> crate::error::Result<bytes::Bytes> {
+    match compression {
+        Compression::None => Ok(bytes::Bytes::copy_from_slice(uncompressed)),
+        #[cfg(feature = "lz4")]
+        #[allow(deprecated)]
+        Compression::Lz4 | Compression::Lz4Hc(_) => lz4::compress(uncompressed),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd(level) => zstd::compress(uncompressed, level),
+    }
+}
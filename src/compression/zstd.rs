@@ -0,0 +1,40 @@
+use bytes::Bytes;
+
+use super::block::{self, ZSTD_METHOD};
+use crate::error::{Error, Result};
+
+pub(crate) fn compress(uncompressed: &[u8], level: i32) -> Result<Bytes> {
+    let max_compressed_size = zstd::zstd_safe::compress_bound(uncompressed.len());
+
+    block::compress(
+        ZSTD_METHOD,
+        uncompressed,
+        max_compressed_size,
+        |src, dst| {
+            zstd::bulk::compress_to_buffer(src, dst, level)
+                .map_err(|err| Error::Compression(err.into()))
+        },
+    )
+}
+
+pub(super) fn decompress(compressed: &[u8], uncompressed_size: usize) -> Result<Vec<u8>> {
+    zstd::bulk::decompress(compressed, uncompressed_size)
+        .map_err(|err| Error::Decompression(err.into()))
+}
+
+#[tokio::test]
+async fn it_roundtrips() {
+    use futures::stream::{self, TryStreamExt};
+
+    let source = b"ClickHouse ClickHouse ClickHouse ClickHouse".repeat(10);
+    let compressed = compress(&source, 3).unwrap();
+    assert_eq!(compressed[16], ZSTD_METHOD);
+    assert!(compressed.len() < source.len());
+
+    let stream = stream::iter([Ok::<_, Error>(compressed.clone())]);
+    let mut decoder = block::BlockDecoder::new(stream, usize::MAX);
+    let actual = decoder.try_next().await.unwrap().unwrap();
+    assert_eq!(actual.data, source);
+    assert_eq!(actual.net_size, compressed.len());
+    assert!(decoder.try_next().await.unwrap().is_none());
+}
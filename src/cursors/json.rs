@@ -7,35 +7,46 @@ use crate::{
 use serde::Deserialize;
 use std::marker::PhantomData;
 
-pub(crate) struct JsonCursor<T> {
+/// A cursor that emits rows deserialized as structures from JSON.
+///
+/// Each row is expected to be a separate line of the response, as produced
+/// by the `JSONEachRow` and `JSONCompactEachRow` formats.
+///
+/// Unlike [`RowCursor`], the payload is human-readable and self-describing,
+/// which is useful for types that cannot be described in RowBinary easily
+/// and for investigating schema mismatches.
+///
+/// [`RowCursor`]: crate::query::RowCursor
+#[must_use]
+pub struct JsonCursor<T> {
     raw: RawCursor,
     bytes: BytesExt,
+    max_row_size: usize,
     line: String,
     _marker: PhantomData<T>,
 }
 
-// We use `JSONEachRowWithProgress` to avoid infinite HTTP connections.
-// See https://github.com/ClickHouse/ClickHouse/issues/22996 for details.
-#[derive(Deserialize)]
-#[serde(rename_all = "lowercase")]
-enum JsonRow<T> {
-    Row(T),
-    Progress {},
-}
-
 impl<T> JsonCursor<T> {
     const INITIAL_BUFFER_SIZE: usize = 1024;
 
-    pub(crate) fn new(response: Response) -> Self {
+    pub(crate) fn new(response: Response, max_row_size: usize) -> Self {
         Self {
             raw: RawCursor::new(response),
             bytes: BytesExt::default(),
+            max_row_size,
             line: String::with_capacity(Self::INITIAL_BUFFER_SIZE),
             _marker: PhantomData,
         }
     }
 
-    pub(crate) async fn next<'a, 'b: 'a>(&'a mut self) -> Result<Option<T>>
+    /// Emits the next row.
+    ///
+    /// The result is unspecified if it's called after `Err` is returned.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancellation safe.
+    pub async fn next<'a, 'b: 'a>(&'a mut self) -> Result<Option<T>>
     where
         T: Deserialize<'b>,
     {
@@ -51,19 +62,43 @@ impl<T> JsonCursor<T> {
             };
 
             if let Some(line) = self.line.strip_suffix('\n') {
+                super::check_row_size(read, self.max_row_size)?;
                 self.bytes.advance(read);
 
-                match serde_json::from_str(super::workaround_51132(line)) {
-                    Ok(JsonRow::Row(value)) => return Ok(Some(value)),
-                    Ok(JsonRow::Progress { .. }) => continue,
-                    Err(err) => return Err(Error::BadResponse(err.to_string())),
-                }
+                return match serde_json::from_str(super::workaround_51132(line)) {
+                    Ok(value) => Ok(Some(value)),
+                    Err(err) => Err(Error::BadResponse(err.to_string())),
+                };
             }
 
+            // All buffered data belongs to the incomplete line.
+            super::check_row_size(self.bytes.remaining(), self.max_row_size)?;
+
             match self.raw.next().await? {
                 Some(chunk) => self.bytes.extend(chunk),
+                None if self.bytes.remaining() > 0 => {
+                    // The last line isn't terminated, the response is truncated.
+                    return Err(Error::NotEnoughData);
+                }
                 None => return Ok(None),
             }
         }
     }
+
+    /// Returns the total size in bytes received from the CH server since
+    /// the cursor was created.
+    ///
+    /// This method counts only size without HTTP headers for now.
+    /// It can be changed in the future without notice.
+    #[inline]
+    pub fn received_bytes(&self) -> u64 {
+        self.raw.received_bytes()
+    }
+
+    /// Returns the total size in bytes decompressed since the cursor was
+    /// created.
+    #[inline]
+    pub fn decoded_bytes(&self) -> u64 {
+        self.raw.decoded_bytes()
+    }
 }
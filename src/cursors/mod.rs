@@ -1,14 +1,26 @@
-#[cfg(feature = "watch")]
-pub(crate) use self::json::JsonCursor;
+#[cfg(feature = "json")]
+pub use self::json::JsonCursor;
 pub(crate) use self::raw::RawCursor;
 pub use self::{bytes::BytesCursor, row::RowCursor};
 
 mod bytes;
-#[cfg(feature = "watch")]
+#[cfg(feature = "json")]
 mod json;
 mod raw;
 mod row;
 
+use crate::error::{Error, Result};
+
+/// Fails if a row (complete or being buffered) is larger than the limit.
+fn check_row_size(size: usize, max_row_size: usize) -> Result<()> {
+    if size > max_row_size {
+        let msg = format!("row is larger than {max_row_size} bytes");
+        return Err(Error::LimitExceeded(msg));
+    }
+
+    Ok(())
+}
+
 // XXX: it was a workaround for https://github.com/rust-lang/rust/issues/51132,
 //      but introduced #24 and must be fixed.
 fn workaround_51132<'a, T: ?Sized>(ptr: &T) -> &'a T {
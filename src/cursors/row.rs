@@ -13,14 +13,16 @@ use std::marker::PhantomData;
 pub struct RowCursor<T> {
     raw: RawCursor,
     bytes: BytesExt,
+    max_row_size: usize,
     _marker: PhantomData<T>,
 }
 
 impl<T> RowCursor<T> {
-    pub(crate) fn new(response: Response) -> Self {
+    pub(crate) fn new(response: Response, max_row_size: usize) -> Self {
         Self {
             raw: RawCursor::new(response),
             bytes: BytesExt::default(),
+            max_row_size,
             _marker: PhantomData,
         }
     }
@@ -41,6 +43,7 @@ impl<T> RowCursor<T> {
 
             match rowbinary::deserialize_from(&mut slice) {
                 Ok(value) => {
+                    super::check_row_size(self.bytes.remaining() - slice.len(), self.max_row_size)?;
                     self.bytes.set_remaining(slice.len());
                     return Ok(Some(value));
                 }
@@ -48,6 +51,9 @@ impl<T> RowCursor<T> {
                 Err(err) => return Err(err),
             }
 
+            // All buffered data belongs to the incomplete row.
+            super::check_row_size(self.bytes.remaining(), self.max_row_size)?;
+
             match self.raw.next().await? {
                 Some(chunk) => self.bytes.extend(chunk),
                 None if self.bytes.remaining() > 0 => {
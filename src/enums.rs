@@ -0,0 +1,213 @@
+use std::{fmt, marker::PhantomData};
+
+use serde::{
+    de::{self, Unexpected, Visitor},
+    ser::Error as _,
+    Deserializer, Serializer,
+};
+
+/// A Rust enum mapped to the `Enum8` or `Enum16` data type.
+///
+/// Use `#[derive(ClickHouseEnum)]` to implement it along with `Serialize`
+/// and `Deserialize`, which encode variants as their values in RowBinary and
+/// as their names in human-readable formats (e.g. in [`Query::bind`]).
+///
+/// ```
+/// use clickhouse::ClickHouseEnum;
+///
+/// #[derive(Debug, PartialEq, ClickHouseEnum)]
+/// enum Level {
+///     #[ch(value = 1, name = "debug")]
+///     Debug,
+///     #[ch(value = 2, name = "info")]
+///     Info,
+///     // Values that are unknown at compile time, e.g. added later to the table.
+///     Unknown(i16),
+/// }
+///
+/// assert_eq!(Level::DATA_TYPE, "Enum8('debug' = 1, 'info' = 2)");
+/// assert_eq!(Level::from_value(2), Some(Level::Info));
+/// assert_eq!(Level::from_value(3), Some(Level::Unknown(3)));
+/// ```
+///
+/// Attributes of variants:
+/// * `#[ch(value = N)]` sets the value, otherwise the discriminant (if the
+///   enum has only unit variants) or the previous value plus one (starting
+///   with `1`) is used.
+/// * `#[ch(name = "...")]` sets the name, otherwise the variant name is used.
+///
+/// At most one variant can have a single `i16` field, it catches all
+/// unknown values. Without it, unknown values lead to an error.
+///
+/// `Enum8` is used if all values fit in `i8`, otherwise `Enum16`.
+/// Use `#[ch(enum16)]` on the enum to force `Enum16`.
+///
+/// [`Query::bind`]: crate::query::Query::bind
+pub trait ClickHouseEnum: Sized {
+    /// The data type, e.g. `Enum8('a' = 1, 'b' = 2)`. Useful to generate DDL.
+    const DATA_TYPE: &'static str;
+
+    /// Names and values of all known variants.
+    const VARIANTS: &'static [(&'static str, i16)];
+
+    /// Returns the value of the variant.
+    fn to_value(&self) -> i16;
+
+    /// Returns the variant for the value, or `None` if the value is unknown
+    /// and there is no catch-all variant.
+    fn from_value(value: i16) -> Option<Self>;
+
+    /// Returns the name of the variant, or `None` for unknown values.
+    fn name(&self) -> Option<&'static str> {
+        let value = self.to_value();
+        Self::VARIANTS
+            .iter()
+            .find(|(_, v)| *v == value)
+            .map(|(name, _)| *name)
+    }
+
+    /// Returns the variant with the name, if any.
+    fn from_name(name: &str) -> Option<Self> {
+        Self::VARIANTS
+            .iter()
+            .find(|(n, _)| *n == name)
+            .and_then(|(_, value)| Self::from_value(*value))
+    }
+}
+
+fn is_enum16<T: ClickHouseEnum>() -> bool {
+    T::DATA_TYPE.starts_with("Enum16")
+}
+
+pub fn serialize<T: ClickHouseEnum, S: Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let raw = value.to_value();
+
+    if serializer.is_human_readable() {
+        return match value.name() {
+            Some(name) => serializer.serialize_str(name),
+            None => serializer.serialize_i16(raw),
+        };
+    }
+
+    if is_enum16::<T>() {
+        serializer.serialize_i16(raw)
+    } else {
+        let raw = i8::try_from(raw)
+            .map_err(|_| S::Error::custom(format!("{raw} is out of range of {}", T::DATA_TYPE)))?;
+        serializer.serialize_i8(raw)
+    }
+}
+
+pub fn deserialize<'de, T: ClickHouseEnum, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<T, D::Error> {
+    let visitor = EnumVisitor(PhantomData);
+
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_any(visitor)
+    } else if is_enum16::<T>() {
+        deserializer.deserialize_i16(visitor)
+    } else {
+        deserializer.deserialize_i8(visitor)
+    }
+}
+
+struct EnumVisitor<T>(PhantomData<T>);
+
+impl<T: ClickHouseEnum> Visitor<'_> for EnumVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a value of {}", T::DATA_TYPE)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<T, E> {
+        i16::try_from(v)
+            .ok()
+            .and_then(T::from_value)
+            .ok_or_else(|| E::invalid_value(Unexpected::Signed(v), &self))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<T, E> {
+        i16::try_from(v)
+            .ok()
+            .and_then(T::from_value)
+            .ok_or_else(|| E::invalid_value(Unexpected::Unsigned(v), &self))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+        T::from_name(v).ok_or_else(|| E::invalid_value(Unexpected::Str(v), &self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::Error, rowbinary};
+
+    #[derive(Debug, PartialEq)]
+    enum Small {
+        A,
+        B,
+    }
+
+    impl ClickHouseEnum for Small {
+        const DATA_TYPE: &'static str = "Enum8('a' = -1, 'b' = 5)";
+        const VARIANTS: &'static [(&'static str, i16)] = &[("a", -1), ("b", 5)];
+
+        fn to_value(&self) -> i16 {
+            match self {
+                Self::A => -1,
+                Self::B => 5,
+            }
+        }
+
+        fn from_value(value: i16) -> Option<Self> {
+            match value {
+                -1 => Some(Self::A),
+                5 => Some(Self::B),
+                _ => None,
+            }
+        }
+    }
+
+    impl serde::Serialize for Small {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serialize(self, serializer)
+        }
+    }
+
+    impl<'de> serde::Deserialize<'de> for Small {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserialize(deserializer)
+        }
+    }
+
+    #[test]
+    fn it_encodes_values() {
+        let mut buffer = Vec::new();
+        rowbinary::serialize_into(&mut buffer, &(Small::A, Small::B)).unwrap();
+        assert_eq!(buffer, [0xff, 0x05]);
+
+        let actual: (Small, Small) = rowbinary::deserialize_from(&mut &buffer[..]).unwrap();
+        assert_eq!(actual, (Small::A, Small::B));
+    }
+
+    #[test]
+    fn it_rejects_unknown_values() {
+        let err = rowbinary::deserialize_from::<Small>(&mut &[0x02][..]).unwrap_err();
+        assert!(matches!(&err, Error::Custom(msg) if msg.contains("Enum8('a' = -1")));
+    }
+
+    #[test]
+    fn it_binds_names() {
+        use crate::sql::Bind;
+
+        let mut out = String::new();
+        Small::B.write(&mut out).unwrap();
+        assert_eq!(out, "'b'");
+    }
+}
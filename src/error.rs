@@ -44,6 +44,12 @@ pub enum Error {
     TimedOut,
     #[error("unsupported: {0}")]
     Unsupported(String),
+    #[error("spool error: {0}")]
+    Spool(#[source] io::Error),
+    #[error("limit exceeded: {0}")]
+    LimitExceeded(String),
+    #[error("batcher is closed")]
+    BatcherClosed,
     #[error("{0}")]
     Other(BoxedError),
 }
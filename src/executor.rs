@@ -0,0 +1,288 @@
+//! Contains [`Executor`] controlling where background tasks run.
+
+use std::{
+    any::Any,
+    collections::HashMap,
+    fmt,
+    future::Future,
+    panic::AssertUnwindSafe,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+};
+
+use futures::{
+    channel::oneshot,
+    future::{AbortHandle, Abortable},
+    FutureExt,
+};
+use tokio::sync::Notify;
+
+/// Spawns background tasks of the client, e.g. requests of `INSERT`s and
+/// the task started by [`Batcher::spawn()`].
+///
+/// By default, [`TokioExecutor`] is used, which calls `tokio::spawn`.
+/// Implement this trait to instrument tasks, to run them on a dedicated
+/// runtime or on a `LocalSet`. See [`TrackingExecutor`] to wait for
+/// outstanding tasks on shutdown.
+///
+/// [`Batcher::spawn()`]: crate::batcher::Batcher::spawn
+///
+/// # Examples
+/// ```
+/// use clickhouse::{executor::{Executor, Task}, Client};
+///
+/// struct Dedicated(tokio::runtime::Handle);
+///
+/// impl Executor for Dedicated {
+///     fn spawn(&self, task: Task) {
+///         self.0.spawn(task);
+///     }
+/// }
+///
+/// # fn example(handle: tokio::runtime::Handle) {
+/// let client = Client::default().with_executor(Dedicated(handle));
+/// # }
+/// ```
+pub trait Executor: Send + Sync + 'static {
+    /// Runs the task to completion in the background.
+    ///
+    /// The task can be dropped without completion, it's handled as
+    /// cancellation by the client.
+    fn spawn(&self, task: Task);
+}
+
+/// A background task spawned by the client.
+///
+/// It's a future, so it can be passed directly to `tokio::spawn` and similar.
+#[must_use = "futures do nothing unless polled"]
+pub struct Task {
+    name: &'static str,
+    future: Pin<Box<dyn Future<Output = ()> + Send>>,
+}
+
+impl Task {
+    pub(crate) fn new(
+        name: &'static str,
+        future: impl Future<Output = ()> + Send + 'static,
+    ) -> Self {
+        Self {
+            name,
+            future: Box::pin(future),
+        }
+    }
+
+    /// Returns a name of the task, e.g. `"insert"` or `"batcher"`.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl Future for Task {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.future.as_mut().poll(cx)
+    }
+}
+
+impl fmt::Debug for Task {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Task").field("name", &self.name).finish()
+    }
+}
+
+// === TokioExecutor ===
+
+/// Spawns tasks by `tokio::spawn`, the default executor.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioExecutor;
+
+impl Executor for TokioExecutor {
+    fn spawn(&self, task: Task) {
+        tokio::spawn(task);
+    }
+}
+
+// === TrackingExecutor ===
+
+/// Wraps another executor and keeps track of outstanding tasks, so they can
+/// be enumerated and awaited on shutdown.
+///
+/// # Examples
+/// ```
+/// # async fn example() {
+/// use clickhouse::{executor::TrackingExecutor, Client};
+///
+/// let executor = TrackingExecutor::default();
+/// let client = Client::default().with_executor(executor.clone());
+///
+/// // ... use the client ...
+///
+/// println!("waiting for {:?}", executor.tasks());
+/// executor.wait().await;
+/// # }
+/// ```
+pub struct TrackingExecutor<E = TokioExecutor> {
+    inner: Arc<Tracking<E>>,
+}
+
+struct Tracking<E> {
+    executor: E,
+    next_id: AtomicU64,
+    tasks: Mutex<HashMap<u64, &'static str>>,
+    notify: Notify,
+}
+
+impl<E> TrackingExecutor<E> {
+    /// Creates a tracking executor spawning tasks by the provided one.
+    pub fn new(executor: E) -> Self {
+        Self {
+            inner: Arc::new(Tracking {
+                executor,
+                next_id: AtomicU64::new(0),
+                tasks: Mutex::new(HashMap::new()),
+                notify: Notify::new(),
+            }),
+        }
+    }
+
+    /// Returns names of outstanding tasks, see [`Task::name()`].
+    pub fn tasks(&self) -> Vec<&'static str> {
+        self.inner.tasks.lock().unwrap().values().copied().collect()
+    }
+
+    /// Waits until all outstanding tasks are completed or cancelled,
+    /// including ones spawned while waiting.
+    pub async fn wait(&self) {
+        loop {
+            // Created before the check to not miss notifications.
+            let notified = self.inner.notify.notified();
+
+            if self.inner.tasks.lock().unwrap().is_empty() {
+                return;
+            }
+
+            notified.await;
+        }
+    }
+}
+
+impl Default for TrackingExecutor {
+    fn default() -> Self {
+        Self::new(TokioExecutor)
+    }
+}
+
+impl<E> Clone for TrackingExecutor<E> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<E: Executor> Executor for TrackingExecutor<E> {
+    fn spawn(&self, task: Task) {
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        self.inner.tasks.lock().unwrap().insert(id, task.name);
+
+        // Removes the task even if it's dropped without completion.
+        let guard = TrackingGuard {
+            inner: self.inner.clone(),
+            id,
+        };
+
+        let name = task.name;
+        self.inner.executor.spawn(Task::new(name, async move {
+            let _guard = guard;
+            task.await;
+        }));
+    }
+}
+
+struct TrackingGuard<E> {
+    inner: Arc<Tracking<E>>,
+    id: u64,
+}
+
+impl<E> Drop for TrackingGuard<E> {
+    fn drop(&mut self) {
+        let mut tasks = self.inner.tasks.lock().unwrap();
+        tasks.remove(&self.id);
+
+        if tasks.is_empty() {
+            self.inner.notify.notify_waiters();
+        }
+    }
+}
+
+// === spawn ===
+
+/// Spawns the future by the executor and returns a handle to its output.
+pub(crate) fn spawn<F>(
+    executor: &dyn Executor,
+    name: &'static str,
+    future: F,
+) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let (sender, receiver) = oneshot::channel();
+    let (abort, registration) = AbortHandle::new_pair();
+
+    let future = Abortable::new(AssertUnwindSafe(future).catch_unwind(), registration);
+    executor.spawn(Task::new(name, async move {
+        if let Ok(result) = future.await {
+            let _ = sender.send(result);
+        }
+    }));
+
+    JoinHandle { receiver, abort }
+}
+
+/// A handle to the output of a spawned task, similar to tokio's one.
+///
+/// Dropping the handle detaches the task.
+pub(crate) struct JoinHandle<T> {
+    receiver: oneshot::Receiver<std::thread::Result<T>>,
+    abort: AbortHandle,
+}
+
+impl<T> JoinHandle<T> {
+    pub(crate) fn abort(&self) {
+        self.abort.abort();
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Poll::Ready(match futures::ready!(self.receiver.poll_unpin(cx)) {
+            Ok(Ok(output)) => Ok(output),
+            Ok(Err(payload)) => Err(JoinError::Panic(payload)),
+            Err(oneshot::Canceled) => Err(JoinError::Cancelled),
+        })
+    }
+}
+
+/// A task failed to complete.
+pub(crate) enum JoinError {
+    Panic(Box<dyn Any + Send>),
+    // Aborted or dropped by the executor.
+    Cancelled,
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Panic(_) => f.write_str("task panicked"),
+            Self::Cancelled => f.write_str("task was cancelled"),
+        }
+    }
+}
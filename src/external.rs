@@ -0,0 +1,581 @@
+//! External data tables sent along with a query, see
+//! [`Query::with_external_table()`].
+//!
+//! [`Query::with_external_table()`]: crate::query::Query::with_external_table
+
+use std::fmt::Write;
+
+use bstr::ByteSlice;
+use bytes::{BufMut, Bytes, BytesMut};
+use serde::{
+    ser::{self, Impossible, Serialize},
+    Serializer,
+};
+
+use crate::{
+    error::{Error, Result},
+    row::Row,
+    rowbinary,
+    sql::escape,
+};
+
+/// A table encoded in `RowBinary` and sent as a part of `multipart/form-data`.
+#[derive(Debug, Clone)]
+pub(crate) struct ExternalTable {
+    pub(crate) name: String,
+    pub(crate) structure: String,
+    data: Bytes,
+}
+
+impl ExternalTable {
+    /// Encodes rows, the structure is inferred from values if not provided.
+    pub(crate) fn new<'a, R>(
+        name: &str,
+        structure: Option<&str>,
+        rows: impl IntoIterator<Item = &'a R>,
+    ) -> Result<Self, String>
+    where
+        R: Row + Serialize + 'a,
+    {
+        let is_valid_name = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_valid_name {
+            return Err(format!("invalid name of external table: {name:?}"));
+        }
+
+        let mut data = BytesMut::new();
+        let mut inferred = Type::Unknown;
+
+        for row in rows {
+            // Inferred first to report unsupported types before serialization.
+            if structure.is_none() {
+                let ty = row
+                    .serialize(TypeSerializer)
+                    .map_err(|err| err.to_string())?;
+                inferred = inferred.merge(ty)?;
+            }
+
+            rowbinary::serialize_into(&mut data, row).map_err(|err| err.to_string())?;
+        }
+
+        let structure = match structure {
+            Some(structure) => structure.to_string(),
+            None => infer_structure(R::COLUMN_NAMES, inferred).map_err(|err| {
+                format!("cannot infer the structure of external table {name}: {err}")
+            })?,
+        };
+
+        Ok(Self {
+            name: name.into(),
+            structure,
+            data: data.freeze(),
+        })
+    }
+}
+
+fn infer_structure(names: &[&str], ty: Type) -> Result<String, String> {
+    if ty == Type::Unknown {
+        return Err("no rows".into());
+    }
+
+    let types = match ty {
+        Type::Tuple(types) => types,
+        ty if names.is_empty() => vec![ty],
+        _ => return Err("unsupported row type".into()),
+    };
+
+    if !names.is_empty() && names.len() != types.len() {
+        return Err("unsupported row type".into());
+    }
+
+    let mut structure = String::new();
+    for (i, ty) in types.iter().enumerate() {
+        if i > 0 {
+            structure.push_str(", ");
+        }
+
+        match names.get(i) {
+            Some(name) if is_plain_identifier(name) => structure.push_str(name),
+            Some(name) => escape::identifier(name, &mut structure).map_err(|e| e.to_string())?,
+            None => write!(structure, "_{}", i + 1).map_err(|e| e.to_string())?,
+        }
+
+        structure.push(' ');
+        ty.write(&mut structure)?;
+    }
+
+    Ok(structure)
+}
+
+fn is_plain_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Encodes tables as `multipart/form-data`, returns a boundary and a body.
+pub(crate) fn encode_multipart(tables: &[ExternalTable]) -> (String, Bytes) {
+    // The boundary must not occur in any data.
+    let boundary = (0u64..)
+        .map(|i| format!("clickhouse-rs-{i:016x}"))
+        .find(|b| tables.iter().all(|t| t.data.find(b).is_none()))
+        .expect("a unique boundary");
+
+    let mut body = BytesMut::new();
+    for table in tables {
+        let name = &table.name;
+        body.put_slice(format!("--{boundary}\r\n").as_bytes());
+        body.put_slice(
+            format!("Content-Disposition: form-data; name=\"{name}\"; filename=\"{name}\"\r\n")
+                .as_bytes(),
+        );
+        body.put_slice(b"Content-Type: application/octet-stream\r\n\r\n");
+        body.put_slice(&table.data);
+        body.put_slice(b"\r\n");
+    }
+    body.put_slice(format!("--{boundary}--\r\n").as_bytes());
+
+    (boundary, body.freeze())
+}
+
+// === Type ===
+
+/// A ClickHouse type inferred from serialized values.
+#[derive(Debug, Clone, PartialEq)]
+enum Type {
+    Name(&'static str),
+    Nullable(Box<Type>),
+    Array(Box<Type>),
+    Tuple(Vec<Type>),
+    Map(Box<Type>, Box<Type>),
+    // `None` or an element of an empty array.
+    Unknown,
+}
+
+impl Type {
+    fn merge(self, other: Type) -> Result<Type, String> {
+        Ok(match (self, other) {
+            (Type::Unknown, ty) | (ty, Type::Unknown) => ty,
+            (Type::Nullable(a), Type::Nullable(b)) => Type::Nullable(Box::new(a.merge(*b)?)),
+            (Type::Array(a), Type::Array(b)) => Type::Array(Box::new(a.merge(*b)?)),
+            (Type::Map(ak, av), Type::Map(bk, bv)) => {
+                Type::Map(Box::new(ak.merge(*bk)?), Box::new(av.merge(*bv)?))
+            }
+            (Type::Tuple(a), Type::Tuple(b)) if a.len() == b.len() => Type::Tuple(
+                a.into_iter()
+                    .zip(b)
+                    .map(|(a, b)| a.merge(b))
+                    .collect::<Result<_, _>>()?,
+            ),
+            (a, b) if a == b => a,
+            (a, b) => {
+                let (mut a_name, mut b_name) = (String::new(), String::new());
+                let _ = a.write(&mut a_name);
+                let _ = b.write(&mut b_name);
+                return Err(format!("inconsistent types {a_name} and {b_name}"));
+            }
+        })
+    }
+
+    fn write(&self, dst: &mut String) -> Result<(), String> {
+        match self {
+            Type::Name(name) => dst.push_str(name),
+            Type::Nullable(ty) => {
+                dst.push_str("Nullable(");
+                ty.write(dst)?;
+                dst.push(')');
+            }
+            Type::Array(ty) => {
+                dst.push_str("Array(");
+                ty.write(dst)?;
+                dst.push(')');
+            }
+            Type::Tuple(types) => {
+                dst.push_str("Tuple(");
+                for (i, ty) in types.iter().enumerate() {
+                    if i > 0 {
+                        dst.push_str(", ");
+                    }
+                    ty.write(dst)?;
+                }
+                dst.push(')');
+            }
+            Type::Map(key, value) => {
+                dst.push_str("Map(");
+                key.write(dst)?;
+                dst.push_str(", ");
+                value.write(dst)?;
+                dst.push(')');
+            }
+            Type::Unknown => return Err("only NULLs or empty arrays".into()),
+        }
+        Ok(())
+    }
+}
+
+// === TypeSerializer ===
+
+struct TypeSerializer;
+
+macro_rules! infer {
+    ($($method:ident($ty:ty) => $name:literal),* $(,)?) => {
+        $(
+            #[inline]
+            fn $method(self, _v: $ty) -> Result<Type> {
+                Ok(Type::Name($name))
+            }
+        )*
+    };
+}
+
+impl Serializer for TypeSerializer {
+    type Error = Error;
+    type Ok = Type;
+    type SerializeMap = Compound;
+    type SerializeSeq = Compound;
+    type SerializeStruct = Compound;
+    type SerializeStructVariant = Impossible<Type, Error>;
+    type SerializeTuple = Compound;
+    type SerializeTupleStruct = Compound;
+    type SerializeTupleVariant = Impossible<Type, Error>;
+
+    infer! {
+        serialize_bool(bool) => "Bool",
+        serialize_i8(i8) => "Int8",
+        serialize_i16(i16) => "Int16",
+        serialize_i32(i32) => "Int32",
+        serialize_i64(i64) => "Int64",
+        serialize_i128(i128) => "Int128",
+        serialize_u8(u8) => "UInt8",
+        serialize_u16(u16) => "UInt16",
+        serialize_u32(u32) => "UInt32",
+        serialize_u64(u64) => "UInt64",
+        serialize_u128(u128) => "UInt128",
+        serialize_f32(f32) => "Float32",
+        serialize_f64(f64) => "Float64",
+        serialize_str(&str) => "String",
+        serialize_bytes(&[u8]) => "String",
+    }
+
+    fn serialize_char(self, _v: char) -> Result<Type> {
+        unsupported("char")
+    }
+
+    fn serialize_none(self) -> Result<Type> {
+        Ok(Type::Nullable(Box::new(Type::Unknown)))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Type> {
+        Ok(Type::Nullable(Box::new(value.serialize(self)?)))
+    }
+
+    fn serialize_unit(self) -> Result<Type> {
+        unsupported("()")
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Type> {
+        unsupported(name)
+    }
+
+    fn serialize_unit_variant(self, name: &'static str, _: u32, _: &'static str) -> Result<Type> {
+        unsupported(name)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Type> {
+        // Helpers of `crate::serde` provide types, e.g. `DateTime64(3)`.
+        match crate::serde::marker::type_name(name) {
+            Some(type_name) => Ok(Type::Name(type_name)),
+            None => value.serialize(self),
+        }
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<Type> {
+        unsupported(name)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound> {
+        Ok(Compound::new(Kind::Array))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Compound> {
+        Ok(Compound::new(Kind::Tuple))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Compound> {
+        Ok(Compound::new(Kind::Tuple))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        unsupported(name)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound> {
+        Ok(Compound::new(Kind::Map))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Compound> {
+        Ok(Compound::new(Kind::Tuple))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        unsupported(name)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+fn unsupported<T>(what: &str) -> Result<T> {
+    Err(Error::Unsupported(format!(
+        "cannot infer a type of {what}, specify the structure explicitly"
+    )))
+}
+
+enum Kind {
+    Array,
+    Tuple,
+    Map,
+}
+
+struct Compound {
+    kind: Kind,
+    types: Vec<Type>,
+    // Merged types of keys and values (or elements) of maps (or arrays).
+    key: Type,
+    value: Type,
+}
+
+impl Compound {
+    fn new(kind: Kind) -> Self {
+        Self {
+            kind,
+            types: Vec::new(),
+            key: Type::Unknown,
+            value: Type::Unknown,
+        }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let ty = value.serialize(TypeSerializer)?;
+
+        if let Kind::Tuple = self.kind {
+            self.types.push(ty);
+        } else {
+            self.value = merge(&mut self.value, ty)?;
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Type> {
+        Ok(match self.kind {
+            Kind::Array => Type::Array(Box::new(self.value)),
+            Kind::Tuple => Type::Tuple(self.types),
+            Kind::Map => Type::Map(Box::new(self.key), Box::new(self.value)),
+        })
+    }
+}
+
+fn merge(acc: &mut Type, ty: Type) -> Result<Type> {
+    std::mem::replace(acc, Type::Unknown)
+        .merge(ty)
+        .map_err(Error::Custom)
+}
+
+impl ser::SerializeSeq for Compound {
+    type Error = Error;
+    type Ok = Type;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Type> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for Compound {
+    type Error = Error;
+    type Ok = Type;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Type> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound {
+    type Error = Error;
+    type Ok = Type;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Type> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for Compound {
+    type Error = Error;
+    type Ok = Type;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Type> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeMap for Compound {
+    type Error = Error;
+    type Ok = Type;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        let ty = key.serialize(TypeSerializer)?;
+        self.key = merge(&mut self.key, ty)?;
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Type> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::Serialize;
+
+    use super::*;
+
+    // XXX: need for `derive(Row)`. Provide `row(crate = ..)` instead.
+    use crate as clickhouse;
+    use clickhouse_derive::Row;
+
+    #[derive(Row, Serialize)]
+    struct Sample {
+        id: u64,
+        name: String,
+        tags: Vec<String>,
+        parent: Option<u32>,
+        attrs: BTreeMap<String, (i8, f64)>,
+    }
+
+    #[derive(Row, Serialize)]
+    struct Letter {
+        letter: char,
+    }
+
+    fn structure<'a, R: Row + Serialize + 'a>(rows: impl IntoIterator<Item = &'a R>) -> String {
+        match ExternalTable::new("t", None, rows) {
+            Ok(table) => table.structure,
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn it_infers_structure() {
+        let rows = [
+            Sample {
+                id: 1,
+                name: "a".into(),
+                tags: vec![],
+                parent: None,
+                attrs: BTreeMap::new(),
+            },
+            Sample {
+                id: 2,
+                name: "b".into(),
+                tags: vec!["x".into()],
+                parent: Some(1),
+                attrs: [("k".into(), (1, 2.))].into(),
+            },
+        ];
+
+        assert_eq!(
+            structure(&rows),
+            "id UInt64, name String, tags Array(String), parent Nullable(UInt32), \
+             attrs Map(String, Tuple(Int8, Float64))"
+        );
+
+        assert_eq!(structure(&[1u64, 2]), "_1 UInt64");
+        assert_eq!(
+            structure(&[(1u64, "a".to_string())]),
+            "_1 UInt64, _2 String"
+        );
+    }
+
+    #[test]
+    fn it_fails_to_infer_structure() {
+        assert!(structure::<u64>(&[]).contains("no rows"));
+        assert!(structure(&[Vec::<u32>::new()]).contains("only NULLs or empty arrays"));
+        assert!(structure(&[Letter { letter: 'a' }]).contains("cannot infer a type of char"));
+        assert!(structure(&[vec![Some(1u8)], vec![None], vec![]]).contains("Nullable(UInt8)"));
+        assert!(ExternalTable::new("a-b", None, &[1u64])
+            .unwrap_err()
+            .contains("invalid name"));
+
+        let table = ExternalTable::new("t", Some("id UInt64"), &[1u64]).unwrap();
+        assert_eq!(table.structure, "id UInt64");
+    }
+
+    #[test]
+    fn it_encodes_multipart() {
+        let a = ExternalTable::new("a", None, &[1u8, 2]).unwrap();
+        let b = ExternalTable::new("b", None, &["--clickhouse-rs-0000000000000000".to_string()])
+            .unwrap();
+
+        let (boundary, body) = encode_multipart(&[a, b]);
+        assert_eq!(boundary, "clickhouse-rs-0000000000000001");
+        assert_eq!(
+            body,
+            "--clickhouse-rs-0000000000000001\r\n\
+             Content-Disposition: form-data; name=\"a\"; filename=\"a\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n\
+             \x01\x02\r\n\
+             --clickhouse-rs-0000000000000001\r\n\
+             Content-Disposition: form-data; name=\"b\"; filename=\"b\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n\
+             \x20--clickhouse-rs-0000000000000000\r\n\
+             --clickhouse-rs-0000000000000001--\r\n"
+        );
+    }
+}
@@ -1,17 +1,20 @@
 use std::{future::Future, marker::PhantomData, mem, panic, pin::Pin, time::Duration};
 
 use bytes::{Bytes, BytesMut};
-use hyper::{self, Request};
+use hyper::{
+    self,
+    header::{ACCEPT_ENCODING, CONTENT_ENCODING},
+    Request,
+};
 use replace_with::replace_with_or_abort;
 use serde::Serialize;
-use tokio::{
-    task::JoinHandle,
-    time::{Instant, Sleep},
-};
+use tokio::time::{Instant, Sleep};
 use url::Url;
 
 use crate::{
+    compression::http,
     error::{Error, Result},
+    executor::{self, JoinError, JoinHandle},
     headers::{with_authentication, with_request_headers},
     request_body::{ChunkSender, RequestBody},
     response::Response,
@@ -37,10 +40,18 @@ const_assert!(BUFFER_SIZE.is_power_of_two()); // to use the whole buffer's capac
 pub struct Insert<T> {
     state: InsertState,
     buffer: BytesMut,
-    #[cfg(feature = "lz4")]
+    #[cfg(any(feature = "lz4", feature = "zstd"))]
     compression: Compression,
+    // Set on starting the request if the HTTP compression is enabled.
+    encoder: Option<http::Encoder>,
     send_timeout: Option<Duration>,
     end_timeout: Option<Duration>,
+    // Start the request only on sending data, see `defer_request()`.
+    deferred: bool,
+    // Set by `new_inner()` to rebuild the statement in `columns()`.
+    table: Option<String>,
+    // Fields to serialize, see `columns()`.
+    columns: Option<Box<[&'static str]>>,
     // Use boxed `Sleep` to reuse a timer entry, it improves performance.
     // Also, `tokio::time::timeout()` significantly increases a future's size.
     sleep: Pin<Box<Sleep>>,
@@ -140,19 +151,29 @@ impl<T> Insert<T> {
         // TODO: what about escaping a table name?
         // https://clickhouse.com/docs/en/sql-reference/syntax#identifiers
         let sql = format!("INSERT INTO {}({}) FORMAT RowBinary", table, fields_names);
-        Ok(Self {
+        let mut insert = Self::with_sql(client, sql);
+        insert.table = Some(table.into());
+        Ok(insert)
+    }
+
+    pub(crate) fn with_sql(client: &Client, sql: String) -> Self {
+        Self {
             state: InsertState::NotStarted {
                 client: Box::new(client.clone()),
                 sql,
             },
             buffer: BytesMut::with_capacity(BUFFER_SIZE),
-            #[cfg(feature = "lz4")]
-            compression: client.compression,
+            #[cfg(any(feature = "lz4", feature = "zstd"))]
+            compression: client.native_compression(),
+            encoder: None,
             send_timeout: None,
             end_timeout: None,
+            deferred: false,
+            table: None,
+            columns: None,
             sleep: Box::pin(tokio::time::sleep(Duration::new(0, 0))),
             _marker: PhantomData,
-        })
+        }
     }
 
     /// Sets timeouts for different operations.
@@ -178,6 +199,79 @@ impl<T> Insert<T> {
         self
     }
 
+    /// Inserts only the provided columns, other fields of `T` are skipped
+    /// while serializing, so the server fills them using `DEFAULT`
+    /// expressions of the table.
+    ///
+    /// Columns are inserted in the order of fields of `T`, regardless of
+    /// the order in `columns`.
+    ///
+    /// Returns [`Error::InvalidParams`] if `columns` is empty, contains
+    /// duplicates or names that aren't fields of `T`.
+    ///
+    /// ```
+    /// # async fn example() -> clickhouse::error::Result<()> {
+    /// use clickhouse::{Client, Row};
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Row, Serialize)]
+    /// struct Event {
+    ///     id: u64,
+    ///     name: String,
+    ///     created_at: u32, // `DEFAULT now()` in the table
+    /// }
+    ///
+    /// let client = Client::default();
+    /// let mut insert = client.insert::<Event>("events")?.columns(&["id", "name"])?;
+    /// insert.write(&Event { id: 1, name: "foo".into(), created_at: 0 }).await?;
+    /// insert.end().await?;
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// # Panics
+    /// If called after the request is started, e.g., after [`Insert::write`].
+    #[track_caller]
+    pub fn columns(mut self, columns: &[&str]) -> Result<Self>
+    where
+        T: Row,
+    {
+        assert!(
+            matches!(self.state, InsertState::NotStarted { .. }),
+            "columns() after the request is started"
+        );
+
+        if columns.is_empty() {
+            return Err(Error::InvalidParams("no columns to insert".into()));
+        }
+
+        for (i, column) in columns.iter().enumerate() {
+            if !T::COLUMN_NAMES.contains(column) {
+                let msg = format!("`{column}` isn't a field of the row type");
+                return Err(Error::InvalidParams(msg.into()));
+            }
+            if columns[..i].contains(column) {
+                let msg = format!("`{column}` is provided multiple times");
+                return Err(Error::InvalidParams(msg.into()));
+            }
+        }
+
+        let selected = T::COLUMN_NAMES
+            .iter()
+            .copied()
+            .filter(|name| columns.contains(name))
+            .collect::<Box<[_]>>();
+
+        let fields_names = row::join_names(selected.iter());
+        let table = self.table.as_deref().expect("the table is unknown");
+        let sql = format!("INSERT INTO {}({}) FORMAT RowBinary", table, fields_names);
+        if let InsertState::NotStarted { sql: old, .. } = &mut self.state {
+            *old = sql;
+        }
+
+        self.columns = Some(selected);
+        Ok(self)
+    }
+
     /// Similar to [`Client::with_option`], but for this particular INSERT
     /// statement only.
     ///
@@ -189,6 +283,17 @@ impl<T> Insert<T> {
         self
     }
 
+    #[cfg(feature = "inserter")]
+    pub(crate) fn set_option(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.state.with_option(name, value);
+    }
+
+    /// Returns the statement, if the request isn't started yet.
+    #[cfg(feature = "inserter")]
+    pub(crate) fn sql(&self) -> Option<&str> {
+        self.state.client_with_sql().map(|(_, sql)| sql)
+    }
+
     pub(crate) fn set_timeouts(
         &mut self,
         send_timeout: Option<Duration>,
@@ -223,26 +328,35 @@ impl<T> Insert<T> {
 
         async move {
             result?;
-            if self.buffer.len() >= MIN_CHUNK_SIZE {
-                self.send_chunk().await?;
-            }
-            Ok(())
+            self.send_if_full().await
         }
     }
 
+    /// Sends the buffer if it's big enough, see [`Insert::write`].
+    #[inline]
+    pub(crate) async fn send_if_full(&mut self) -> Result<()> {
+        if self.buffer.len() >= MIN_CHUNK_SIZE && !self.deferred {
+            self.send_chunk().await?;
+        }
+        Ok(())
+    }
+
     #[inline(always)]
     pub(crate) fn do_write(&mut self, row: &T) -> Result<usize>
     where
         T: Serialize,
     {
         match self.state {
-            InsertState::NotStarted { .. } => self.init_request(),
-            InsertState::Active { .. } => Ok(()),
+            InsertState::NotStarted { .. } if !self.deferred => self.init_request(),
+            InsertState::NotStarted { .. } | InsertState::Active { .. } => Ok(()),
             _ => panic!("write() after error"),
         }?;
 
         let old_buf_size = self.buffer.len();
-        let result = rowbinary::serialize_into(&mut self.buffer, row);
+        let result = match &self.columns {
+            Some(columns) => rowbinary::serialize_columns_into(&mut self.buffer, row, columns),
+            None => rowbinary::serialize_into(&mut self.buffer, row),
+        };
         let written = self.buffer.len() - old_buf_size;
 
         if result.is_err() {
@@ -252,6 +366,59 @@ impl<T> Insert<T> {
         result.and(Ok(written))
     }
 
+    /// Same as [`Insert::do_write`], but for an already serialized row.
+    #[cfg(feature = "inserter")]
+    pub(crate) fn do_write_raw(&mut self, row: &[u8]) -> Result<usize> {
+        match self.state {
+            InsertState::NotStarted { .. } if !self.deferred => self.init_request(),
+            InsertState::NotStarted { .. } | InsertState::Active { .. } => Ok(()),
+            _ => panic!("write() after error"),
+        }?;
+
+        self.buffer.extend_from_slice(row);
+        Ok(row.len())
+    }
+
+    /// Doesn't start the request until data is sent, so options can be set
+    /// depending on written rows.
+    #[cfg(feature = "inserter")]
+    pub(crate) fn defer_request(&mut self) {
+        self.deferred = true;
+    }
+
+    /// Returns written, but not yet sent data.
+    #[cfg(feature = "inserter")]
+    pub(crate) fn buffered(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Returns the last `len` bytes written by [`Insert::do_write`].
+    #[cfg(feature = "inserter")]
+    #[inline]
+    pub(crate) fn last_written(&self, len: usize) -> &[u8] {
+        &self.buffer[self.buffer.len() - len..]
+    }
+
+    /// Writes already serialized rows.
+    #[cfg(feature = "inserter")]
+    pub(crate) async fn write_raw(&mut self, mut data: &[u8]) -> Result<()> {
+        if matches!(self.state, InsertState::NotStarted { .. }) {
+            self.init_request()?;
+        }
+
+        while !data.is_empty() {
+            let len = data.len().min(BUFFER_SIZE - self.buffer.len());
+            self.buffer.extend_from_slice(&data[..len]);
+            data = &data[len..];
+
+            if self.buffer.len() >= MIN_CHUNK_SIZE {
+                self.send_chunk().await?;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Ends `INSERT`, the server starts processing the data.
     ///
     /// Succeeds if the server returns 200, that means the `INSERT` was handled
@@ -259,10 +426,29 @@ impl<T> Insert<T> {
     ///
     /// NOTE: If it isn't called, the whole `INSERT` is aborted.
     pub async fn end(mut self) -> Result<()> {
+        self.send_end().await?;
+        self.wait_handle().await
+    }
+
+    /// The first part of [`Insert::end`], sends the rest of data.
+    pub(crate) async fn send_end(&mut self) -> Result<()> {
         if !self.buffer.is_empty() {
+            if matches!(self.state, InsertState::NotStarted { .. }) {
+                self.init_request()?;
+            }
             self.send_chunk().await?;
         }
+        if let Some(encoder) = self.encoder.take() {
+            let trailer = encoder.finish()?;
+            self.send_bytes(trailer).await?;
+        }
         self.state.terminated();
+        Ok(())
+    }
+
+    /// The second part of [`Insert::end`], waits for the response.
+    #[cfg(feature = "inserter")]
+    pub(crate) async fn wait_end(&mut self) -> Result<()> {
         self.wait_handle().await
     }
 
@@ -273,8 +459,11 @@ impl<T> Insert<T> {
         // It's difficult to determine when allocations occur.
         // So, instead we control it manually here and rely on the system allocator.
         let chunk = self.take_and_prepare_chunk()?;
+        self.send_bytes(chunk).await
+    }
 
-        let sender = self.state.sender().unwrap(); // checked above
+    async fn send_bytes(&mut self, chunk: Bytes) -> Result<()> {
+        let sender = self.state.sender().unwrap(); // checked by callers
 
         let is_timed_out = match timeout!(self, send_timeout, sender.send(chunk)) {
             Some(true) => return Ok(()),
@@ -302,7 +491,7 @@ impl<T> Insert<T> {
             Some(handle) => {
                 let result = match timeout!(self, end_timeout, &mut *handle) {
                     Some(Ok(res)) => res,
-                    Some(Err(err)) if err.is_panic() => panic::resume_unwind(err.into_panic()),
+                    Some(Err(JoinError::Panic(payload))) => panic::resume_unwind(payload),
                     Some(Err(err)) => Err(Error::Custom(format!("unexpected error: {err}"))),
                     None => {
                         // We can do nothing useful here, so just shut down the background task.
@@ -317,19 +506,20 @@ impl<T> Insert<T> {
         }
     }
 
-    #[cfg(feature = "lz4")]
     fn take_and_prepare_chunk(&mut self) -> Result<Bytes> {
-        Ok(if self.compression.is_lz4() {
-            let compressed = crate::compression::lz4::compress(&self.buffer)?;
+        if let Some(encoder) = &mut self.encoder {
+            let compressed = encoder.encode(&self.buffer)?;
             self.buffer.clear();
-            compressed
-        } else {
-            mem::replace(&mut self.buffer, BytesMut::with_capacity(BUFFER_SIZE)).freeze()
-        })
-    }
+            return Ok(compressed);
+        }
+
+        #[cfg(any(feature = "lz4", feature = "zstd"))]
+        if self.compression.is_native() {
+            let compressed = crate::compression::compress(self.compression, &self.buffer)?;
+            self.buffer.clear();
+            return Ok(compressed);
+        }
 
-    #[cfg(not(feature = "lz4"))]
-    fn take_and_prepare_chunk(&mut self) -> Result<Bytes> {
         Ok(mem::replace(&mut self.buffer, BytesMut::with_capacity(BUFFER_SIZE)).freeze())
     }
 
@@ -350,10 +540,14 @@ impl<T> Insert<T> {
 
         pairs.append_pair("query", sql);
 
-        if client.compression.is_lz4() {
+        if client.native_compression().is_native() {
             pairs.append_pair("decompress", "1");
         }
 
+        if client.http_compression.is_some() {
+            pairs.append_pair("enable_http_compression", "1");
+        }
+
         for (name, value) in &client.options {
             pairs.append_pair(name, value);
         }
@@ -364,6 +558,13 @@ impl<T> Insert<T> {
         builder = with_request_headers(builder, &client.headers, &client.products_info);
         builder = with_authentication(builder, &client.authentication);
 
+        let http_compression = client.http_compression;
+        if let Some(compression) = &http_compression {
+            builder = builder
+                .header(CONTENT_ENCODING, compression.encoding())
+                .header(ACCEPT_ENCODING, compression.encoding());
+        }
+
         let (sender, body) = RequestBody::chunked();
 
         let request = builder
@@ -371,10 +572,14 @@ impl<T> Insert<T> {
             .map_err(|err| Error::InvalidParams(Box::new(err)))?;
 
         let future = client.http.request(request);
-        // TODO: introduce `Executor` to allow bookkeeping of spawned tasks.
-        let handle =
-            tokio::spawn(async move { Response::new(future, Compression::None).finish().await });
+        let limits = client.limits;
+        let handle = executor::spawn(&*client.executor, "insert", async move {
+            Response::new(future, Compression::None, limits)
+                .finish()
+                .await
+        });
 
+        self.encoder = http_compression.map(http::Encoder::new).transpose()?;
         self.state = InsertState::Active { handle, sender };
         Ok(())
     }
@@ -1,9 +1,16 @@
-use std::mem;
+use std::{fs, mem, path::PathBuf};
 
 use serde::Serialize;
 use tokio::time::Duration;
 
-use crate::{error::Result, insert::Insert, row::Row, ticks::Ticks, Client};
+use crate::{
+    error::{Error, Result},
+    insert::Insert,
+    row::Row,
+    spool::{self, Segment, SegmentToken, Spool},
+    ticks::Ticks,
+    Client,
+};
 
 /// Performs multiple consecutive `INSERT`s.
 ///
@@ -14,7 +21,14 @@ use crate::{error::Result, insert::Insert, row::Row, ticks::Ticks, Client};
 /// Rows are being sent progressively to spread network load.
 ///
 /// All rows written by [`Inserter::write()`] between [`Inserter::commit()`]
-/// calls are sent in one `INSERT` statement.
+/// calls are sent in one `INSERT` statement (or in one per shard, see
+/// [`Inserter::with_parallelism()`]).
+///
+/// If the `async_insert` option is enabled, the server batches rows itself,
+/// so limits are ignored and every [`Inserter::commit()`] ends the `INSERT`.
+/// Consider [`AsyncInserter`] for this case.
+///
+/// [`AsyncInserter`]: crate::async_inserter::AsyncInserter
 #[must_use]
 pub struct Inserter<T> {
     client: Client,
@@ -23,10 +37,95 @@ pub struct Inserter<T> {
     max_rows: u64,
     send_timeout: Option<Duration>,
     end_timeout: Option<Duration>,
-    insert: Option<Insert<T>>,
+    shards: Vec<Shard<T>>,
+    next_shard: usize,
     ticks: Ticks,
     pending: Quantities,
     in_transaction: bool,
+    spool: Option<Spool>,
+    deduplication: Option<DeduplicationToken>,
+    committed: Vec<Quantities>,
+    // `INSERT`s ended by the previous commit, which responses are awaited.
+    in_flight: Vec<InFlight<T>>,
+    // Transactions of the previous commit.
+    in_flight_transactions: u64,
+}
+
+struct Shard<T> {
+    insert: Option<Insert<T>>,
+    segment: Option<Segment>,
+    pending: Quantities,
+    in_transaction: bool,
+    // Set if the token is derived from rows on ending the `INSERT`.
+    hash_prefix: Option<String>,
+}
+
+// An `INSERT` which data is sent, but the response is awaited.
+struct InFlight<T> {
+    index: usize,
+    insert: Insert<T>,
+    segment: Option<Segment>,
+    quantities: Quantities,
+}
+
+impl<T> Shard<T> {
+    fn new() -> Self {
+        Self {
+            insert: None,
+            segment: None,
+            pending: Quantities::ZERO,
+            in_transaction: false,
+            hash_prefix: None,
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        self.insert.is_none()
+    }
+}
+
+/// Defines how to generate [`insert_deduplication_token`] for batches,
+/// see [`Inserter::with_deduplication_token()`].
+///
+/// [`insert_deduplication_token`]: https://clickhouse.com/docs/en/operations/settings/settings#insert_deduplication_token
+#[derive(Debug, Clone)]
+pub struct DeduplicationToken(TokenKind);
+
+#[derive(Debug, Clone)]
+enum TokenKind {
+    Sequence { prefix: String, next: u64 },
+    Hash { prefix: String },
+}
+
+impl DeduplicationToken {
+    /// `{prefix}-{seq}`, where `seq` is the number of the `INSERT` made by
+    /// the inserter, starting from `start`.
+    ///
+    /// The prefix should identify the source of rows, e.g. a partition of
+    /// a queue.
+    ///
+    /// **The sequence isn't persisted. If it restarts from the same `start`
+    /// with the same prefix (e.g. after a restart of the process), the server
+    /// treats new batches as duplicates of earlier ones and silently drops
+    /// them.** Thus, `start` must be restored from a persisted offset, e.g.
+    /// the number of committed batches stored along with consumed offsets,
+    /// or the prefix must be unique for every run.
+    pub fn sequence(prefix: impl Into<String>, start: u64) -> Self {
+        Self(TokenKind::Sequence {
+            prefix: prefix.into(),
+            next: start,
+        })
+    }
+
+    /// `{prefix}-{hash}`, where `hash` is a hash of serialized rows of
+    /// the `INSERT`, so the same batch always gets the same token.
+    ///
+    /// The request is started only once the batch is complete.
+    pub fn hash(prefix: impl Into<String>) -> Self {
+        Self(TokenKind::Hash {
+            prefix: prefix.into(),
+        })
+    }
 }
 
 /// Statistics about pending or inserted data.
@@ -47,6 +146,12 @@ impl Quantities {
         rows: 0,
         transactions: 0,
     };
+
+    pub(crate) fn add(&mut self, other: &Quantities) {
+        self.bytes += other.bytes;
+        self.rows += other.rows;
+        self.transactions += other.transactions;
+    }
 }
 
 impl<T> Inserter<T>
@@ -62,10 +167,16 @@ where
             max_rows: u64::MAX,
             send_timeout: None,
             end_timeout: None,
-            insert: None,
+            shards: vec![Shard::new()],
+            next_shard: 0,
             ticks: Ticks::default(),
             pending: Quantities::ZERO,
             in_transaction: false,
+            spool: None,
+            deduplication: None,
+            committed: vec![Quantities::ZERO],
+            in_flight: Vec::new(),
+            in_flight_transactions: 0,
         })
     }
 
@@ -164,11 +275,88 @@ where
         self
     }
 
+    /// Enables a write-ahead spool in the provided directory.
+    ///
+    /// Every row is also appended to a segment file in the directory, one
+    /// file per `INSERT`. The file is synced before ending the `INSERT` and
+    /// removed once the `INSERT` succeeds. Otherwise, it's kept on the disk
+    /// and replayed later, so rows survive both failed `INSERT`s and restarts
+    /// of the application.
+    ///
+    /// Each `INSERT` (including replays) is sent with the segment's
+    /// [`insert_deduplication_token`] (unique, unless
+    /// [`Inserter::with_deduplication_token()`] is used), so rows that have
+    /// actually reached the server before a failure aren't inserted twice. Note that
+    /// non-replicated tables require [`non_replicated_deduplication_window`]
+    /// to be set for that.
+    ///
+    /// Stored segments are replayed on [`Inserter::commit()`] after the first
+    /// successful `INSERT` and after every successful one following a failure.
+    /// Replay errors are not returned from `commit()`, segments are just
+    /// retried next time; call [`Inserter::replay_spool()`] to handle them.
+    ///
+    /// The directory must not be shared with other inserters.
+    /// The spool uses blocking file I/O.
+    ///
+    /// [`insert_deduplication_token`]: https://clickhouse.com/docs/en/operations/settings/settings#insert_deduplication_token
+    /// [`non_replicated_deduplication_window`]: https://clickhouse.com/docs/en/operations/settings/merge-tree-settings#non_replicated_deduplication_window
+    pub fn with_spool(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.set_spool(dir);
+        self
+    }
+
+    /// Attaches an [`insert_deduplication_token`] to every `INSERT`, so
+    /// retrying a batch is idempotent on `Replicated*MergeTree` tables and on
+    /// `MergeTree` tables with [`non_replicated_deduplication_window`] set.
+    ///
+    /// If the spool is enabled, replayed segments get their original tokens.
+    ///
+    /// Unset by default.
+    ///
+    /// [`insert_deduplication_token`]: https://clickhouse.com/docs/en/operations/settings/settings#insert_deduplication_token
+    /// [`non_replicated_deduplication_window`]: https://clickhouse.com/docs/en/operations/settings/merge-tree-settings#non_replicated_deduplication_window
+    pub fn with_deduplication_token(mut self, token: DeduplicationToken) -> Self {
+        self.set_deduplication_token(token);
+        self
+    }
+
+    /// Shards rows across up to `n` concurrent `INSERT`s to increase
+    /// throughput, which is otherwise limited by one HTTP stream.
+    ///
+    /// Rows are distributed among shards in a round-robin manner, so ordering
+    /// is guaranteed only inside a shard. Limits are checked for all shards
+    /// together, and every commit ends `INSERT`s of all shards.
+    ///
+    /// With `n > 1`, commits complete asynchronously, so new rows keep
+    /// flowing while the server processes `INSERT`s: [`Inserter::commit()`]
+    /// only sends the rest of data to all shards and waits for responses to
+    /// the `INSERT`s ended by the previous commit. Thus, returned statistics
+    /// (and errors) are related to the previous commit, counting one
+    /// transaction per commit. [`Inserter::end()`] waits for all `INSERT`s.
+    /// Use [`Inserter::shard_quantities()`] for per-shard statistics.
+    ///
+    /// `1` by default.
+    ///
+    /// # Panics
+    /// If `n` is zero or there are active `INSERT`s.
+    pub fn with_parallelism(mut self, n: usize) -> Self {
+        assert!(n > 0, "parallelism must be positive");
+        assert!(
+            self.shards.iter().all(Shard::is_idle) && self.in_flight.is_empty(),
+            "parallelism cannot be changed with active INSERTs"
+        );
+
+        self.shards = (0..n).map(|_| Shard::new()).collect();
+        self.committed = vec![Quantities::ZERO; n];
+        self.next_shard = 0;
+        self
+    }
+
     /// See [`Inserter::with_timeouts()`].
     pub fn set_timeouts(&mut self, send_timeout: Option<Duration>, end_timeout: Option<Duration>) {
         self.send_timeout = send_timeout;
         self.end_timeout = end_timeout;
-        if let Some(insert) = &mut self.insert {
+        for insert in self.shards.iter_mut().filter_map(|s| s.insert.as_mut()) {
             insert.set_timeouts(self.send_timeout, self.end_timeout);
         }
     }
@@ -195,6 +383,20 @@ where
         self.ticks.reschedule();
     }
 
+    /// See [`Inserter::with_deduplication_token()`].
+    ///
+    /// Applied to new `INSERT`s only.
+    pub fn set_deduplication_token(&mut self, token: DeduplicationToken) {
+        self.deduplication = Some(token);
+    }
+
+    /// See [`Inserter::with_spool()`].
+    ///
+    /// The current `INSERT`, if any, isn't spooled.
+    pub fn set_spool(&mut self, dir: impl Into<PathBuf>) {
+        self.spool = Some(Spool::new(dir.into()));
+    }
+
     /// How much time we have until the next tick.
     ///
     /// `None` if the period isn't configured.
@@ -203,10 +405,19 @@ where
     }
 
     /// Returns statistics about data not yet inserted into ClickHouse.
+    ///
+    /// Data of `INSERT`s waiting for responses isn't included.
     pub fn pending(&self) -> &Quantities {
         &self.pending
     }
 
+    /// Returns statistics about data inserted by the last commit, per shard.
+    ///
+    /// Replayed data of the spool isn't included.
+    pub fn shard_quantities(&self) -> &[Quantities] {
+        &self.committed
+    }
+
     /// Serializes the provided row into an internal buffer.
     ///
     /// To check the limits and send the data to ClickHouse, call
@@ -219,24 +430,86 @@ where
     where
         T: Serialize,
     {
-        if self.insert.is_none() {
-            self.init_insert()?;
+        let index = self.select_shard()?;
+        let shard = &mut self.shards[index];
+        let insert = shard.insert.as_mut().unwrap();
+        let result = insert.do_write(row).and_then(|bytes| {
+            if let Some(segment) = &mut shard.segment {
+                let row = insert.last_written(bytes);
+                segment.append(row).map_err(Error::Spool)?;
+            }
+            Ok(bytes)
+        });
+
+        self.after_write(index, result)
+    }
+
+    /// Same as [`Inserter::write()`], but for an already serialized row.
+    pub(crate) fn write_serialized(&mut self, row: &[u8]) -> Result<()> {
+        let index = self.select_shard()?;
+        let shard = &mut self.shards[index];
+        let insert = shard.insert.as_mut().unwrap();
+        let result = insert.do_write_raw(row).and_then(|bytes| {
+            if let Some(segment) = &mut shard.segment {
+                segment.append(row).map_err(Error::Spool)?;
+            }
+            Ok(bytes)
+        });
+
+        self.after_write(index, result)
+    }
+
+    #[inline]
+    fn select_shard(&mut self) -> Result<usize> {
+        let index = self.next_shard;
+        self.next_shard = (index + 1) % self.shards.len();
+
+        if self.shards[index].insert.is_none() {
+            self.init_insert(index)?;
         }
 
-        match self.insert.as_mut().unwrap().do_write(row) {
-            Ok(bytes) => {
-                self.pending.bytes += bytes as u64;
-                self.pending.rows += 1;
+        Ok(index)
+    }
+
+    fn after_write(&mut self, index: usize, result: Result<usize>) -> Result<()> {
+        let shard = &mut self.shards[index];
 
-                if !self.in_transaction {
-                    self.pending.transactions += 1;
-                    self.in_transaction = true;
+        match result {
+            Ok(bytes) => {
+                for (pending, in_transaction) in [
+                    (&mut self.pending, &mut self.in_transaction),
+                    (&mut shard.pending, &mut shard.in_transaction),
+                ] {
+                    pending.bytes += bytes as u64;
+                    pending.rows += 1;
+
+                    if !*in_transaction {
+                        pending.transactions += 1;
+                        *in_transaction = true;
+                    }
                 }
 
                 Ok(())
             }
             Err(err) => {
-                self.pending = Quantities::ZERO;
+                // If the row is buffered, but not spooled, abort the `INSERT`
+                // to replay only spooled rows. Deferred `INSERT`s can contain
+                // a partially written row, so they're aborted too.
+                if matches!(err, Error::Spool(_)) || shard.hash_prefix.is_some() {
+                    if let (Some(spool), Some(segment)) = (&mut self.spool, shard.segment.take()) {
+                        spool.detach(segment);
+                    }
+                    shard.insert = None;
+                    shard.hash_prefix = None;
+                }
+
+                // Rows of the shard's `INSERT` are lost.
+                let lost = mem::replace(&mut shard.pending, Quantities::ZERO);
+                self.pending.bytes -= lost.bytes;
+                self.pending.rows -= lost.rows;
+                if self.pending.rows == 0 {
+                    self.pending = Quantities::ZERO;
+                }
                 Err(err)
             }
         }
@@ -246,6 +519,9 @@ where
     pub async fn commit(&mut self) -> Result<Quantities> {
         if !self.limits_reached() {
             self.in_transaction = false;
+            for shard in &mut self.shards {
+                shard.in_transaction = false;
+            }
             return Ok(Quantities::ZERO);
         }
 
@@ -254,45 +530,264 @@ where
 
     /// Ends the current `INSERT` unconditionally.
     pub async fn force_commit(&mut self) -> Result<Quantities> {
+        let wait = self.shards.len() == 1;
+        self.do_force_commit(wait).await
+    }
+
+    /// Same as [`Inserter::force_commit()`], but also waits for responses
+    /// to all `INSERT`s regardless of parallelism.
+    pub(crate) async fn force_commit_all(&mut self) -> Result<Quantities> {
+        self.do_force_commit(true).await
+    }
+
+    async fn do_force_commit(&mut self, wait: bool) -> Result<Quantities> {
+        let transactions = self.pending.transactions;
         self.in_transaction = false;
+        self.pending = Quantities::ZERO;
 
-        let quantities = mem::replace(&mut self.pending, Quantities::ZERO);
-        let result = self.insert().await;
+        let result = self.end_shards(transactions, wait).await;
         self.ticks.reschedule();
-        result?;
+        let mut quantities = result?;
+
+        if self.spool.as_ref().is_some_and(Spool::needs_replay) {
+            // Errors are ignored, failed segments are retried next time.
+            if let Ok(replayed) = self.replay_spool().await {
+                quantities.add(&replayed);
+            }
+        }
+
         Ok(quantities)
     }
 
+    /// Inserts rows stored in the spool, see [`Inserter::with_spool()`].
+    ///
+    /// Segments are inserted one by one, each in a separate `INSERT`.
+    /// Stops on the first error, leaving the rest of segments for next time.
+    ///
+    /// Returns statistics about replayed rows.
+    /// Does nothing if the spool isn't enabled.
+    pub async fn replay_spool(&mut self) -> Result<Quantities> {
+        let mut quantities = Quantities::ZERO;
+        let Some(spool) = &mut self.spool else {
+            return Ok(quantities);
+        };
+
+        // Set it back on failure.
+        spool.set_needs_replay(false);
+
+        let result = async {
+            for path in spool.stored().map_err(Error::Spool)? {
+                let stored = spool::read(&path).map_err(Error::Spool)?;
+
+                if stored.rows > 0 {
+                    let mut insert = Insert::<T>::with_sql(&self.client, stored.sql);
+                    insert.set_timeouts(self.send_timeout, self.end_timeout);
+                    let token = match stored.token {
+                        SegmentToken::Literal(token) => token,
+                        SegmentToken::Hash(prefix) => hash_token(&prefix, &stored.data),
+                    };
+                    insert.set_option("insert_deduplication_token", token);
+                    insert.write_raw(&stored.data).await?;
+                    insert.end().await?;
+
+                    quantities.bytes += stored.data.len() as u64;
+                    quantities.rows += stored.rows;
+                    quantities.transactions += 1;
+                }
+
+                fs::remove_file(&stored.path).map_err(Error::Spool)?;
+            }
+            Ok(())
+        }
+        .await;
+
+        if result.is_err() {
+            spool.set_needs_replay(true);
+        }
+
+        result.map(|_| quantities)
+    }
+
     /// Ends the current `INSERT` and whole `Inserter` unconditionally.
     ///
     /// If it isn't called, the current `INSERT` is aborted.
     pub async fn end(mut self) -> Result<Quantities> {
-        self.insert().await?;
-        Ok(self.pending)
+        let transactions = self.pending.transactions;
+        self.end_shards(transactions, true).await
     }
 
-    fn limits_reached(&self) -> bool {
-        self.pending.rows >= self.max_rows
+    pub(crate) fn limits_reached(&self) -> bool {
+        self.server_batching()
+            || self.pending.rows >= self.max_rows
             || self.pending.bytes >= self.max_bytes
             || self.ticks.reached()
     }
 
-    async fn insert(&mut self) -> Result<()> {
-        if let Some(insert) = self.insert.take() {
-            insert.end().await?;
+    fn server_batching(&self) -> bool {
+        let value = self.client.options.get("async_insert");
+        value.is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+    }
+
+    /// Ends `INSERT`s of all shards: sends the rest of data to all shards,
+    /// then waits for responses to `INSERT`s ended by the previous commit.
+    /// If `wait` is set, responses to just ended `INSERT`s are awaited too,
+    /// otherwise they're awaited by the next call.
+    ///
+    /// Returns statistics about data of completed `INSERT`s, counting
+    /// `transactions` of every commit only once.
+    async fn end_shards(&mut self, transactions: u64, wait: bool) -> Result<Quantities> {
+        let mut first_error = None;
+        let previous = mem::take(&mut self.in_flight);
+        let previous_transactions = mem::replace(&mut self.in_flight_transactions, 0);
+        let mut ended = Vec::with_capacity(self.shards.len());
+
+        self.committed.fill(Quantities::ZERO);
+
+        for (index, shard) in self.shards.iter_mut().enumerate() {
+            shard.in_transaction = false;
+
+            let Some(mut insert) = shard.insert.take() else {
+                continue;
+            };
+
+            let quantities = mem::replace(&mut shard.pending, Quantities::ZERO);
+            let mut segment = shard.segment.take();
+
+            if let Some(prefix) = shard.hash_prefix.take() {
+                let token = hash_token(&prefix, insert.buffered());
+                insert.set_option("insert_deduplication_token", token);
+            }
+
+            let sent = match segment.as_mut().map(Segment::sync) {
+                Some(Err(err)) => Err(Error::Spool(err)),
+                _ => insert.send_end().await,
+            };
+
+            if let Err(err) = sent {
+                // Dropping the `INSERT` aborts it.
+                let err = settle(&mut self.spool, segment, Err(err)).unwrap_err();
+                first_error.get_or_insert(err);
+            } else {
+                ended.push(InFlight {
+                    index,
+                    insert,
+                    segment,
+                    quantities,
+                });
+            }
+        }
+
+        let mut total = self
+            .wait_in_flight(previous, previous_transactions, &mut first_error)
+            .await;
+
+        if wait {
+            let completed = self
+                .wait_in_flight(ended, transactions, &mut first_error)
+                .await;
+            total.add(&completed);
+        } else {
+            self.in_flight = ended;
+            self.in_flight_transactions = transactions;
+        }
+
+        match first_error {
+            Some(err) => Err(err),
+            None => Ok(total),
+        }
+    }
+
+    /// Waits for responses to `INSERT`s ended by one commit.
+    async fn wait_in_flight(
+        &mut self,
+        in_flight: Vec<InFlight<T>>,
+        transactions: u64,
+        first_error: &mut Option<Error>,
+    ) -> Quantities {
+        let mut total = Quantities::ZERO;
+
+        for mut shard in in_flight {
+            let res = shard.insert.wait_end().await;
+            match settle(&mut self.spool, shard.segment, res) {
+                Ok(()) => {
+                    total.bytes += shard.quantities.bytes;
+                    total.rows += shard.quantities.rows;
+                    self.committed[shard.index].add(&shard.quantities);
+                }
+                Err(err) => {
+                    first_error.get_or_insert(err);
+                }
+            }
         }
-        Ok(())
+
+        if total.rows > 0 {
+            total.transactions = transactions;
+        }
+
+        total
     }
 
     #[cold]
     #[inline(never)]
-    fn init_insert(&mut self) -> Result<()> {
-        debug_assert!(self.insert.is_none());
-        debug_assert_eq!(self.pending, Quantities::ZERO);
+    fn init_insert(&mut self, index: usize) -> Result<()> {
+        let shard = &mut self.shards[index];
+        debug_assert!(shard.insert.is_none());
+        debug_assert_eq!(shard.pending, Quantities::ZERO);
 
         let mut new_insert: Insert<T> = self.client.insert(&self.table)?;
         new_insert.set_timeouts(self.send_timeout, self.end_timeout);
-        self.insert = Some(new_insert);
+
+        let mut token = match self.deduplication.as_mut().map(|d| &mut d.0) {
+            Some(TokenKind::Sequence { prefix, next }) => {
+                let token = format!("{prefix}-{next}");
+                *next += 1;
+                Some(SegmentToken::Literal(token))
+            }
+            Some(TokenKind::Hash { prefix }) => Some(SegmentToken::Hash(prefix.clone())),
+            None => None,
+        };
+
+        if let Some(spool) = &mut self.spool {
+            let sql = new_insert.sql().expect("the insert isn't started");
+            let segment = spool.begin(sql, token).map_err(Error::Spool)?;
+            token = Some(segment.token().clone());
+            shard.segment = Some(segment);
+        }
+
+        match token {
+            Some(SegmentToken::Literal(token)) => {
+                new_insert.set_option("insert_deduplication_token", token);
+            }
+            Some(SegmentToken::Hash(prefix)) => {
+                // The token is set on ending the `INSERT`.
+                new_insert.defer_request();
+                shard.hash_prefix = Some(prefix);
+            }
+            None => {}
+        }
+
+        shard.insert = Some(new_insert);
         Ok(())
     }
 }
+
+/// Completes or detaches the segment of the ended `INSERT`.
+fn settle(spool: &mut Option<Spool>, segment: Option<Segment>, result: Result<()>) -> Result<()> {
+    let (Some(spool), Some(segment)) = (spool, segment) else {
+        return result;
+    };
+
+    match result {
+        Ok(()) => spool.complete(segment).map_err(Error::Spool),
+        Err(err) => {
+            spool.detach(segment);
+            Err(err)
+        }
+    }
+}
+
+fn hash_token(prefix: &str, data: &[u8]) -> String {
+    // A fixed version of CityHash, so tokens are stable across releases.
+    let hash = cityhash_rs::cityhash_102_128(data);
+    format!("{prefix}-{hash:032x}")
+}
@@ -5,27 +5,97 @@
 #[macro_use]
 extern crate static_assertions;
 
-use self::{error::Result, http_client::HttpClient};
+use self::{error::Result, executor::Executor, http_client::HttpClient};
 use std::{collections::HashMap, fmt::Display, sync::Arc};
 
-pub use self::{compression::Compression, row::Row};
-pub use clickhouse_derive::Row;
+pub use self::{
+    compression::{Compression, HttpCompression},
+    enums::ClickHouseEnum,
+    row::Row,
+};
+pub use clickhouse_derive::{ClickHouseEnum, Row};
+
+/// Checks placeholders of the query at compile time and binds arguments.
+///
+/// Expands `query!(client, sql, args..)` into `client.query(sql)` followed by
+/// [`Query::bind()`] for each positional argument and [`Query::bind_named()`]
+/// for each `name = value` argument. The number of positional arguments must
+/// match the number of `?`, and named ones must match `:name` and `?{name}`
+/// placeholders exactly, otherwise the query doesn't compile.
+///
+/// `?fields` is filled by the row type of `fetch*()`, which is usually
+/// inferred from the context, e.g. from `let rows: Vec<MyRow>`.
+///
+/// # Examples
+/// ```
+/// # async fn example() -> clickhouse::error::Result<()> {
+/// use clickhouse::{Client, Row};
+/// use serde::Deserialize;
+///
+/// #[derive(Row, Deserialize)]
+/// struct Event {
+///     id: u64,
+///     name: String,
+/// }
+///
+/// let client = Client::default();
+/// let (min_id, tenant) = (42, "a");
+///
+/// let events: Vec<Event> = clickhouse::query!(
+///     client,
+///     "SELECT ?fields FROM events WHERE id > ? AND tenant = :tenant",
+///     min_id,
+///     tenant = tenant,
+/// )
+/// .fetch_all()
+/// .await?;
+/// # Ok(()) }
+/// ```
+///
+/// A wrong number of arguments is reported by the compiler:
+/// ```compile_fail
+/// # let client = clickhouse::Client::default();
+/// let query = clickhouse::query!(client, "SELECT ? + ?", 1);
+/// ```
+///
+/// [`Query::bind()`]: crate::query::Query::bind
+/// [`Query::bind_named()`]: crate::query::Query::bind_named
+pub use clickhouse_derive::query;
 
+#[cfg(feature = "inserter")]
+pub mod async_inserter;
+#[cfg(feature = "inserter")]
+pub mod batcher;
+#[cfg(feature = "catalog")]
+pub mod catalog;
 pub mod error;
+pub mod executor;
 pub mod insert;
 #[cfg(feature = "inserter")]
 pub mod inserter;
+#[cfg(feature = "migrate")]
+pub mod migrate;
+#[cfg(feature = "qb")]
+pub mod qb;
 pub mod query;
+#[cfg(feature = "inserter")]
+pub mod routing_inserter;
+pub mod script;
 pub mod serde;
 pub mod sql;
+#[cfg(feature = "catalog")]
+pub mod table;
 #[cfg(feature = "test-util")]
 pub mod test;
+pub mod types;
 #[cfg(feature = "watch")]
 pub mod watch;
 
 mod bytes_ext;
 mod compression;
 mod cursors;
+mod enums;
+mod external;
 mod headers;
 mod http_client;
 mod request_body;
@@ -33,17 +103,22 @@ mod response;
 mod row;
 mod rowbinary;
 #[cfg(feature = "inserter")]
+mod spool;
+#[cfg(feature = "inserter")]
 mod ticks;
 
 /// A client containing HTTP pool.
 #[derive(Clone)]
 pub struct Client {
     http: Arc<dyn HttpClient>,
+    executor: Arc<dyn Executor>,
 
     url: String,
     database: Option<String>,
     authentication: Authentication,
     compression: Compression,
+    http_compression: Option<HttpCompression>,
+    limits: Limits,
     options: HashMap<String, String>,
     headers: HashMap<String, String>,
     products_info: Vec<ProductInfo>,
@@ -72,6 +147,24 @@ pub(crate) enum Authentication {
     },
 }
 
+/// Limits protecting against misbehaving servers and proxies.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Limits {
+    pub(crate) max_error_body_size: usize,
+    pub(crate) max_block_size: usize,
+    pub(crate) max_row_size: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_error_body_size: 1024 * 1024,
+            max_block_size: 1024 * 1024 * 1024,
+            max_row_size: usize::MAX,
+        }
+    }
+}
+
 impl Default for Authentication {
     fn default() -> Self {
         Self::Credentials {
@@ -94,16 +187,33 @@ impl Client {
     pub fn with_http_client(client: impl HttpClient) -> Self {
         Self {
             http: Arc::new(client),
+            executor: Arc::new(executor::TokioExecutor),
             url: String::new(),
             database: None,
             authentication: Authentication::default(),
             compression: Compression::default(),
+            http_compression: None,
+            limits: Limits::default(),
             options: HashMap::new(),
             headers: HashMap::new(),
             products_info: Vec::default(),
         }
     }
 
+    /// Specifies an executor spawning background tasks, e.g. requests of
+    /// `INSERT`s. See [`Executor`] for details.
+    /// By default, [`executor::TokioExecutor`] is used.
+    ///
+    /// # Examples
+    /// ```
+    /// # use clickhouse::{executor::TrackingExecutor, Client};
+    /// let client = Client::default().with_executor(TrackingExecutor::default());
+    /// ```
+    pub fn with_executor(mut self, executor: impl Executor) -> Self {
+        self.executor = Arc::new(executor);
+        self
+    }
+
     /// Specifies ClickHouse's url. Should point to HTTP endpoint.
     ///
     /// # Examples
@@ -221,6 +331,94 @@ impl Client {
         self
     }
 
+    /// Enables compression of HTTP bodies instead of the native compression
+    /// specified by [`Client::with_compression`].
+    /// See [`HttpCompression`] for details.
+    ///
+    /// Sets the [`enable_http_compression`] setting and the `Accept-Encoding`
+    /// header for queries, `INSERT`s are sent with `Content-Encoding`.
+    ///
+    /// [`enable_http_compression`]: https://clickhouse.com/docs/en/operations/settings/settings#enable_http_compression
+    ///
+    /// # Examples
+    /// ```
+    /// # use clickhouse::{Client, HttpCompression};
+    /// # #[cfg(feature = "gzip")]
+    /// let client = Client::default().with_http_compression(HttpCompression::Gzip(6));
+    /// ```
+    pub fn with_http_compression(mut self, compression: HttpCompression) -> Self {
+        self.http_compression = Some(compression);
+        self
+    }
+
+    /// Returns the native compression, which isn't used together with
+    /// the HTTP compression.
+    pub(crate) fn native_compression(&self) -> Compression {
+        if self.http_compression.is_some() {
+            Compression::None
+        } else {
+            self.compression
+        }
+    }
+
+    /// Limits the size of a body of a failed response, decompressed.
+    /// If exceeded, [`Error::LimitExceeded`] is returned instead of
+    /// [`Error::BadResponse`].
+    ///
+    /// 1 MiB by default.
+    ///
+    /// [`Error::LimitExceeded`]: error::Error::LimitExceeded
+    /// [`Error::BadResponse`]: error::Error::BadResponse
+    ///
+    /// # Examples
+    /// ```
+    /// # use clickhouse::Client;
+    /// let client = Client::default().with_max_error_body_size(64 * 1024);
+    /// ```
+    pub fn with_max_error_body_size(mut self, bytes: usize) -> Self {
+        self.limits.max_error_body_size = bytes;
+        self
+    }
+
+    /// Limits the size of one decompressed block of a response, both for
+    /// the native and HTTP compression. If exceeded, reading the response
+    /// fails with [`Error::LimitExceeded`].
+    ///
+    /// Blocks are checked before decompression, so a malicious peer cannot
+    /// make the client allocate more memory than this limit.
+    ///
+    /// 1 GiB by default.
+    ///
+    /// [`Error::LimitExceeded`]: error::Error::LimitExceeded
+    ///
+    /// # Examples
+    /// ```
+    /// # use clickhouse::Client;
+    /// let client = Client::default().with_max_block_size(64 * 1024 * 1024);
+    /// ```
+    pub fn with_max_block_size(mut self, bytes: usize) -> Self {
+        self.limits.max_block_size = bytes;
+        self
+    }
+
+    /// Limits the size of one row (or one line for JSON formats) buffered by
+    /// cursors. If exceeded, reading the response fails with
+    /// [`Error::LimitExceeded`].
+    ///
+    /// Unlimited by default.
+    ///
+    /// [`Error::LimitExceeded`]: error::Error::LimitExceeded
+    ///
+    /// # Examples
+    /// ```
+    /// # use clickhouse::Client;
+    /// let client = Client::default().with_max_row_size(16 * 1024 * 1024);
+    /// ```
+    pub fn with_max_row_size(mut self, bytes: usize) -> Self {
+        self.limits.max_row_size = bytes;
+        self
+    }
+
     /// Used to specify options that will be passed to all queries.
     ///
     /// # Example
@@ -317,11 +515,96 @@ impl Client {
         inserter::Inserter::new(self, table)
     }
 
+    /// Creates an inserter to perform asynchronous INSERTs batched by
+    /// the server, see [`AsyncInserter`].
+    ///
+    /// [`AsyncInserter`]: async_inserter::AsyncInserter
+    #[cfg(feature = "inserter")]
+    pub fn async_inserter<T: Row>(&self, table: &str) -> async_inserter::AsyncInserter<T> {
+        async_inserter::AsyncInserter::new(self, table)
+    }
+
+    /// Creates an inserter to perform multiple INSERTs into tables chosen
+    /// per row by `router`, see [`RoutingInserter`].
+    ///
+    /// [`RoutingInserter`]: routing_inserter::RoutingInserter
+    #[cfg(feature = "inserter")]
+    pub fn routing_inserter<T, F>(&self, router: F) -> routing_inserter::RoutingInserter<T>
+    where
+        T: Row,
+        F: for<'a> Fn(&'a T) -> std::borrow::Cow<'a, str> + Send + Sync + 'static,
+    {
+        routing_inserter::RoutingInserter::new(self, router)
+    }
+
+    /// Creates a batcher to insert rows from multiple tasks through
+    /// a cloneable [`BatchHandle`].
+    ///
+    /// [`BatchHandle`]: batcher::BatchHandle
+    #[cfg(feature = "inserter")]
+    pub fn batcher<T: Row>(&self, table: &str) -> Result<batcher::Batcher<T>> {
+        batcher::Batcher::new(self, table)
+    }
+
     /// Starts a new SELECT/DDL query.
     pub fn query(&self, query: &str) -> query::Query {
         query::Query::new(self, query)
     }
 
+    /// Creates a script of several statements separated by `;`,
+    /// see [`script::Script`] for options.
+    pub fn script(&self, script: &str) -> script::Script {
+        script::Script::new(self, script)
+    }
+
+    /// Executes statements of the script in order, stops on the first error.
+    ///
+    /// Shortcut for `client.script(script).execute()`.
+    ///
+    /// # Examples
+    /// ```
+    /// # async fn example() -> clickhouse::error::Result<()> {
+    /// # let client = clickhouse::Client::default();
+    /// let report = client
+    ///     .execute_script(
+    ///         "CREATE TABLE IF NOT EXISTS t (s String) ENGINE = MergeTree ORDER BY s;
+    ///          INSERT INTO t VALUES ('a;b');",
+    ///     )
+    ///     .await?
+    ///     .into_result()?;
+    /// assert_eq!(report.statements.len(), 2);
+    /// # Ok(()) }
+    /// ```
+    pub async fn execute_script(&self, script: &str) -> Result<script::ScriptReport> {
+        self.script(script).execute().await
+    }
+
+    /// Parses the query once to execute it many times, see [`query::Prepared`].
+    pub fn prepare(&self, query: &str) -> query::Prepared {
+        query::Prepared::new(self, query)
+    }
+
+    /// Provides typed access to `system.*` tables, see [`catalog::Catalog`].
+    #[cfg(feature = "catalog")]
+    pub fn catalog(&self) -> catalog::Catalog {
+        catalog::Catalog::new(self)
+    }
+
+    /// Returns an API to manage the table, see [`table::Table`].
+    ///
+    /// The name can be qualified by a database, e.g. `db.table`. Both parts
+    /// are escaped, so they are used as is, without quotes.
+    #[cfg(feature = "catalog")]
+    pub fn table(&self, name: &str) -> table::Table {
+        table::Table::new(self, name)
+    }
+
+    /// Creates a runner of versioned migrations, see [`migrate`] for details.
+    #[cfg(feature = "migrate")]
+    pub fn migrator(&self) -> migrate::Migrator {
+        migrate::Migrator::new(self)
+    }
+
     /// Starts a new WATCH query.
     ///
     /// The `query` can be either the table name or a SELECT query.
@@ -342,6 +625,9 @@ impl Client {
 /// Do not use it in your code directly, it doesn't follow semver.
 #[doc(hidden)]
 pub mod _priv {
+    pub use crate::enums::{deserialize as deserialize_enum, serialize as serialize_enum};
+    pub use serde;
+
     #[cfg(feature = "lz4")]
     pub fn lz4_compress(uncompressed: &[u8]) -> super::Result<bytes::Bytes> {
         crate::compression::lz4::compress(uncompressed)
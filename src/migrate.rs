@@ -0,0 +1,595 @@
+//! Contains [`Migrator`] to apply versioned schema migrations.
+//!
+//! Migrations are scripts named `NNNN_name.sql`, where `NNNN` is a version.
+//! They are applied in order of versions, each one is recorded along with
+//! a checksum of its content to the `_migrations` table, so that applied
+//! migrations are skipped next time and changes in them are detected.
+//!
+//! # Examples
+//! ```
+//! # async fn example() -> clickhouse::error::Result<()> {
+//! use clickhouse::migrate::Migration;
+//!
+//! let client = clickhouse::Client::default();
+//! let applied = client
+//!     .migrator()
+//!     .with_migrations([
+//!         // Usually, `include_str!("migrations/0001_events.sql")`.
+//!         Migration::parse(
+//!             "0001_events.sql",
+//!             "CREATE TABLE events {on_cluster} (id UInt64) ENGINE = MergeTree ORDER BY id",
+//!         )?,
+//!         Migration::new(2, "add_name", "ALTER TABLE events {on_cluster} ADD COLUMN name String"),
+//!     ])
+//!     .with_cluster("main")
+//!     .run()
+//!     .await?;
+//! # Ok(()) }
+//! ```
+
+use std::{
+    fmt::Write,
+    fs,
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use sha1::{Digest, Sha1};
+
+use crate::{
+    error::{Error, Result},
+    script,
+    sql::{self, TableName},
+    Client,
+};
+
+const DEFAULT_TABLE: &str = "_migrations";
+const DEFAULT_LOCK_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// A single migration, see the [module-level](self) documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    version: u64,
+    name: String,
+    sql: String,
+}
+
+impl Migration {
+    /// Creates a migration with the provided version.
+    pub fn new(version: u64, name: impl Into<String>, sql: impl Into<String>) -> Self {
+        Self {
+            version,
+            name: name.into(),
+            sql: sql.into(),
+        }
+    }
+
+    /// Creates a migration from its file name in the `NNNN_name.sql` form.
+    ///
+    /// Useful for embedded migrations, e.g.
+    /// `Migration::parse("0001_init.sql", include_str!("migrations/0001_init.sql"))`.
+    pub fn parse(file_name: &str, sql: impl Into<String>) -> Result<Self> {
+        let (version, name) = parse_file_name(file_name).ok_or_else(|| {
+            Error::InvalidParams(
+                format!("invalid migration name `{file_name}`, expected `NNNN_name.sql`").into(),
+            )
+        })?;
+
+        Ok(Self::new(version, name, sql))
+    }
+
+    /// The version of the migration.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// The name of the migration without the version and the extension.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The script of the migration as is, without templating.
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    /// The hex-encoded SHA-1 of the script, stored along with the version.
+    pub fn checksum(&self) -> String {
+        let mut hasher = Sha1::new();
+        hasher.update(self.sql.as_bytes());
+        let result = hasher.finalize();
+
+        let mut checksum = String::with_capacity(40);
+        for word in &result[..] {
+            let _ = write!(&mut checksum, "{word:02x}");
+        }
+        checksum
+    }
+}
+
+/// Applies migrations, see [`Client::migrator()`].
+///
+/// Migrations can use the `{on_cluster}` placeholder, which is replaced
+/// with `ON CLUSTER <cluster>` if [`Migrator::with_cluster()`] is used
+/// and with nothing otherwise.
+///
+/// Concurrent runners are serialized by a lock stored in the
+/// `<table>_lock` table: a runner inserts its own row and proceeds only
+/// if its row is the earliest one among unreleased and unexpired rows.
+/// The row is renewed before each migration. Release is an insert too,
+/// the latest row of the owner wins by ReplacingMergeTree, so the same
+/// owner can take the lock again. Expired and released rows are removed
+/// by TTL a day later.
+#[must_use]
+#[derive(Clone)]
+pub struct Migrator {
+    client: Client,
+    migrations: Vec<Migration>,
+    table: String,
+    cluster: Option<String>,
+    owner: String,
+    lock_ttl: Duration,
+}
+
+impl Migrator {
+    pub(crate) fn new(client: &Client) -> Self {
+        Self {
+            client: client.clone(),
+            migrations: Vec::new(),
+            table: DEFAULT_TABLE.into(),
+            cluster: None,
+            owner: default_owner(),
+            lock_ttl: DEFAULT_LOCK_TTL,
+        }
+    }
+
+    /// Adds a migration.
+    pub fn with_migration(mut self, migration: Migration) -> Self {
+        self.migrations.push(migration);
+        self
+    }
+
+    /// Adds several migrations, the order doesn't matter.
+    pub fn with_migrations(mut self, migrations: impl IntoIterator<Item = Migration>) -> Self {
+        self.migrations.extend(migrations);
+        self
+    }
+
+    /// Adds all `*.sql` files of the directory as migrations.
+    ///
+    /// Files must be named `NNNN_name.sql`, other files are ignored.
+    pub fn with_dir(mut self, dir: impl AsRef<Path>) -> Result<Self> {
+        let read_error = |err: std::io::Error| {
+            let dir = dir.as_ref().display();
+            Error::InvalidParams(format!("cannot read migrations from {dir}: {err}").into())
+        };
+
+        for entry in fs::read_dir(dir.as_ref()).map_err(read_error)? {
+            let path = entry.map_err(read_error)?.path();
+            let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+
+            if path.is_file() && file_name.ends_with(".sql") {
+                let sql = fs::read_to_string(&path).map_err(read_error)?;
+                self.migrations.push(Migration::parse(file_name, sql)?);
+            }
+        }
+
+        Ok(self)
+    }
+
+    /// The table to record applied migrations to, `_migrations` by default.
+    ///
+    /// Can be qualified by a database, e.g. `db._migrations`.
+    pub fn with_table(mut self, table: impl Into<String>) -> Self {
+        self.table = table.into();
+        self
+    }
+
+    /// Specifies the cluster for the `{on_cluster}` placeholder.
+    ///
+    /// Service tables are created `ON CLUSTER` too, using replicated engines.
+    pub fn with_cluster(mut self, cluster: impl Into<String>) -> Self {
+        self.cluster = Some(cluster.into());
+        self
+    }
+
+    /// The identifier of this runner in the lock table, random by default.
+    pub fn with_owner(mut self, owner: impl Into<String>) -> Self {
+        self.owner = owner.into();
+        self
+    }
+
+    /// For how long the lock is held without renewal, 10 minutes by default.
+    ///
+    /// The lock is renewed before each migration, so the TTL must exceed
+    /// the duration of the longest migration. If the lock has expired
+    /// anyway, [`Migrator::run()`] fails before the next migration.
+    pub fn with_lock_ttl(mut self, ttl: Duration) -> Self {
+        self.lock_ttl = ttl;
+        self
+    }
+
+    /// Returns migrations that aren't applied yet, in order.
+    ///
+    /// Fails if any applied migration has been changed since then.
+    pub async fn pending(&self) -> Result<Vec<&Migration>> {
+        let migrations = self.sorted()?;
+        let applied = if self.has_table().await? {
+            self.applied().await?
+        } else {
+            Vec::new()
+        };
+
+        pending(&migrations, &applied)
+    }
+
+    /// Renders pending migrations without executing them.
+    ///
+    /// Unlike [`Migrator::run()`], nothing is created, so it's safe to call
+    /// against a database without the migrations table.
+    pub async fn dry_run(&self) -> Result<String> {
+        let mut output = String::new();
+
+        for migration in self.pending().await? {
+            let sql = self.render(migration.sql());
+            let _ = writeln!(
+                &mut output,
+                "-- {:04}_{}.sql",
+                migration.version(),
+                migration.name()
+            );
+            for statement in script::split(&sql)? {
+                let _ = writeln!(&mut output, "{};", statement.sql);
+            }
+            output.push('\n');
+        }
+
+        Ok(output)
+    }
+
+    /// Applies pending migrations in order and returns their versions.
+    ///
+    /// Stops on the first failed statement, previous migrations stay applied.
+    /// Errors of migrations take precedence over errors of releasing the lock.
+    pub async fn run(&self) -> Result<Vec<u64>> {
+        let migrations = self.sorted()?;
+
+        self.create_tables().await?;
+        self.lock().await?;
+        let result = self.apply(&migrations).await;
+        let unlocked = self.unlock().await;
+        let versions = result?;
+        unlocked?;
+        Ok(versions)
+    }
+
+    async fn apply(&self, migrations: &[&Migration]) -> Result<Vec<u64>> {
+        let applied = self.applied().await?;
+        let mut versions = Vec::new();
+
+        for migration in pending(migrations, &applied)? {
+            self.renew_lock().await?;
+
+            let report = self
+                .client
+                .execute_script(&self.render(migration.sql()))
+                .await?;
+
+            if let Some(failed) = report.first_error() {
+                let error = failed.result.as_ref().unwrap_err();
+                return Err(Error::Other(
+                    format!(
+                        "migration {:04}_{} failed at line {}: {error}",
+                        migration.version(),
+                        migration.name(),
+                        failed.line,
+                    )
+                    .into(),
+                ));
+            }
+
+            self.client
+                .query("INSERT INTO ? (version, name, checksum) VALUES (?, ?, ?)")
+                .bind(TableName(&self.table))
+                .bind(migration.version())
+                .bind(migration.name())
+                .bind(migration.checksum())
+                .execute()
+                .await?;
+
+            versions.push(migration.version());
+        }
+
+        Ok(versions)
+    }
+
+    fn sorted(&self) -> Result<Vec<&Migration>> {
+        let mut migrations = self.migrations.iter().collect::<Vec<_>>();
+        migrations.sort_by_key(|m| m.version());
+
+        if let Some(pair) = migrations
+            .windows(2)
+            .find(|pair| pair[0].version() == pair[1].version())
+        {
+            return Err(Error::InvalidParams(
+                format!(
+                    "migrations `{}` and `{}` have the same version {}",
+                    pair[0].name(),
+                    pair[1].name(),
+                    pair[0].version(),
+                )
+                .into(),
+            ));
+        }
+
+        Ok(migrations)
+    }
+
+    fn render(&self, sql: &str) -> String {
+        sql.replace("{on_cluster}", &self.on_cluster())
+    }
+
+    fn on_cluster(&self) -> String {
+        sql::on_cluster(self.cluster.as_deref())
+    }
+
+    fn lock_table(&self) -> String {
+        format!("{}_lock", self.table)
+    }
+
+    async fn has_table(&self) -> Result<bool> {
+        self.client
+            .query("EXISTS TABLE ?")
+            .bind(TableName(&self.table))
+            .fetch_one::<u8>()
+            .await
+            .map(|exists| exists == 1)
+    }
+
+    async fn applied(&self) -> Result<Vec<(u64, String)>> {
+        self.client
+            .query("SELECT version, checksum FROM ? FINAL ORDER BY version")
+            .bind(TableName(&self.table))
+            .fetch_all()
+            .await
+    }
+
+    async fn create_tables(&self) -> Result<()> {
+        let (on_cluster, engine) = match self.cluster {
+            Some(_) => (self.on_cluster(), "ReplicatedReplacingMergeTree"),
+            None => (String::new(), "ReplacingMergeTree"),
+        };
+
+        let script = format!(
+            "CREATE TABLE IF NOT EXISTS {table} {on_cluster} (
+                version UInt64,
+                name String,
+                checksum String,
+                applied_at DateTime64(3) DEFAULT now64(3)
+            ) ENGINE = {engine}(applied_at) ORDER BY version;
+
+            CREATE TABLE IF NOT EXISTS {lock_table} {on_cluster} (
+                owner String,
+                acquired_at DateTime64(6) DEFAULT now64(6),
+                expires_at DateTime64(6),
+                released UInt8 DEFAULT 0,
+                updated_at DateTime64(6) DEFAULT now64(6)
+            ) ENGINE = {engine}(updated_at) ORDER BY owner
+            TTL toDateTime(expires_at) + INTERVAL 1 DAY;",
+            table = escaped(&self.table),
+            lock_table = escaped(&self.lock_table()),
+        );
+
+        self.client
+            .execute_script(&script)
+            .await?
+            .into_result()
+            .map(drop)
+    }
+
+    async fn lock(&self) -> Result<()> {
+        self.client
+            .query(
+                "INSERT INTO ? (owner, expires_at) VALUES (?, now64(6) + toIntervalMillisecond(?))",
+            )
+            .bind(TableName(&self.lock_table()))
+            .bind(&self.owner)
+            .bind(self.lock_ttl.as_millis() as u64)
+            .execute()
+            .await?;
+
+        match self.lock_holder().await? {
+            Some(holder) if holder == self.owner => Ok(()),
+            holder => {
+                // The row expires anyway, so an error of releasing it
+                // isn't reported to keep the reason of the failure.
+                let _ = self.unlock().await;
+                let holder = holder.as_deref().unwrap_or("<unknown>");
+                Err(Error::Other(
+                    format!("migrations are locked by `{holder}`").into(),
+                ))
+            }
+        }
+    }
+
+    // Prolongs the lock keeping `acquired_at`, so it stays the earliest one.
+    async fn renew_lock(&self) -> Result<()> {
+        self.client
+            .query(
+                "INSERT INTO ? (owner, acquired_at, expires_at)
+                 SELECT owner, acquired_at, now64(6) + toIntervalMillisecond(?)
+                 FROM ? FINAL
+                 WHERE owner = ? AND released = 0 AND expires_at > now64(6)",
+            )
+            .bind(TableName(&self.lock_table()))
+            .bind(self.lock_ttl.as_millis() as u64)
+            .bind(TableName(&self.lock_table()))
+            .bind(&self.owner)
+            .execute()
+            .await?;
+
+        match self.lock_holder().await? {
+            Some(holder) if holder == self.owner => Ok(()),
+            _ => Err(Error::Other(
+                format!("the migration lock of `{}` has expired", self.owner).into(),
+            )),
+        }
+    }
+
+    // The earliest active row wins, so all contenders agree on the owner.
+    async fn lock_holder(&self) -> Result<Option<String>> {
+        self.client
+            .query(
+                "SELECT owner FROM ? FINAL
+                 WHERE released = 0 AND expires_at > now64(6)
+                 ORDER BY acquired_at, owner LIMIT 1",
+            )
+            .bind(TableName(&self.lock_table()))
+            .fetch_optional::<String>()
+            .await
+    }
+
+    async fn unlock(&self) -> Result<()> {
+        self.client
+            .query("INSERT INTO ? (owner, expires_at, released) VALUES (?, now64(6), 1)")
+            .bind(TableName(&self.lock_table()))
+            .bind(&self.owner)
+            .execute()
+            .await
+    }
+}
+
+fn pending<'a>(
+    migrations: &[&'a Migration],
+    applied: &[(u64, String)],
+) -> Result<Vec<&'a Migration>> {
+    let mut pending = Vec::new();
+
+    for migration in migrations {
+        match applied
+            .iter()
+            .find(|(version, _)| *version == migration.version())
+        {
+            Some((_, checksum)) if *checksum != migration.checksum() => {
+                return Err(Error::Other(
+                    format!(
+                        "migration {:04}_{} has been changed after it was applied",
+                        migration.version(),
+                        migration.name(),
+                    )
+                    .into(),
+                ));
+            }
+            Some(_) => {}
+            None => pending.push(*migration),
+        }
+    }
+
+    Ok(pending)
+}
+
+// `0001_create_events.sql` -> `(1, "create_events")`.
+fn parse_file_name(file_name: &str) -> Option<(u64, &str)> {
+    let stem = file_name.strip_suffix(".sql")?;
+    let (version, name) = stem.split_once('_')?;
+
+    if version.is_empty() || !version.bytes().all(|c| c.is_ascii_digit()) || name.is_empty() {
+        return None;
+    }
+
+    Some((version.parse().ok()?, name))
+}
+
+// Both `table` and `db.table` are supported.
+fn escaped(table: &str) -> String {
+    let mut escaped = String::new();
+    sql::escape::table(table, &mut escaped).expect("impossible");
+    escaped
+}
+
+fn default_owner() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let counter = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{}-{nanos:x}-{counter}", std::process::id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_file_names() {
+        assert_eq!(parse_file_name("0001_init.sql"), Some((1, "init")));
+        assert_eq!(parse_file_name("20_add_col_x.sql"), Some((20, "add_col_x")));
+        assert_eq!(parse_file_name("0001_init.txt"), None);
+        assert_eq!(parse_file_name("0001.sql"), None);
+        assert_eq!(parse_file_name("0001_.sql"), None);
+        assert_eq!(parse_file_name("v1_init.sql"), None);
+        assert_eq!(parse_file_name("_init.sql"), None);
+
+        assert!(Migration::parse("init.sql", "").is_err());
+    }
+
+    #[test]
+    fn it_calculates_checksum() {
+        let migration = Migration::new(1, "init", "SELECT 1");
+        assert_eq!(
+            migration.checksum(),
+            "42364a017b73ef516a0eca9827e6fa00623257ee"
+        );
+        assert_ne!(
+            Migration::new(1, "init", "SELECT 2").checksum(),
+            migration.checksum()
+        );
+    }
+
+    #[test]
+    fn it_finds_pending() {
+        let a = Migration::new(1, "a", "SELECT 1");
+        let b = Migration::new(2, "b", "SELECT 2");
+        let migrations = [&a, &b];
+
+        let applied = [(1, a.checksum())];
+        assert_eq!(pending(&migrations, &applied).unwrap(), [&b]);
+        assert_eq!(pending(&migrations, &[]).unwrap(), [&a, &b]);
+
+        let changed = [(1, b.checksum())];
+        let err = pending(&migrations, &changed).unwrap_err();
+        assert!(err.to_string().contains("0001_a has been changed"), "{err}");
+    }
+
+    #[test]
+    fn it_renders_on_cluster() {
+        let client = Client::default();
+        let sql = "CREATE TABLE t {on_cluster} (x UInt8)";
+
+        let migrator = Migrator::new(&client);
+        assert_eq!(migrator.render(sql), "CREATE TABLE t  (x UInt8)");
+
+        let migrator = migrator.with_cluster("main");
+        assert_eq!(
+            migrator.render(sql),
+            "CREATE TABLE t ON CLUSTER `main` (x UInt8)"
+        );
+    }
+
+    #[test]
+    fn it_rejects_duplicated_versions() {
+        let migrator = Migrator::new(&Client::default()).with_migrations([
+            Migration::new(2, "b", ""),
+            Migration::new(1, "a", ""),
+            Migration::new(2, "c", ""),
+        ]);
+
+        let err = migrator.sorted().unwrap_err();
+        assert!(err.to_string().contains("same version 2"), "{err}");
+    }
+}
@@ -0,0 +1,115 @@
+use super::{
+    expr::{self, Expr},
+    select,
+};
+use crate::{error::Result, query::Query, sql::Bind, Client};
+
+/// An `ALTER TABLE ... UPDATE/DELETE` mutation, see [`alter_table()`].
+///
+/// Mutations are asynchronous by default,
+/// use `.settings("mutations_sync", 1)` to wait for them.
+///
+/// [`alter_table()`]: super::alter_table
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct Alter {
+    table: Expr,
+    on_cluster: Option<Expr>,
+    updates: Vec<Expr>,
+    delete: bool,
+    filter: Vec<Expr>,
+    settings: Vec<Expr>,
+}
+
+impl Alter {
+    pub(super) fn new(table: &str) -> Self {
+        let mut sql = String::new();
+        Self {
+            table: Expr::atom(expr::write_table(table, &mut sql).map(|_| sql)),
+            on_cluster: None,
+            updates: Vec::new(),
+            delete: false,
+            filter: Vec::new(),
+            settings: Vec::new(),
+        }
+    }
+
+    /// `ON CLUSTER cluster`
+    pub fn on_cluster(mut self, cluster: &str) -> Self {
+        let mut sql = String::new();
+        self.on_cluster = Some(Expr::atom(
+            expr::write_identifier(cluster, &mut sql).map(|_| sql),
+        ));
+        self
+    }
+
+    /// `UPDATE column = value`, can be called several times.
+    pub fn update(mut self, column: &str, value: impl Bind) -> Self {
+        self.updates.push(Expr::column(column).eq(value));
+        self
+    }
+
+    /// `DELETE`
+    pub fn delete(mut self) -> Self {
+        self.delete = true;
+        self
+    }
+
+    /// `WHERE condition`, required by mutations.
+    pub fn filter(mut self, condition: Expr) -> Self {
+        self.filter.push(condition);
+        self
+    }
+
+    /// `SETTINGS name = value`, can be called several times.
+    pub fn settings(mut self, name: &str, value: impl Bind) -> Self {
+        self.settings.push(select::setting(name, value));
+        self
+    }
+
+    /// Renders the query, mostly for debugging.
+    ///
+    /// Errors are returned as [`Error::InvalidParams`].
+    ///
+    /// [`Error::InvalidParams`]: crate::error::Error::InvalidParams
+    pub fn to_sql(&self) -> Result<String> {
+        super::to_result(self.render())
+    }
+
+    /// Creates a query, which can be executed as usual.
+    pub fn query(&self, client: &Client) -> Query {
+        Query::raw(client, self.render())
+    }
+
+    fn render(&self) -> Result<String, String> {
+        let mut sql = String::from("ALTER TABLE ");
+        sql.push_str(self.table.sql()?);
+
+        if let Some(cluster) = &self.on_cluster {
+            sql.push_str(" ON CLUSTER ");
+            sql.push_str(cluster.sql()?);
+        }
+
+        match (self.updates.is_empty(), self.delete) {
+            (false, false) => {
+                sql.push_str(" UPDATE ");
+                for (i, update) in self.updates.iter().enumerate() {
+                    if i > 0 {
+                        sql.push_str(", ");
+                    }
+                    sql.push_str(update.sql()?);
+                }
+            }
+            (true, true) => sql.push_str(" DELETE"),
+            (true, false) => return Err("either UPDATE or DELETE is required".into()),
+            (false, true) => return Err("UPDATE and DELETE cannot be combined".into()),
+        }
+
+        if self.filter.is_empty() {
+            return Err("WHERE is required by mutations".into());
+        }
+        select::write_clause(&mut sql, " WHERE ", &self.filter, " AND ")?;
+        select::write_clause(&mut sql, " SETTINGS ", &self.settings, ", ")?;
+        Ok(sql)
+    }
+}
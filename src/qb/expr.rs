@@ -0,0 +1,236 @@
+use std::fmt::{self, Write};
+
+use crate::sql::{escape, Bind};
+
+/// An SQL expression, e.g. a column, a value or a condition.
+///
+/// Created by [`col()`], [`val()`], [`func()`] and [`raw()`] and combined by
+/// methods, e.g. `col("ts").gt(x).and(col("id").is_in(ids))`.
+///
+/// Errors (e.g. values that cannot be bound) are kept inside and returned
+/// as [`Error::InvalidParams`] by the resulting query.
+///
+/// [`col()`]: super::col
+/// [`val()`]: super::val
+/// [`func()`]: super::func
+/// [`raw()`]: super::raw
+/// [`Error::InvalidParams`]: crate::error::Error::InvalidParams
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct Expr {
+    sql: Result<String, String>,
+    // Requires parentheses when used as an operand.
+    compound: bool,
+}
+
+impl Expr {
+    pub(super) fn atom(sql: Result<String, String>) -> Self {
+        Self {
+            sql,
+            compound: false,
+        }
+    }
+
+    fn compound(sql: Result<String, String>) -> Self {
+        Self {
+            sql,
+            compound: true,
+        }
+    }
+
+    pub(super) fn column(name: &str) -> Self {
+        let mut sql = String::new();
+        Self::atom(write_identifier(name, &mut sql).map(|_| sql))
+    }
+
+    pub(super) fn value(value: impl Bind) -> Self {
+        let mut sql = String::new();
+        Self::atom(value.write(&mut sql).map(|_| sql))
+    }
+
+    pub(super) fn function(name: &str, args: impl IntoIterator<Item = Expr>) -> Self {
+        let mut sql = String::new();
+
+        let result = check_function_name(name).and_then(|_| {
+            sql.push_str(name);
+            sql.push('(');
+            for (i, arg) in args.into_iter().enumerate() {
+                if i > 0 {
+                    sql.push_str(", ");
+                }
+                sql.push_str(arg.sql?.as_str());
+            }
+            sql.push(')');
+            Ok(())
+        });
+
+        Self::atom(result.map(|_| sql))
+    }
+
+    fn binary(self, op: &str, rhs: impl Bind) -> Self {
+        let mut sql = String::new();
+
+        let result = self.write_operand(&mut sql).and_then(|_| {
+            let _ = write!(sql, " {op} ");
+            rhs.write(&mut sql)
+        });
+
+        Self::compound(result.map(|_| sql))
+    }
+
+    fn postfix(self, op: &str) -> Self {
+        let mut sql = String::new();
+        let result = self.write_operand(&mut sql);
+        sql.push_str(op);
+        Self::compound(result.map(|_| sql))
+    }
+
+    /// `self = rhs`
+    pub fn eq(self, rhs: impl Bind) -> Self {
+        self.binary("=", rhs)
+    }
+
+    /// `self != rhs`
+    pub fn ne(self, rhs: impl Bind) -> Self {
+        self.binary("!=", rhs)
+    }
+
+    /// `self > rhs`
+    pub fn gt(self, rhs: impl Bind) -> Self {
+        self.binary(">", rhs)
+    }
+
+    /// `self >= rhs`
+    pub fn ge(self, rhs: impl Bind) -> Self {
+        self.binary(">=", rhs)
+    }
+
+    /// `self < rhs`
+    pub fn lt(self, rhs: impl Bind) -> Self {
+        self.binary("<", rhs)
+    }
+
+    /// `self <= rhs`
+    pub fn le(self, rhs: impl Bind) -> Self {
+        self.binary("<=", rhs)
+    }
+
+    /// `self LIKE pattern`
+    pub fn like(self, pattern: impl Bind) -> Self {
+        self.binary("LIKE", pattern)
+    }
+
+    /// `self IN list`, e.g. for slices and tuples.
+    pub fn is_in(self, list: impl Bind) -> Self {
+        self.binary("IN", list)
+    }
+
+    /// `self NOT IN list`
+    pub fn not_in(self, list: impl Bind) -> Self {
+        self.binary("NOT IN", list)
+    }
+
+    /// `self BETWEEN low AND high`
+    pub fn between(self, low: impl Bind, high: impl Bind) -> Self {
+        let mut sql = String::new();
+
+        let result = self.write_operand(&mut sql).and_then(|_| {
+            sql.push_str(" BETWEEN ");
+            low.write(&mut sql)?;
+            sql.push_str(" AND ");
+            high.write(&mut sql)
+        });
+
+        Self::compound(result.map(|_| sql))
+    }
+
+    /// `self IS NULL`
+    pub fn is_null(self) -> Self {
+        self.postfix(" IS NULL")
+    }
+
+    /// `self IS NOT NULL`
+    pub fn is_not_null(self) -> Self {
+        self.postfix(" IS NOT NULL")
+    }
+
+    /// `self AND rhs`
+    pub fn and(self, rhs: Expr) -> Self {
+        self.binary("AND", rhs)
+    }
+
+    /// `self OR rhs`
+    pub fn or(self, rhs: Expr) -> Self {
+        self.binary("OR", rhs)
+    }
+
+    /// `NOT self`
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Self {
+        let mut sql = String::from("NOT ");
+        let result = self.write_operand(&mut sql);
+        Self::compound(result.map(|_| sql))
+    }
+
+    /// `self AS alias`, e.g. for columns of `SELECT`.
+    pub fn alias(self, alias: &str) -> Self {
+        let mut sql = String::new();
+
+        let result = self.write_operand(&mut sql).and_then(|_| {
+            sql.push_str(" AS ");
+            write_identifier(alias, &mut sql)
+        });
+
+        Self::compound(result.map(|_| sql))
+    }
+
+    /// `self ASC` for `ORDER BY`.
+    pub fn asc(self) -> Self {
+        self.postfix(" ASC")
+    }
+
+    /// `self DESC` for `ORDER BY`.
+    pub fn desc(self) -> Self {
+        self.postfix(" DESC")
+    }
+
+    /// Returns SQL of the expression, wrapped into parentheses if needed.
+    pub(crate) fn write_operand(&self, dst: &mut impl fmt::Write) -> Result<(), String> {
+        let sql = self.sql.as_deref().map_err(Clone::clone)?;
+
+        if self.compound {
+            write!(dst, "({sql})").map_err(|err| err.to_string())
+        } else {
+            dst.write_str(sql).map_err(|err| err.to_string())
+        }
+    }
+
+    /// Returns SQL of the expression as is.
+    pub(super) fn sql(&self) -> Result<&str, String> {
+        self.sql.as_deref().map_err(Clone::clone)
+    }
+}
+
+pub(super) fn write_identifier(name: &str, dst: &mut impl fmt::Write) -> Result<(), String> {
+    escape::identifier(name, dst).map_err(|err| err.to_string())
+}
+
+// Both `table` and `database.table` are supported.
+pub(super) fn write_table(name: &str, dst: &mut impl fmt::Write) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("empty identifier".into());
+    }
+
+    escape::table(name, dst).map_err(|err| err.to_string())
+}
+
+fn check_function_name(name: &str) -> Result<(), String> {
+    let is_valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if is_valid {
+        Ok(())
+    } else {
+        Err(format!("invalid function name: {name:?}"))
+    }
+}
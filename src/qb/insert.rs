@@ -0,0 +1,71 @@
+use super::{
+    expr::{self, Expr},
+    select::Select,
+};
+use crate::{error::Result, query::Query, row::Row, Client};
+
+/// An `INSERT INTO ... SELECT` query, see [`insert_into()`].
+///
+/// To insert rows from the client, use [`Client::insert()`] instead.
+///
+/// [`insert_into()`]: super::insert_into
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct InsertSelect {
+    table: Expr,
+    columns: Vec<Expr>,
+    select: Option<Select>,
+}
+
+impl InsertSelect {
+    pub(super) fn new<T: Row>(table: &str) -> Self {
+        let mut sql = String::new();
+        Self {
+            table: Expr::atom(expr::write_table(table, &mut sql).map(|_| sql)),
+            columns: T::COLUMN_NAMES.iter().map(|c| Expr::column(c)).collect(),
+            select: None,
+        }
+    }
+
+    /// Specifies the query producing rows to insert.
+    pub fn select(mut self, select: Select) -> Self {
+        self.select = Some(select);
+        self
+    }
+
+    /// Renders the query, mostly for debugging.
+    ///
+    /// Errors are returned as [`Error::InvalidParams`].
+    ///
+    /// [`Error::InvalidParams`]: crate::error::Error::InvalidParams
+    pub fn to_sql(&self) -> Result<String> {
+        super::to_result(self.render())
+    }
+
+    /// Creates a query, which can be executed as usual.
+    pub fn query(&self, client: &Client) -> Query {
+        Query::raw(client, self.render())
+    }
+
+    fn render(&self) -> Result<String, String> {
+        let select = self.select.as_ref().ok_or("SELECT is required")?;
+
+        let mut sql = String::from("INSERT INTO ");
+        sql.push_str(self.table.sql()?);
+
+        if !self.columns.is_empty() {
+            sql.push_str(" (");
+            for (i, column) in self.columns.iter().enumerate() {
+                if i > 0 {
+                    sql.push_str(", ");
+                }
+                sql.push_str(column.sql()?);
+            }
+            sql.push(')');
+        }
+
+        sql.push(' ');
+        sql.push_str(&select.render()?);
+        Ok(sql)
+    }
+}
@@ -0,0 +1,249 @@
+//! A composable builder of `SELECT`, `INSERT ... SELECT` and `ALTER` queries.
+//!
+//! Identifiers are escaped and values are bound the same way as by
+//! [`Query::bind()`], so dynamic conditions can be built without string
+//! concatenation. The result is a usual [`Query`].
+//!
+//! Requires the `qb` feature.
+//!
+//! # Examples
+//! ```
+//! # async fn example() -> clickhouse::error::Result<()> {
+//! use clickhouse::{qb::{self, col, func}, Client, Row};
+//! use serde::Deserialize;
+//!
+//! #[derive(Row, Deserialize)]
+//! struct Event {
+//!     tenant: String,
+//!     ts: u32,
+//! }
+//!
+//! # let (since, tenants) = (0u32, ["a", "b"]);
+//! let client = Client::default();
+//! let mut select = qb::select::<Event>()
+//!     .from("events")
+//!     .prewhere(col("ts").gt(since))
+//!     .order_by(col("ts").desc())
+//!     .limit_by(10, [col("tenant")])
+//!     .settings("max_threads", 8);
+//!
+//! if !tenants.is_empty() {
+//!     select = select.filter(col("tenant").is_in(&tenants[..]));
+//! }
+//!
+//! let events = select.query(&client).fetch_all::<Event>().await?;
+//!
+//! // Rows of primitive types require explicit columns.
+//! let counts = qb::select::<(String, u64)>()
+//!     .column(col("tenant"))
+//!     .column(func("count", []))
+//!     .from("events")
+//!     .group_by(col("tenant"))
+//!     .query(&client)
+//!     .fetch_all::<(String, u64)>()
+//!     .await?;
+//! # Ok(()) }
+//! ```
+//!
+//! [`Query::bind()`]: crate::query::Query::bind
+//! [`Query`]: crate::query::Query
+
+use crate::{
+    error::{Error, Result},
+    row::Row,
+    sql::Bind,
+};
+
+pub use self::{alter::Alter, expr::Expr, insert::InsertSelect, select::Select};
+
+mod alter;
+mod expr;
+mod insert;
+mod select;
+
+/// Starts a `SELECT` query of fields of the row type.
+///
+/// For rows of primitive types, columns must be added by
+/// [`Select::column()`].
+pub fn select<T: Row>() -> Select {
+    Select::new::<T>()
+}
+
+/// Starts an `INSERT INTO table (fields) SELECT ...` query.
+pub fn insert_into<T: Row>(table: &str) -> InsertSelect {
+    InsertSelect::new::<T>(table)
+}
+
+/// Starts an `ALTER TABLE table UPDATE/DELETE` mutation.
+pub fn alter_table(table: &str) -> Alter {
+    Alter::new(table)
+}
+
+/// A column, escaped as an identifier.
+pub fn col(name: &str) -> Expr {
+    Expr::column(name)
+}
+
+/// A value, escaped as by [`Query::bind()`].
+///
+/// [`Query::bind()`]: crate::query::Query::bind
+pub fn val(value: impl Bind) -> Expr {
+    Expr::value(value)
+}
+
+/// A function call, e.g. `func("toDate", [col("ts")])`.
+pub fn func(name: &str, args: impl IntoIterator<Item = Expr>) -> Expr {
+    Expr::function(name, args)
+}
+
+/// A trusted SQL expression, used as is.
+///
+/// Never pass user input here, use [`val()`] instead.
+pub fn raw(sql: impl Into<String>) -> Expr {
+    Expr::atom(Ok(sql.into()))
+}
+
+fn to_result(sql: Result<String, String>) -> Result<String> {
+    sql.map_err(|err| Error::InvalidParams(format!("invalid SQL: {err}").into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // XXX: need for `derive(Row)`. Provide `row(crate = ..)` instead.
+    use crate as clickhouse;
+    use clickhouse_derive::Row;
+
+    #[allow(unused)]
+    #[derive(Row)]
+    struct Event {
+        tenant: String,
+        ts: u32,
+    }
+
+    #[test]
+    fn select_all_clauses() {
+        let sql = select::<Event>()
+            .column(func("count", []).alias("n"))
+            .from("db.events")
+            .final_()
+            .sample(0.1)
+            .array_join(col("tags"))
+            .prewhere(col("ts").gt(100))
+            .filter(col("tenant").is_in(&["a", "b'c"][..]))
+            .filter(col("x").eq(1).or(col("y").is_null()))
+            .group_by(col("tenant"))
+            .group_by(col("ts"))
+            .having(func("count", []).ge(2))
+            .order_by(col("ts").desc())
+            .limit_by(3, [col("tenant")])
+            .limit(10)
+            .offset(20)
+            .settings("max_threads", 8)
+            .to_sql()
+            .unwrap();
+
+        assert_eq!(
+            sql,
+            "SELECT `tenant`, `ts`, count() AS `n` FROM `db`.`events` FINAL SAMPLE 0.1 \
+             ARRAY JOIN `tags` PREWHERE `ts` > 100 \
+             WHERE (`tenant` IN ['a','b\\'c']) AND ((`x` = 1) OR (`y` IS NULL)) \
+             GROUP BY `tenant`, `ts` HAVING count() >= 2 ORDER BY `ts` DESC \
+             LIMIT 3 BY `tenant` LIMIT 10 OFFSET 20 SETTINGS max_threads = 8"
+        );
+    }
+
+    #[test]
+    fn select_values_with_placeholders() {
+        // Values aren't parsed as placeholders of `Query`.
+        let sql = select::<Event>()
+            .from("events")
+            .filter(col("tenant").eq("?fields :a ?{b}"))
+            .filter(col("ts").between(1, val(2)))
+            .filter(col("tenant").like("a%").not())
+            .to_sql()
+            .unwrap();
+
+        assert_eq!(
+            sql,
+            "SELECT `tenant`, `ts` FROM `events` \
+             WHERE (`tenant` = '?fields :a ?{b}') AND (`ts` BETWEEN 1 AND 2) \
+             AND (NOT (`tenant` LIKE 'a%'))"
+        );
+    }
+
+    #[test]
+    fn insert_select() {
+        let sql = insert_into::<Event>("copy")
+            .select(select::<Event>().from("events").filter(col("ts").lt(5)))
+            .to_sql()
+            .unwrap();
+
+        assert_eq!(
+            sql,
+            "INSERT INTO `copy` (`tenant`, `ts`) SELECT `tenant`, `ts` FROM `events` WHERE `ts` < 5"
+        );
+    }
+
+    #[test]
+    fn alter() {
+        let sql = alter_table("events")
+            .on_cluster("main")
+            .update("ts", col("ts").gt(0))
+            .update("tenant", "x")
+            .filter(col("tenant").ne(""))
+            .settings("mutations_sync", 2)
+            .to_sql()
+            .unwrap();
+
+        assert_eq!(
+            sql,
+            "ALTER TABLE `events` ON CLUSTER `main` UPDATE `ts` = (`ts` > 0), \
+             `tenant` = 'x' WHERE `tenant` != '' SETTINGS mutations_sync = 2"
+        );
+
+        let sql = alter_table("events")
+            .delete()
+            .filter(col("ts").lt(5))
+            .to_sql()
+            .unwrap();
+        assert_eq!(sql, "ALTER TABLE `events` DELETE WHERE `ts` < 5");
+    }
+
+    #[test]
+    fn failures() {
+        fn check(sql: Result<String>, expected: &str) {
+            let err = sql.unwrap_err();
+            assert!(matches!(err, Error::InvalidParams(_)));
+            assert!(err.to_string().contains(expected), "{err}");
+        }
+
+        check(select::<String>().from("t").to_sql(), "no columns");
+        check(select::<Event>().to_sql(), "FROM is required");
+        check(
+            select::<Event>().from("t").offset(1).to_sql(),
+            "OFFSET requires LIMIT",
+        );
+        check(
+            select::<Event>()
+                .from("t")
+                .settings("a = 1; DROP", 1)
+                .to_sql(),
+            "invalid setting name",
+        );
+        check(
+            select::<Event>()
+                .column(func("evil()", []))
+                .from("t")
+                .to_sql(),
+            "invalid function name",
+        );
+        check(alter_table("t").delete().to_sql(), "WHERE is required");
+        check(
+            alter_table("t").filter(raw("1")).to_sql(),
+            "either UPDATE or DELETE",
+        );
+        check(insert_into::<Event>("t").to_sql(), "SELECT is required");
+    }
+}
@@ -0,0 +1,255 @@
+use std::fmt::Write;
+
+use super::expr::{self, Expr};
+use crate::{error::Result, query::Query, row::Row, sql::Bind, Client};
+
+/// A `SELECT` query, see [`select()`].
+///
+/// Conditions passed to `filter`, `prewhere` and `having` several times
+/// are combined by `AND`.
+///
+/// [`select()`]: super::select
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct Select {
+    columns: Vec<Expr>,
+    from: Option<Expr>,
+    is_final: bool,
+    sample: Option<Expr>,
+    array_joins: Vec<(&'static str, Expr)>,
+    prewhere: Vec<Expr>,
+    filter: Vec<Expr>,
+    group_by: Vec<Expr>,
+    having: Vec<Expr>,
+    order_by: Vec<Expr>,
+    limit_by: Option<(u64, Vec<Expr>)>,
+    limit: Option<u64>,
+    offset: Option<u64>,
+    settings: Vec<Expr>,
+}
+
+impl Select {
+    pub(super) fn new<T: Row>() -> Self {
+        Self {
+            columns: T::COLUMN_NAMES.iter().map(|c| Expr::column(c)).collect(),
+            from: None,
+            is_final: false,
+            sample: None,
+            array_joins: Vec::new(),
+            prewhere: Vec::new(),
+            filter: Vec::new(),
+            group_by: Vec::new(),
+            having: Vec::new(),
+            order_by: Vec::new(),
+            limit_by: None,
+            limit: None,
+            offset: None,
+            settings: Vec::new(),
+        }
+    }
+
+    /// Adds a column after fields of the row type, e.g. `count()` for
+    /// `(SomeRow, u64)`. Required for rows of primitive types.
+    pub fn column(mut self, column: Expr) -> Self {
+        self.columns.push(column);
+        self
+    }
+
+    /// `FROM table`, `database.table` is also supported.
+    pub fn from(mut self, table: &str) -> Self {
+        let mut sql = String::new();
+        self.from = Some(Expr::atom(expr::write_table(table, &mut sql).map(|_| sql)));
+        self
+    }
+
+    /// `FINAL` modifier of `FROM`.
+    pub fn final_(mut self) -> Self {
+        self.is_final = true;
+        self
+    }
+
+    /// `SAMPLE ratio`, e.g. `0.1` or `10000` (rows).
+    pub fn sample(mut self, ratio: impl Bind) -> Self {
+        self.sample = Some(Expr::value(ratio));
+        self
+    }
+
+    /// `ARRAY JOIN array`
+    pub fn array_join(mut self, array: Expr) -> Self {
+        self.array_joins.push(("ARRAY JOIN", array));
+        self
+    }
+
+    /// `LEFT ARRAY JOIN array`
+    pub fn left_array_join(mut self, array: Expr) -> Self {
+        self.array_joins.push(("LEFT ARRAY JOIN", array));
+        self
+    }
+
+    /// `PREWHERE condition`
+    pub fn prewhere(mut self, condition: Expr) -> Self {
+        self.prewhere.push(condition);
+        self
+    }
+
+    /// `WHERE condition`
+    pub fn filter(mut self, condition: Expr) -> Self {
+        self.filter.push(condition);
+        self
+    }
+
+    /// `GROUP BY key`, can be called several times.
+    pub fn group_by(mut self, key: Expr) -> Self {
+        self.group_by.push(key);
+        self
+    }
+
+    /// `HAVING condition`
+    pub fn having(mut self, condition: Expr) -> Self {
+        self.having.push(condition);
+        self
+    }
+
+    /// `ORDER BY key`, can be called several times.
+    /// Use [`Expr::desc()`] for the descending order.
+    pub fn order_by(mut self, key: Expr) -> Self {
+        self.order_by.push(key);
+        self
+    }
+
+    /// `LIMIT limit BY keys`
+    pub fn limit_by(mut self, limit: u64, keys: impl IntoIterator<Item = Expr>) -> Self {
+        self.limit_by = Some((limit, keys.into_iter().collect()));
+        self
+    }
+
+    /// `LIMIT limit`
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// `OFFSET offset`, requires [`Select::limit()`].
+    pub fn offset(mut self, offset: u64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// `SETTINGS name = value`, can be called several times.
+    pub fn settings(mut self, name: &str, value: impl Bind) -> Self {
+        self.settings.push(setting(name, value));
+        self
+    }
+
+    /// Renders the query, mostly for debugging.
+    ///
+    /// Errors are returned as [`Error::InvalidParams`].
+    ///
+    /// [`Error::InvalidParams`]: crate::error::Error::InvalidParams
+    pub fn to_sql(&self) -> Result<String> {
+        super::to_result(self.render())
+    }
+
+    /// Creates a query, which can be fetched as usual.
+    pub fn query(&self, client: &Client) -> Query {
+        Query::raw(client, self.render())
+    }
+
+    pub(super) fn render(&self) -> Result<String, String> {
+        let mut sql = String::from("SELECT ");
+
+        if self.columns.is_empty() {
+            return Err("no columns to select".into());
+        }
+        write_list(&mut sql, &self.columns, ", ")?;
+
+        let from = self.from.as_ref().ok_or("FROM is required")?;
+        sql.push_str(" FROM ");
+        sql.push_str(from.sql()?);
+
+        if self.is_final {
+            sql.push_str(" FINAL");
+        }
+
+        if let Some(sample) = &self.sample {
+            sql.push_str(" SAMPLE ");
+            sql.push_str(sample.sql()?);
+        }
+
+        for (kind, array) in &self.array_joins {
+            let _ = write!(sql, " {kind} {}", array.sql()?);
+        }
+
+        write_clause(&mut sql, " PREWHERE ", &self.prewhere, " AND ")?;
+        write_clause(&mut sql, " WHERE ", &self.filter, " AND ")?;
+        write_clause(&mut sql, " GROUP BY ", &self.group_by, ", ")?;
+        write_clause(&mut sql, " HAVING ", &self.having, " AND ")?;
+        write_clause(&mut sql, " ORDER BY ", &self.order_by, ", ")?;
+
+        if let Some((limit, keys)) = &self.limit_by {
+            if keys.is_empty() {
+                return Err("LIMIT BY requires keys".into());
+            }
+            let _ = write!(sql, " LIMIT {limit} BY ");
+            write_list(&mut sql, keys, ", ")?;
+        }
+
+        match (self.limit, self.offset) {
+            (Some(limit), Some(offset)) => {
+                let _ = write!(sql, " LIMIT {limit} OFFSET {offset}");
+            }
+            (Some(limit), None) => {
+                let _ = write!(sql, " LIMIT {limit}");
+            }
+            (None, Some(_)) => return Err("OFFSET requires LIMIT".into()),
+            (None, None) => {}
+        }
+
+        write_clause(&mut sql, " SETTINGS ", &self.settings, ", ")?;
+        Ok(sql)
+    }
+}
+
+pub(super) fn setting(name: &str, value: impl Bind) -> Expr {
+    let is_valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    let mut sql = String::new();
+    let result = if is_valid {
+        sql.push_str(name);
+        sql.push_str(" = ");
+        value.write(&mut sql)
+    } else {
+        Err(format!("invalid setting name: {name:?}"))
+    };
+
+    Expr::atom(result.map(|_| sql))
+}
+
+pub(super) fn write_clause(
+    sql: &mut String,
+    clause: &str,
+    exprs: &[Expr],
+    sep: &str,
+) -> Result<(), String> {
+    if !exprs.is_empty() {
+        sql.push_str(clause);
+        write_list(sql, exprs, sep)?;
+    }
+    Ok(())
+}
+
+// Operands are wrapped into parentheses only if combined by `AND`.
+fn write_list(sql: &mut String, exprs: &[Expr], sep: &str) -> Result<(), String> {
+    for (i, expr) in exprs.iter().enumerate() {
+        if i > 0 {
+            sql.push_str(sep);
+        }
+
+        if exprs.len() > 1 && sep == " AND " {
+            expr.write_operand(sql)?;
+        } else {
+            sql.push_str(expr.sql()?);
+        }
+    }
+    Ok(())
+}
@@ -1,20 +1,26 @@
-use hyper::{header::CONTENT_LENGTH, Method, Request};
+use hyper::{
+    header::{ACCEPT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE},
+    Method, Request,
+};
 use serde::{Deserialize, Serialize};
-use std::fmt::Display;
+use std::{fmt::Display, sync::Arc};
 use url::Url;
 
 use crate::{
     error::{Error, Result},
+    external::{self, ExternalTable},
     headers::with_request_headers,
     request_body::RequestBody,
     response::Response,
     row::Row,
-    sql::{ser, Bind, SqlBuilder},
+    sql::{self, ser, Bind, SqlBuilder},
     Client,
 };
 
 const MAX_QUERY_LEN_TO_USE_GET: usize = 8192;
 
+#[cfg(feature = "json")]
+pub use crate::cursors::JsonCursor;
 pub use crate::cursors::{BytesCursor, RowCursor};
 use crate::headers::with_authentication;
 
@@ -23,6 +29,9 @@ use crate::headers::with_authentication;
 pub struct Query {
     client: Client,
     sql: SqlBuilder,
+    // Names of `{name: Type}` params, checked before sending.
+    server_params: Arc<[String]>,
+    external_tables: Vec<ExternalTable>,
 }
 
 impl Query {
@@ -30,6 +39,19 @@ impl Query {
         Self {
             client: client.clone(),
             sql: SqlBuilder::new(template),
+            server_params: sql::server_params(template).into(),
+            external_tables: Vec::new(),
+        }
+    }
+
+    /// Creates a query from SQL without placeholders, e.g. rendered by `qb`
+    /// or a statement of a script.
+    pub(crate) fn raw(client: &Client, sql: std::result::Result<String, String>) -> Self {
+        Self {
+            client: client.clone(),
+            server_params: sql.as_deref().map_or(Vec::new(), sql::server_params).into(),
+            sql: SqlBuilder::raw(sql),
+            external_tables: Vec::new(),
         }
     }
 
@@ -57,6 +79,31 @@ impl Query {
         self
     }
 
+    /// Binds `value` to all occurrences of the named placeholder in the
+    /// query, written as `:name` or `?{name}`.
+    ///
+    /// Escaping and errors are the same as for [`Query::bind()`].
+    /// `:name` isn't recognized inside quotes, comments, `::` and `.:` casts
+    /// and `{name:Type}` of server-side parameters, use `?{name}` there
+    /// if needed.
+    ///
+    /// # Examples
+    /// ```
+    /// # async fn example() -> clickhouse::error::Result<()> {
+    /// # let client = clickhouse::Client::default();
+    /// client
+    ///     .query("SELECT count() FROM events WHERE tenant = :tenant OR owner = :tenant")
+    ///     .bind_named("tenant", "acme")
+    ///     .execute()
+    ///     .await?;
+    /// # Ok(()) }
+    /// ```
+    #[track_caller]
+    pub fn bind_named(mut self, name: &str, value: impl Bind) -> Self {
+        self.sql.bind_named(name, value);
+        self
+    }
+
     pub fn bind_ref(&mut self, value: impl Bind) {
         self.sql.bind_arg(value);
     }
@@ -91,8 +138,9 @@ impl Query {
         self.sql.bind_fields::<T>();
         self.sql.set_output_format("RowBinary");
 
+        let max_row_size = self.client.limits.max_row_size;
         let response = self.do_execute(true)?;
-        Ok(RowCursor::new(response))
+        Ok(RowCursor::new(response, max_row_size))
     }
 
     /// Executes the query and returns just a single row.
@@ -147,9 +195,77 @@ impl Query {
         Ok(BytesCursor::new(response))
     }
 
+    /// Executes the query, returning a [`JsonCursor`] to obtain results
+    /// deserialized from the [`JSONEachRow`] format.
+    ///
+    /// Each row is a JSON object keyed by column names, so `T` is expected
+    /// to be a struct (or a map). The `?fields` placeholder is supported.
+    ///
+    /// 64-bit integers are requested unquoted, so they can be deserialized
+    /// into integer types directly.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # async fn example() -> clickhouse::error::Result<()> {
+    /// #[derive(clickhouse::Row, serde::Deserialize)]
+    /// struct MyRow {
+    ///     no: u32,
+    ///     payload: serde_json::Value,
+    /// }
+    ///
+    /// let mut cursor = clickhouse::Client::default()
+    ///     .query("SELECT ?fields FROM some WHERE no BETWEEN 0 AND 1")
+    ///     .fetch_json::<MyRow>()?;
+    ///
+    /// while let Some(MyRow { no, payload }) = cursor.next().await? {
+    ///     println!("{no}: {payload}");
+    /// }
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// [`JSONEachRow`]: https://clickhouse.com/docs/en/interfaces/formats#jsoneachrow
+    #[cfg(feature = "json")]
+    pub fn fetch_json<T: Row>(self) -> Result<JsonCursor<T>> {
+        self.fetch_json_with_format("JSONEachRow")
+    }
+
+    /// Similar to [`Query::fetch_json`], but uses the [`JSONCompactEachRow`]
+    /// format, where each row is a JSON array of values in the column order.
+    ///
+    /// It's more compact than `JSONEachRow`, and tuples can be used as rows.
+    ///
+    /// [`JSONCompactEachRow`]: https://clickhouse.com/docs/en/interfaces/formats#jsoncompacteachrow
+    #[cfg(feature = "json")]
+    pub fn fetch_json_compact<T: Row>(self) -> Result<JsonCursor<T>> {
+        self.fetch_json_with_format("JSONCompactEachRow")
+    }
+
+    #[cfg(feature = "json")]
+    fn fetch_json_with_format<T: Row>(mut self, format: &str) -> Result<JsonCursor<T>> {
+        self.sql.bind_fields::<T>();
+        self.sql.set_output_format(format);
+
+        let max_row_size = self.client.limits.max_row_size;
+        let response = self
+            .with_option("output_format_json_quote_64bit_integers", "0")
+            .do_execute(true)?;
+        Ok(JsonCursor::new(response, max_row_size))
+    }
+
     pub(crate) fn do_execute(self, read_only: bool) -> Result<Response> {
         let query = self.sql.finish()?;
 
+        if let Some(name) = self
+            .server_params
+            .iter()
+            .find(|name| !self.client.options.contains_key(&format!("param_{name}")))
+        {
+            return Err(Error::InvalidParams(
+                format!("missing server-side parameter {{{name}: ..}}, use `param()`").into(),
+            ));
+        }
+
         let mut url =
             Url::parse(&self.client.url).map_err(|err| Error::InvalidParams(Box::new(err)))?;
         let mut pairs = url.query_pairs_mut();
@@ -160,8 +276,26 @@ impl Query {
         }
 
         let use_post = !read_only || query.len() > MAX_QUERY_LEN_TO_USE_GET;
+        let mut content_type = None;
+
+        // External tables are sent as `multipart/form-data`,
+        // thus the query is passed in the URL.
+        let (method, body, content_length) = if !self.external_tables.is_empty() {
+            if read_only {
+                pairs.append_pair("readonly", "1");
+            }
+            pairs.append_pair("query", &query);
+
+            for table in &self.external_tables {
+                pairs.append_pair(&format!("{}_structure", table.name), &table.structure);
+                pairs.append_pair(&format!("{}_format", table.name), "RowBinary");
+            }
 
-        let (method, body, content_length) = if use_post {
+            let (boundary, body) = external::encode_multipart(&self.external_tables);
+            content_type = Some(format!("multipart/form-data; boundary={boundary}"));
+            let len = body.len();
+            (Method::POST, RequestBody::full(body), len)
+        } else if use_post {
             if read_only {
                 pairs.append_pair("readonly", "1");
             }
@@ -172,8 +306,19 @@ impl Query {
             (Method::GET, RequestBody::empty(), 0)
         };
 
-        if self.client.compression.is_lz4() {
+        let compression = self.client.native_compression();
+        if compression.is_native() {
             pairs.append_pair("compress", "1");
+
+            #[cfg(feature = "zstd")]
+            if let crate::Compression::Zstd(level) = compression {
+                pairs.append_pair("network_compression_method", "ZSTD");
+                pairs.append_pair("network_zstd_compression_level", &level.to_string());
+            }
+        }
+
+        if self.client.http_compression.is_some() {
+            pairs.append_pair("enable_http_compression", "1");
         }
 
         for (name, value) in &self.client.options {
@@ -185,6 +330,14 @@ impl Query {
         builder = with_request_headers(builder, &self.client.headers, &self.client.products_info);
         builder = with_authentication(builder, &self.client.authentication);
 
+        if let Some(http_compression) = &self.client.http_compression {
+            builder = builder.header(ACCEPT_ENCODING, http_compression.encoding());
+        }
+
+        if let Some(content_type) = content_type {
+            builder = builder.header(CONTENT_TYPE, content_type);
+        }
+
         if content_length == 0 {
             builder = builder.header(CONTENT_LENGTH, "0");
         } else {
@@ -196,7 +349,7 @@ impl Query {
             .map_err(|err| Error::InvalidParams(Box::new(err)))?;
 
         let future = self.client.http.request(request);
-        Ok(Response::new(future, self.client.compression))
+        Ok(Response::new(future, compression, self.client.limits))
     }
 
     /// Similar to [`Client::with_option`], but for this particular query only.
@@ -205,9 +358,80 @@ impl Query {
         self
     }
 
+    /// Sends `rows` as a temporary table `name` available only in this query,
+    /// e.g. for `WHERE id IN name` or `JOIN name USING (id)` with large sets
+    /// of values, which are inefficient to bind into the query text.
+    ///
+    /// Rows are encoded in `RowBinary` and sent as [external data].
+    /// The structure of the table is inferred from the row type and values:
+    /// names are taken from fields (or `_1`, `_2`, ... for primitives and
+    /// tuples), `Option` becomes `Nullable` and so on. Use
+    /// [`Query::with_external_table_structure()`] if it cannot be inferred,
+    /// e.g. for dates, enums, no rows or only `None` values.
+    ///
+    /// All possible errors will be returned as [`Error::InvalidParams`]
+    /// during query execution.
+    ///
+    /// # Examples
+    /// ```
+    /// # async fn example() -> clickhouse::error::Result<()> {
+    /// # let client = clickhouse::Client::default();
+    /// let ids: Vec<u64> = vec![1, 2, 3];
+    /// let names = client
+    ///     .query("SELECT name FROM users WHERE id IN ids")
+    ///     .with_external_table("ids", &ids)
+    ///     .fetch_all::<String>()
+    ///     .await?;
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// [external data]: https://clickhouse.com/docs/en/engines/table-engines/special/external-data
+    pub fn with_external_table<'a, R>(
+        self,
+        name: &str,
+        rows: impl IntoIterator<Item = &'a R>,
+    ) -> Self
+    where
+        R: Row + Serialize + 'a,
+    {
+        self.add_external_table(ExternalTable::new(name, None, rows))
+    }
+
+    /// Similar to [`Query::with_external_table()`], but with the explicit
+    /// structure, e.g. `"id UInt64, date Date"`.
+    pub fn with_external_table_structure<'a, R>(
+        self,
+        name: &str,
+        structure: &str,
+        rows: impl IntoIterator<Item = &'a R>,
+    ) -> Self
+    where
+        R: Row + Serialize + 'a,
+    {
+        self.add_external_table(ExternalTable::new(name, Some(structure), rows))
+    }
+
+    fn add_external_table(mut self, table: std::result::Result<ExternalTable, String>) -> Self {
+        match table {
+            Ok(table) => self.external_tables.push(table),
+            Err(err) => self.sql = SqlBuilder::Failed(format!("invalid external table: {err}")),
+        }
+        self
+    }
+
     /// Specify server side parameter for query.
     ///
     /// In queries you can reference params as {name: type} e.g. {val: Int32}.
+    ///
+    /// Values are written in the same way as by [`Query::bind()`], so the
+    /// server parses them into the same values: sequences as `Array`, maps as
+    /// `Map`, tuples and structs as `Tuple` (including named ones), `None` as
+    /// `NULL`. Strings are also suitable for `UUID`, `IPv6` and `Decimal`.
+    ///
+    /// Unlike [`Query::bind()`], values of `DateTime64` and `IPv4` helpers of
+    /// [`crate::serde`] are passed as text, because params cannot be numbers
+    /// of these types. `bind()` writes them as numbers, e.g. in units of
+    /// the precision for `DateTime64`.
     pub fn param(mut self, name: &str, value: impl Serialize) -> Self {
         let mut param = String::from("");
         if let Err(err) = ser::write_param(&mut param, &value) {
@@ -218,3 +442,89 @@ impl Query {
         }
     }
 }
+
+/// A query template parsed once and reused for many executions,
+/// see [`Client::prepare()`].
+///
+/// It's cheap to clone and can be stored in a static, e.g. in `OnceLock`.
+/// Each call of [`Prepared::query()`] or binding methods creates a fresh
+/// [`Query`] without parsing the template again.
+///
+/// # Examples
+/// ```
+/// # async fn example() -> clickhouse::error::Result<()> {
+/// # let client = clickhouse::Client::default();
+/// let prepared = client.prepare("SELECT name FROM users WHERE id = ? AND age > {age: UInt8}");
+///
+/// for id in [1, 2, 3] {
+///     let name = prepared
+///         .bind(id)
+///         .param("age", 18)
+///         .fetch_optional::<String>()
+///         .await?;
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Clone)]
+pub struct Prepared {
+    inner: Arc<PreparedInner>,
+}
+
+struct PreparedInner {
+    client: Client,
+    sql: SqlBuilder,
+    server_params: Arc<[String]>,
+}
+
+assert_impl_all!(Prepared: Send, Sync);
+
+impl Prepared {
+    pub(crate) fn new(client: &Client, template: &str) -> Self {
+        Self {
+            inner: Arc::new(PreparedInner {
+                client: client.clone(),
+                sql: SqlBuilder::new(template),
+                server_params: sql::server_params(template).into(),
+            }),
+        }
+    }
+
+    /// Creates a fresh query to bind arguments and execute.
+    pub fn query(&self) -> Query {
+        Query {
+            client: self.inner.client.clone(),
+            sql: self.inner.sql.clone(),
+            server_params: self.inner.server_params.clone(),
+            external_tables: Vec::new(),
+        }
+    }
+
+    /// Creates a fresh query and binds `value` to the first `?`,
+    /// see [`Query::bind()`].
+    pub fn bind(&self, value: impl Bind) -> Query {
+        self.query().bind(value)
+    }
+
+    /// Creates a fresh query and binds the named placeholder,
+    /// see [`Query::bind_named()`].
+    pub fn bind_named(&self, name: &str, value: impl Bind) -> Query {
+        self.query().bind_named(name, value)
+    }
+
+    /// Creates a fresh query and sets the server-side parameter,
+    /// see [`Query::param()`].
+    pub fn param(&self, name: &str, value: impl Serialize) -> Query {
+        self.query().param(name, value)
+    }
+
+    /// Returns names of server-side parameters `{name: Type}` of the template,
+    /// all of them must be set by [`Query::param()`] before execution.
+    pub fn server_params(&self) -> impl Iterator<Item = &str> {
+        self.inner.server_params.iter().map(String::as_str)
+    }
+
+    /// Display SQL query as string.
+    pub fn sql_display(&self) -> &impl Display {
+        &self.inner.sql
+    }
+}
@@ -29,8 +29,8 @@ impl RequestBody {
         Self(Inner::Full(Bytes::new()))
     }
 
-    pub(crate) fn full(content: String) -> Self {
-        Self(Inner::Full(Bytes::from(content)))
+    pub(crate) fn full(content: impl Into<Bytes>) -> Self {
+        Self(Inner::Full(content.into()))
     }
 
     pub(crate) fn chunked() -> (ChunkSender, Self) {
@@ -7,21 +7,23 @@ use std::{
 use bstr::ByteSlice;
 use bytes::{BufMut, Bytes};
 use futures::{
-    future,
+    future, ready,
     stream::{self, Stream, TryStreamExt},
 };
-use http_body_util::BodyExt as _;
+use http_body_util::{BodyExt as _, LengthLimitError, Limited};
 use hyper::{
     body::{Body as _, Incoming},
-    StatusCode,
+    header::CONTENT_ENCODING,
+    HeaderMap, StatusCode,
 };
 use hyper_util::client::legacy::ResponseFuture as HyperResponseFuture;
 
-#[cfg(feature = "lz4")]
-use crate::compression::lz4::Lz4Decoder;
+#[cfg(any(feature = "lz4", feature = "zstd"))]
+use crate::compression::block::BlockDecoder;
 use crate::{
-    compression::Compression,
+    compression::{http, Compression},
     error::{Error, Result},
+    Limits,
 };
 
 // === Response ===
@@ -37,19 +39,24 @@ pub(crate) enum Response {
 pub(crate) type ResponseFuture = Pin<Box<dyn Future<Output = Result<Chunks>> + Send>>;
 
 impl Response {
-    pub(crate) fn new(response: HyperResponseFuture, compression: Compression) -> Self {
+    pub(crate) fn new(
+        response: HyperResponseFuture,
+        compression: Compression,
+        limits: Limits,
+    ) -> Self {
         Self::Waiting(Box::pin(async move {
             let response = response.await?;
             let status = response.status();
+            let http = http_decoder(response.headers(), limits.max_block_size);
             let body = response.into_body();
 
             if status == StatusCode::OK {
                 // More likely to be successful, start streaming.
                 // It still can fail, but we'll handle it in `DetectDbException`.
-                Ok(Chunks::new(body, compression))
+                Ok(Chunks::new(body, compression, http, limits))
             } else {
                 // An instantly failed request.
-                Err(collect_bad_response(status, body, compression).await)
+                Err(collect_bad_response(status, body, compression, http, limits).await)
             }
         }))
     }
@@ -74,34 +81,50 @@ impl Response {
     }
 }
 
+// Responses are compressed on the HTTP level only if requested, see
+// `HttpCompression`, but it's cheap to check the header anyway.
+fn http_decoder(headers: &HeaderMap, max_block_size: usize) -> Option<http::Decoder> {
+    let encoding = headers.get(CONTENT_ENCODING)?.to_str().ok()?;
+    http::Decoder::new(encoding, max_block_size)
+}
+
 #[cold]
 #[inline(never)]
 async fn collect_bad_response(
     status: StatusCode,
     body: Incoming,
     compression: Compression,
+    http: Option<http::Decoder>,
+    limits: Limits,
 ) -> Error {
+    let max_size = limits.max_error_body_size;
+
     // Collect the whole body into one contiguous buffer to simplify handling.
     // Only network errors can occur here and we return them instead of status code
     // because it means the request can be repeated to get a more detailed error.
-    //
-    // TODO: we don't implement any length checks and a malicious peer (e.g. MITM)
-    //       might make us consume arbitrary amounts of memory.
-    let raw_bytes = match body.collect().await {
+    // The body is limited, so a malicious peer (e.g. MITM) cannot make us consume
+    // arbitrary amounts of memory.
+    let raw_bytes = match Limited::new(body, max_size).collect().await {
         Ok(collected) => collected.to_bytes(),
+        Err(err) if err.is::<LengthLimitError>() => return error_body_too_large(max_size),
         // If we can't collect the body, return standardised reason for the status code.
         Err(_) => return Error::BadResponse(stringify_status(status)),
     };
 
     // Try to decompress the body, because CH uses compression even for errors.
     let stream = stream::once(future::ready(Result::<_>::Ok(raw_bytes.slice(..))));
-    let stream = Decompress::new(stream, compression).map_ok(|chunk| chunk.data);
+    let stream = Decompress::new(stream, compression, http, limits.max_block_size)
+        .map_ok(|chunk| chunk.data);
 
     // We're collecting already fetched chunks, thus only decompression errors can
     // be here. If decompression is failed, we should try the raw body because
     // it can be sent without any compression if some proxy is used, which
     // typically know nothing about CH params.
-    let bytes = collect_bytes(stream).await.unwrap_or(raw_bytes);
+    let bytes = match collect_bytes(stream, max_size).await {
+        Ok(bytes) => bytes,
+        Err(err @ Error::LimitExceeded(_)) => return err,
+        Err(_) => raw_bytes,
+    };
 
     let reason = String::from_utf8(bytes.into())
         .map(|reason| reason.trim().into())
@@ -111,19 +134,29 @@ async fn collect_bad_response(
     Error::BadResponse(reason)
 }
 
-async fn collect_bytes(stream: impl Stream<Item = Result<Bytes>>) -> Result<Bytes> {
+async fn collect_bytes(
+    stream: impl Stream<Item = Result<Bytes>>,
+    max_size: usize,
+) -> Result<Bytes> {
     futures::pin_mut!(stream);
 
     let mut bytes = Vec::new();
 
     // TODO: avoid extra copying if there is only one chunk in the stream.
     while let Some(chunk) = stream.try_next().await? {
+        if bytes.len().saturating_add(chunk.len()) > max_size {
+            return Err(error_body_too_large(max_size));
+        }
         bytes.put(chunk);
     }
 
     Ok(bytes.into())
 }
 
+fn error_body_too_large(max_size: usize) -> Error {
+    Error::LimitExceeded(format!("error body is larger than {max_size} bytes"))
+}
+
 fn stringify_status(status: StatusCode) -> String {
     format!(
         "{} {}",
@@ -144,9 +177,14 @@ pub(crate) struct Chunk {
 pub(crate) struct Chunks(Option<Box<DetectDbException<Decompress<IncomingStream>>>>);
 
 impl Chunks {
-    fn new(stream: Incoming, compression: Compression) -> Self {
+    fn new(
+        stream: Incoming,
+        compression: Compression,
+        http: Option<http::Decoder>,
+        limits: Limits,
+    ) -> Self {
         let stream = IncomingStream(stream);
-        let stream = Decompress::new(stream, compression);
+        let stream = Decompress::new(stream, compression, http, limits.max_block_size);
         let stream = DetectDbException(stream);
         Self(Some(Box::new(stream)))
     }
@@ -213,17 +251,28 @@ impl Stream for IncomingStream {
 
 enum Decompress<S> {
     Plain(S),
-    #[cfg(feature = "lz4")]
-    Lz4(Lz4Decoder<S>),
+    #[cfg(any(feature = "lz4", feature = "zstd"))]
+    Native(BlockDecoder<S>),
+    Http(Box<HttpDecoder<S>>),
 }
 
 impl<S> Decompress<S> {
-    fn new(stream: S, compression: Compression) -> Self {
+    #[cfg_attr(not(any(feature = "lz4", feature = "zstd")), allow(unused_variables))]
+    fn new(
+        stream: S,
+        compression: Compression,
+        http: Option<http::Decoder>,
+        max_block_size: usize,
+    ) -> Self {
+        // The server uses either the HTTP compression or the native one.
+        if let Some(decoder) = http {
+            return Self::Http(Box::new(HttpDecoder::new(stream, decoder)));
+        }
+
         match compression {
             Compression::None => Self::Plain(stream),
-            #[cfg(feature = "lz4")]
-            #[allow(deprecated)]
-            Compression::Lz4 | Compression::Lz4Hc(_) => Self::Lz4(Lz4Decoder::new(stream)),
+            #[cfg(any(feature = "lz4", feature = "zstd"))]
+            _ => Self::Native(BlockDecoder::new(stream, max_block_size)),
         }
     }
 }
@@ -243,8 +292,70 @@ where
                     data: bytes,
                 })
                 .map_err(Into::into),
-            #[cfg(feature = "lz4")]
-            Self::Lz4(stream) => Pin::new(stream).poll_next(cx),
+            #[cfg(any(feature = "lz4", feature = "zstd"))]
+            Self::Native(stream) => Pin::new(stream).poll_next(cx),
+            Self::Http(stream) => Pin::new(stream).poll_next(cx),
+        }
+    }
+}
+
+// === HttpDecoder ===
+
+// Decodes the body according to `Content-Encoding`.
+struct HttpDecoder<S> {
+    stream: S,
+    // `None` once the stream is finished.
+    decoder: Option<http::Decoder>,
+    // Received bytes, not yet reported in chunks.
+    net_size: usize,
+}
+
+impl<S> HttpDecoder<S> {
+    fn new(stream: S, decoder: http::Decoder) -> Self {
+        Self {
+            stream,
+            decoder: Some(decoder),
+            net_size: 0,
+        }
+    }
+}
+
+impl<S> Stream for HttpDecoder<S>
+where
+    S: Stream<Item = Result<Bytes>> + Unpin,
+{
+    type Item = Result<Chunk>;
+
+    // `http::Decoder` has no variants without HTTP compression features.
+    #[allow(unreachable_code)]
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        loop {
+            if this.decoder.is_none() {
+                return Poll::Ready(None);
+            }
+
+            let data = match ready!(Pin::new(&mut this.stream).poll_next(cx)) {
+                Some(Ok(bytes)) => {
+                    this.net_size += bytes.len();
+                    this.decoder.as_mut().unwrap().decode(&bytes)
+                }
+                Some(Err(err)) => Err(err),
+                None => this.decoder.take().unwrap().finish(),
+            };
+
+            match data {
+                Ok(data) if data.is_empty() => continue,
+                Ok(data) => {
+                    let net_size = std::mem::take(&mut this.net_size);
+                    return Poll::Ready(Some(Ok(Chunk { data, net_size })));
+                }
+                Err(err) => {
+                    this.decoder = None;
+                    return Poll::Ready(Some(Err(err)));
+                }
+            }
         }
     }
 }
@@ -0,0 +1,315 @@
+//! Inserting rows into multiple tables chosen per row.
+
+use std::{borrow::Cow, collections::HashMap, mem};
+
+use futures::future;
+use serde::Serialize;
+use tokio::time::Duration;
+
+use crate::{
+    error::Result,
+    inserter::{Inserter, Quantities},
+    row::Row,
+    ticks::Instant,
+    Client,
+};
+
+type Router<T> = Box<dyn for<'a> Fn(&'a T) -> Cow<'a, str> + Send + Sync>;
+
+/// Performs multiple consecutive `INSERT`s into tables chosen per row,
+/// e.g. per-tenant or per-day tables.
+///
+/// Every active table (a route) has its own [`Inserter`] with per-table
+/// limits set by `with_max_bytes`, `with_max_rows` and `with_period`.
+/// Additionally, `with_total_max_bytes` and `with_total_max_rows` limit data
+/// pending in all tables together, once reached, all `INSERT`s are ended.
+///
+/// Routes without writes for `with_idle_timeout` are evicted on commits.
+///
+/// ```
+/// # async fn example() -> clickhouse::error::Result<()> {
+/// use std::borrow::Cow;
+/// use clickhouse::{Client, Row};
+/// use serde::Serialize;
+///
+/// #[derive(Row, Serialize)]
+/// struct Event {
+///     tenant: String,
+///     payload: String,
+/// }
+///
+/// let client = Client::default();
+/// let mut inserter = client
+///     .routing_inserter(|event: &Event| Cow::Owned(format!("events_{}", event.tenant)))
+///     .with_max_rows(100_000)
+///     .with_total_max_rows(1_000_000);
+///
+/// inserter.write(&Event { tenant: "a".into(), payload: "foo".into() })?;
+/// inserter.write(&Event { tenant: "b".into(), payload: "bar".into() })?;
+/// inserter.commit().await?;
+/// inserter.end().await?;
+/// # Ok(()) }
+/// ```
+#[must_use]
+pub struct RoutingInserter<T> {
+    client: Client,
+    router: Router<T>,
+    routes: HashMap<String, Route<T>>,
+    max_bytes: u64,
+    max_rows: u64,
+    total_max_bytes: u64,
+    total_max_rows: u64,
+    period: Option<Duration>,
+    period_bias: f64,
+    send_timeout: Option<Duration>,
+    end_timeout: Option<Duration>,
+    idle_timeout: Option<Duration>,
+}
+
+struct Route<T> {
+    inserter: Inserter<T>,
+    last_write: Instant,
+}
+
+impl<T: Row> RoutingInserter<T> {
+    pub(crate) fn new<F>(client: &Client, router: F) -> Self
+    where
+        F: for<'a> Fn(&'a T) -> Cow<'a, str> + Send + Sync + 'static,
+    {
+        Self {
+            client: client.clone(),
+            router: Box::new(router),
+            routes: HashMap::new(),
+            max_bytes: u64::MAX,
+            max_rows: u64::MAX,
+            total_max_bytes: u64::MAX,
+            total_max_rows: u64::MAX,
+            period: None,
+            period_bias: 0.,
+            send_timeout: None,
+            end_timeout: None,
+            idle_timeout: None,
+        }
+    }
+
+    /// See [`Inserter::with_timeouts()`].
+    pub fn with_timeouts(
+        mut self,
+        send_timeout: Option<Duration>,
+        end_timeout: Option<Duration>,
+    ) -> Self {
+        self.send_timeout = send_timeout;
+        self.end_timeout = end_timeout;
+        for route in self.routes.values_mut() {
+            route.inserter.set_timeouts(send_timeout, end_timeout);
+        }
+        self
+    }
+
+    /// The maximum number of uncompressed bytes in one `INSERT` into a table.
+    /// See [`Inserter::with_max_bytes()`].
+    pub fn with_max_bytes(mut self, threshold: u64) -> Self {
+        self.max_bytes = threshold;
+        for route in self.routes.values_mut() {
+            route.inserter.set_max_bytes(threshold);
+        }
+        self
+    }
+
+    /// The maximum number of rows in one `INSERT` into a table.
+    /// See [`Inserter::with_max_rows()`].
+    pub fn with_max_rows(mut self, threshold: u64) -> Self {
+        self.max_rows = threshold;
+        for route in self.routes.values_mut() {
+            route.inserter.set_max_rows(threshold);
+        }
+        self
+    }
+
+    /// The maximum number of uncompressed bytes pending in all tables.
+    ///
+    /// Unlimited (`u64::MAX`) by default.
+    pub fn with_total_max_bytes(mut self, threshold: u64) -> Self {
+        self.total_max_bytes = threshold;
+        self
+    }
+
+    /// The maximum number of rows pending in all tables.
+    ///
+    /// Unlimited (`u64::MAX`) by default.
+    pub fn with_total_max_rows(mut self, threshold: u64) -> Self {
+        self.total_max_rows = threshold;
+        self
+    }
+
+    /// The time between `INSERT`s into a table.
+    /// See [`Inserter::with_period()`].
+    pub fn with_period(mut self, period: Option<Duration>) -> Self {
+        self.period = period;
+        for route in self.routes.values_mut() {
+            route.inserter.set_period(period);
+        }
+        self
+    }
+
+    /// See [`Inserter::with_period_bias()`].
+    pub fn with_period_bias(mut self, bias: f64) -> Self {
+        self.period_bias = bias;
+        for route in self.routes.values_mut() {
+            route.inserter.set_period_bias(bias);
+        }
+        self
+    }
+
+    /// Routes without writes for this time are ended and evicted on
+    /// the next [`RoutingInserter::commit()`].
+    ///
+    /// Unlimited (`None`) by default.
+    pub fn with_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// Similar to [`Client::with_option`], but for the INSERT statements
+    /// generated by this [`RoutingInserter`] only.
+    ///
+    /// Applied to new routes only.
+    pub fn with_option(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.client.add_option(name, value);
+        self
+    }
+
+    /// How much time we have until the next tick of any route.
+    ///
+    /// `None` if the period isn't configured.
+    pub fn time_left(&mut self) -> Option<Duration> {
+        self.routes
+            .values_mut()
+            .filter_map(|route| route.inserter.time_left())
+            .min()
+    }
+
+    /// Returns statistics about data not yet inserted into ClickHouse.
+    pub fn pending(&self) -> Quantities {
+        let mut pending = Quantities::ZERO;
+        for route in self.routes.values() {
+            pending.add(route.inserter.pending());
+        }
+        pending
+    }
+
+    /// Returns active tables and statistics about their pending data.
+    pub fn routes(&self) -> impl Iterator<Item = (&str, &Quantities)> {
+        self.routes
+            .iter()
+            .map(|(table, route)| (table.as_str(), route.inserter.pending()))
+    }
+
+    /// Serializes the provided row into an internal buffer of the table
+    /// chosen by the router.
+    ///
+    /// To check the limits and send the data to ClickHouse, call
+    /// [`RoutingInserter::commit()`].
+    ///
+    /// # Panics
+    /// If called after the previous call that returned an error.
+    pub fn write(&mut self, row: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let table = (self.router)(row);
+
+        if !self.routes.contains_key(&*table) {
+            let route = self.new_route(&table)?;
+            self.routes.insert(table.clone().into_owned(), route);
+        }
+
+        let route = self.routes.get_mut(&*table).unwrap();
+        route.last_write = Instant::now();
+        route.inserter.write(row)
+    }
+
+    /// Checks limits and ends `INSERT`s if they are reached.
+    ///
+    /// Also, ends `INSERT`s into idle tables and evicts them.
+    pub async fn commit(&mut self) -> Result<Quantities> {
+        let force = self.total_limits_reached();
+        self.commit_routes(force).await
+    }
+
+    /// Ends all `INSERT`s unconditionally.
+    pub async fn force_commit(&mut self) -> Result<Quantities> {
+        self.commit_routes(true).await
+    }
+
+    /// Ends all `INSERT`s and whole `RoutingInserter` unconditionally.
+    ///
+    /// If it isn't called, current `INSERT`s are aborted.
+    pub async fn end(mut self) -> Result<Quantities> {
+        let routes = mem::take(&mut self.routes);
+        let results = future::join_all(routes.into_values().map(|r| r.inserter.end())).await;
+        collect(results)
+    }
+
+    fn total_limits_reached(&self) -> bool {
+        let pending = self.pending();
+        pending.rows >= self.total_max_rows || pending.bytes >= self.total_max_bytes
+    }
+
+    async fn commit_routes(&mut self, force: bool) -> Result<Quantities> {
+        let now = Instant::now();
+        let idle_timeout = self.idle_timeout;
+        let is_idle = |route: &Route<T>| {
+            idle_timeout.is_some_and(|t| now.saturating_duration_since(route.last_write) >= t)
+        };
+
+        // Routes are committed concurrently, otherwise every `INSERT` waits
+        // for responses to previous ones, even into other tables.
+        let commits = self.routes.values_mut().map(|route| async {
+            if force || is_idle(route) {
+                route.inserter.force_commit().await
+            } else {
+                route.inserter.commit().await
+            }
+        });
+        let mut results = future::join_all(commits).await;
+
+        let idle = self
+            .routes
+            .iter()
+            .filter(|(_, route)| is_idle(route))
+            .map(|(table, _)| table.clone())
+            .collect::<Vec<_>>();
+
+        // Nothing is pending in idle routes, but responses can be awaited.
+        let ends = idle.iter().map(|table| {
+            let route = self.routes.remove(table).unwrap();
+            route.inserter.end()
+        });
+        results.extend(future::join_all(ends.collect::<Vec<_>>()).await);
+
+        collect(results)
+    }
+
+    fn new_route(&self, table: &str) -> Result<Route<T>> {
+        let inserter = Inserter::new(&self.client, table)?
+            .with_timeouts(self.send_timeout, self.end_timeout)
+            .with_max_bytes(self.max_bytes)
+            .with_max_rows(self.max_rows)
+            .with_period(self.period)
+            .with_period_bias(self.period_bias);
+
+        Ok(Route {
+            inserter,
+            last_write: Instant::now(),
+        })
+    }
+}
+
+fn collect(results: Vec<Result<Quantities>>) -> Result<Quantities> {
+    let mut quantities = Quantities::ZERO;
+    for result in results {
+        quantities.add(&result?);
+    }
+    Ok(quantities)
+}
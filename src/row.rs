@@ -43,6 +43,20 @@ macro_rules! impl_row_for_tuple {
 // TODO: revise this?
 impl Primitive for () {}
 
+impl<E: crate::ClickHouseEnum> Primitive for E {}
+
+impl Primitive for crate::types::Dynamic {}
+impl Primitive for crate::types::Json {}
+
+impl_primitive_for![
+    crate::types::geo::Point,
+    crate::types::geo::Ring,
+    crate::types::geo::LineString,
+    crate::types::geo::MultiLineString,
+    crate::types::geo::Polygon,
+    crate::types::geo::MultiPolygon,
+];
+
 impl<P: Primitive> Row for P {
     const COLUMN_NAMES: &'static [&'static str] = &[];
 }
@@ -59,8 +73,13 @@ pub(crate) fn join_column_names<R: Row>() -> Option<String> {
         return None;
     }
 
-    let out = R::COLUMN_NAMES
-        .iter()
+    Some(join_names(R::COLUMN_NAMES))
+}
+
+/// Escapes the provided names and joins them with comma.
+pub(crate) fn join_names<'a>(names: impl IntoIterator<Item = &'a &'a str>) -> String {
+    names
+        .into_iter()
         .enumerate()
         .fold(String::new(), |mut res, (idx, name)| {
             if idx > 0 {
@@ -68,9 +87,7 @@ pub(crate) fn join_column_names<R: Row>() -> Option<String> {
             }
             sql::escape::identifier(name, &mut res).expect("impossible");
             res
-        });
-
-    Some(out)
+        })
 }
 
 #[cfg(test)]
@@ -3,7 +3,7 @@ use std::{convert::TryFrom, mem, str};
 use crate::error::{Error, Result};
 use bytes::Buf;
 use serde::{
-    de::{DeserializeSeed, Deserializer, EnumAccess, SeqAccess, VariantAccess, Visitor},
+    de::{DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor},
     Deserialize,
 };
 
@@ -266,8 +266,46 @@ impl<'data> Deserializer<'data> for &mut RowBinaryDeserializer<'_, 'data> {
     }
 
     #[inline]
-    fn deserialize_map<V: Visitor<'data>>(self, _visitor: V) -> Result<V::Value> {
-        panic!("maps are unsupported, use `Vec<(A, B)>` instead");
+    fn deserialize_map<V: Visitor<'data>>(self, visitor: V) -> Result<V::Value> {
+        struct Access<'de, 'cursor, 'data> {
+            deserializer: &'de mut RowBinaryDeserializer<'cursor, 'data>,
+            len: usize,
+        }
+
+        impl<'data> MapAccess<'data> for Access<'_, '_, 'data> {
+            type Error = Error;
+
+            fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+            where
+                K: DeserializeSeed<'data>,
+            {
+                if self.len > 0 {
+                    self.len -= 1;
+                    let key = DeserializeSeed::deserialize(seed, &mut *self.deserializer)?;
+                    Ok(Some(key))
+                } else {
+                    Ok(None)
+                }
+            }
+
+            fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+            where
+                V: DeserializeSeed<'data>,
+            {
+                DeserializeSeed::deserialize(seed, &mut *self.deserializer)
+            }
+
+            fn size_hint(&self) -> Option<usize> {
+                Some(self.len)
+            }
+        }
+
+        // Map(K, V) is encoded as Array(Tuple(K, V)).
+        let len = self.read_size()?;
+        visitor.visit_map(Access {
+            deserializer: self,
+            len,
+        })
     }
 
     #[inline]
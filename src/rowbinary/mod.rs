@@ -1,5 +1,5 @@
 pub(crate) use de::deserialize_from;
-pub(crate) use ser::serialize_into;
+pub(crate) use ser::{serialize_columns_into, serialize_into};
 
 mod de;
 mod ser;
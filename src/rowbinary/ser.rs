@@ -1,6 +1,6 @@
 use bytes::BufMut;
 use serde::{
-    ser::{Impossible, SerializeSeq, SerializeStruct, SerializeTuple, Serializer},
+    ser::{Impossible, SerializeMap, SerializeSeq, SerializeStruct, SerializeTuple, Serializer},
     Serialize,
 };
 
@@ -8,7 +8,27 @@ use crate::error::{Error, Result};
 
 /// Serializes `value` using the RowBinary format and writes to `buffer`.
 pub(crate) fn serialize_into(buffer: impl BufMut, value: &impl Serialize) -> Result<()> {
-    let mut serializer = RowBinarySerializer { buffer };
+    let mut serializer = RowBinarySerializer {
+        buffer,
+        columns: None,
+        depth: 0,
+    };
+    value.serialize(&mut serializer)?;
+    Ok(())
+}
+
+/// Same as [`serialize_into`], but writes only the provided fields of
+/// the top-level struct, other ones are skipped.
+pub(crate) fn serialize_columns_into(
+    buffer: impl BufMut,
+    value: &impl Serialize,
+    columns: &[&str],
+) -> Result<()> {
+    let mut serializer = RowBinarySerializer {
+        buffer,
+        columns: Some(columns),
+        depth: 0,
+    };
     value.serialize(&mut serializer)?;
     Ok(())
 }
@@ -16,8 +36,12 @@ pub(crate) fn serialize_into(buffer: impl BufMut, value: &impl Serialize) -> Res
 /// A serializer for the RowBinary format.
 ///
 /// See https://clickhouse.com/docs/en/interfaces/formats#rowbinary for details.
-struct RowBinarySerializer<B> {
+struct RowBinarySerializer<'a, B> {
     buffer: B,
+    // Fields of the top-level struct to write, all if `None`.
+    columns: Option<&'a [&'a str]>,
+    // The number of structs being serialized.
+    depth: usize,
 }
 
 macro_rules! impl_num {
@@ -30,10 +54,10 @@ macro_rules! impl_num {
     };
 }
 
-impl<B: BufMut> Serializer for &'_ mut RowBinarySerializer<B> {
+impl<B: BufMut> Serializer for &'_ mut RowBinarySerializer<'_, B> {
     type Error = Error;
     type Ok = ();
-    type SerializeMap = Impossible<(), Error>;
+    type SerializeMap = Self;
     type SerializeSeq = Self;
     type SerializeStruct = Self;
     type SerializeStructVariant = Impossible<(), Error>;
@@ -191,12 +215,16 @@ impl<B: BufMut> Serializer for &'_ mut RowBinarySerializer<B> {
     }
 
     #[inline]
-    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
-        panic!("maps are unsupported, use `Vec<(A, B)>` instead");
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
+        // Map(K, V) is encoded as Array(Tuple(K, V)).
+        let len = len.ok_or(Error::SequenceMustHaveLength)?;
+        put_unsigned_leb128(&mut self.buffer, len as u64);
+        Ok(self)
     }
 
     #[inline]
     fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        self.depth += 1;
         Ok(self)
     }
 
@@ -217,22 +245,32 @@ impl<B: BufMut> Serializer for &'_ mut RowBinarySerializer<B> {
     }
 }
 
-impl<B: BufMut> SerializeStruct for &mut RowBinarySerializer<B> {
+impl<B: BufMut> SerializeStruct for &mut RowBinarySerializer<'_, B> {
     type Error = Error;
     type Ok = ();
 
     #[inline]
-    fn serialize_field<T: Serialize + ?Sized>(&mut self, _: &'static str, value: &T) -> Result<()> {
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        if let Some(columns) = self.columns {
+            if self.depth == 1 && !columns.contains(&name) {
+                return Ok(());
+            }
+        }
         value.serialize(&mut **self)
     }
 
     #[inline]
     fn end(self) -> Result<()> {
+        self.depth -= 1;
         Ok(())
     }
 }
 
-impl<B: BufMut> SerializeSeq for &'_ mut RowBinarySerializer<B> {
+impl<B: BufMut> SerializeSeq for &'_ mut RowBinarySerializer<'_, B> {
     type Error = Error;
     type Ok = ();
 
@@ -245,7 +283,7 @@ impl<B: BufMut> SerializeSeq for &'_ mut RowBinarySerializer<B> {
     }
 }
 
-impl<B: BufMut> SerializeTuple for &'_ mut RowBinarySerializer<B> {
+impl<B: BufMut> SerializeTuple for &'_ mut RowBinarySerializer<'_, B> {
     type Error = Error;
     type Ok = ();
 
@@ -263,6 +301,26 @@ impl<B: BufMut> SerializeTuple for &'_ mut RowBinarySerializer<B> {
     }
 }
 
+impl<B: BufMut> SerializeMap for &'_ mut RowBinarySerializer<'_, B> {
+    type Error = Error;
+    type Ok = ();
+
+    #[inline]
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        key.serialize(&mut **self)
+    }
+
+    #[inline]
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    #[inline]
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
 fn put_unsigned_leb128(mut buffer: impl BufMut, mut value: u64) {
     while {
         let mut byte = value as u8 & 0x7f;
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
@@ -34,6 +36,8 @@ struct Sample<'a> {
     fixed_string: [u8; 4],
     array: Vec<i8>,
     boolean: bool,
+    #[serde(borrow)]
+    map: BTreeMap<&'a str, u8>,
 }
 
 fn sample() -> Sample<'static> {
@@ -57,6 +61,7 @@ fn sample() -> Sample<'static> {
         fixed_string: [b'B', b'T', b'C', 0],
         array: vec![-42, 42, -42, 42],
         boolean: true,
+        map: [("a", 1), ("bc", 2)].into_iter().collect(),
     }
 }
 
@@ -104,6 +109,8 @@ fn sample_serialized() -> Vec<u8> {
         0x04, 0xd6, 0x2a, 0xd6, 0x2a, //
         // [Boolean] true
         0x01, //
+        // [Map(String, UInt8)] {'a': 1, 'bc': 2}
+        0x02, 0x01, 0x61, 0x01, 0x02, 0x62, 0x63, 0x02, //
     ]
 }
 
@@ -129,3 +136,34 @@ fn it_deserializes() {
         assert_eq!(actual, sample());
     }
 }
+
+#[test]
+fn it_serializes_columns() {
+    #[derive(Serialize)]
+    struct Inner {
+        a: u8,
+        b: u8,
+    }
+
+    #[derive(Serialize)]
+    struct Outer {
+        a: u8,
+        inner: Inner,
+        b: u8,
+    }
+
+    let value = Outer {
+        a: 1,
+        inner: Inner { a: 2, b: 3 },
+        b: 4,
+    };
+
+    let mut actual = Vec::new();
+    super::serialize_columns_into(&mut actual, &value, &["inner", "b"]).unwrap();
+    // Fields of nested structs aren't filtered.
+    assert_eq!(actual, [2, 3, 4]);
+
+    actual.clear();
+    super::serialize_columns_into(&mut actual, &value, &["a"]).unwrap();
+    assert_eq!(actual, [1]);
+}
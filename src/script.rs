@@ -0,0 +1,330 @@
+//! Contains [`Script`] to execute several statements in order.
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    error::{Error, Result},
+    query::Query,
+    Client,
+};
+
+/// Executes statements of a script one by one, see [`Client::script()`].
+///
+/// Statements are separated by `;`, which is recognized outside of string
+/// literals, quoted identifiers, heredocs (`$$...$$`) and comments.
+/// Statements are sent as is, so `?` placeholders aren't supported.
+#[must_use]
+#[derive(Clone)]
+pub struct Script {
+    client: Client,
+    script: String,
+    stop_on_error: bool,
+}
+
+/// The result of [`Script::execute()`].
+#[derive(Debug)]
+pub struct ScriptReport {
+    /// Executed statements in order.
+    pub statements: Vec<StatementReport>,
+    /// The number of statements skipped after a failed one.
+    pub skipped: usize,
+}
+
+/// The result of a single statement of a script.
+#[derive(Debug)]
+pub struct StatementReport {
+    /// The statement without leading comments and the trailing `;`.
+    pub sql: String,
+    /// The line of the script where the statement starts, starting from 1.
+    pub line: usize,
+    /// Time spent to execute the statement.
+    pub elapsed: Duration,
+    /// The result of the statement.
+    pub result: Result<()>,
+}
+
+impl Script {
+    pub(crate) fn new(client: &Client, script: &str) -> Self {
+        Self {
+            client: client.clone(),
+            script: script.into(),
+            stop_on_error: true,
+        }
+    }
+
+    /// Whether to stop on the first failed statement, `true` by default.
+    ///
+    /// If disabled, all statements are executed and errors are only
+    /// reported in [`ScriptReport`].
+    pub fn with_stop_on_error(mut self, stop_on_error: bool) -> Self {
+        self.stop_on_error = stop_on_error;
+        self
+    }
+
+    /// Similar to [`Client::with_option`], but for statements of this script only.
+    pub fn with_option(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.client.add_option(name, value);
+        self
+    }
+
+    /// Executes statements in order.
+    ///
+    /// Errors of statements are returned inside [`ScriptReport`], while
+    /// a script that cannot be split (e.g. an unterminated string literal)
+    /// is reported as [`Error::InvalidParams`] without executing anything.
+    pub async fn execute(self) -> Result<ScriptReport> {
+        let statements = split(&self.script)?;
+        let total = statements.len();
+
+        let mut report = ScriptReport {
+            statements: Vec::with_capacity(total),
+            skipped: 0,
+        };
+
+        for statement in statements {
+            let started_at = Instant::now();
+            let result = Query::raw(&self.client, Ok(statement.sql.into()))
+                .execute()
+                .await;
+
+            let failed = result.is_err();
+            report.statements.push(StatementReport {
+                sql: statement.sql.into(),
+                line: statement.line,
+                elapsed: started_at.elapsed(),
+                result,
+            });
+
+            if failed && self.stop_on_error {
+                report.skipped = total - report.statements.len();
+                break;
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+impl ScriptReport {
+    /// Returns `true` if all statements are executed successfully.
+    pub fn is_ok(&self) -> bool {
+        self.skipped == 0 && self.statements.iter().all(|s| s.result.is_ok())
+    }
+
+    /// Returns the first failed statement, if any.
+    pub fn first_error(&self) -> Option<&StatementReport> {
+        self.statements.iter().find(|s| s.result.is_err())
+    }
+
+    /// Returns the error of the first failed statement, if any.
+    pub fn into_result(self) -> Result<Self> {
+        match self.statements.iter().position(|s| s.result.is_err()) {
+            Some(idx) => {
+                let mut statements = self.statements;
+                Err(statements.swap_remove(idx).result.unwrap_err())
+            }
+            None => Ok(self),
+        }
+    }
+}
+
+// === split ===
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct Statement<'a> {
+    pub(crate) sql: &'a str,
+    pub(crate) line: usize,
+}
+
+/// Splits the script into statements, skipping empty ones and comments.
+pub(crate) fn split(script: &str) -> Result<Vec<Statement<'_>>> {
+    let bytes = script.as_bytes();
+    let mut statements = Vec::new();
+    // The first non-comment character of the current statement.
+    let mut code_start = None;
+    let mut idx = 0;
+
+    let mut push = |code_start: &mut Option<usize>, end: usize| {
+        if let Some(start) = code_start.take() {
+            statements.push(Statement {
+                sql: script[start..end].trim_end(),
+                line: line_of(script, start),
+            });
+        }
+    };
+
+    while idx < bytes.len() {
+        let next = bytes.get(idx + 1).copied();
+
+        match bytes[idx] {
+            quote @ (b'\'' | b'"' | b'`') => {
+                code_start.get_or_insert(idx);
+                idx = skip_quoted(script, idx, quote)?;
+                continue;
+            }
+            // `-- comment`, `# comment` and `#!comment`.
+            b'-' if next == Some(b'-') => {
+                idx = script[idx..]
+                    .find('\n')
+                    .map_or(bytes.len(), |pos| idx + pos);
+                continue;
+            }
+            b'#' if matches!(next, Some(b' ' | b'!')) => {
+                idx = script[idx..]
+                    .find('\n')
+                    .map_or(bytes.len(), |pos| idx + pos);
+                continue;
+            }
+            b'/' if next == Some(b'*') => {
+                let end = script[idx + 2..]
+                    .find("*/")
+                    .ok_or_else(|| unterminated("comment", script, idx))?;
+                idx += 2 + end + 2;
+                continue;
+            }
+            b'$' => {
+                if let Some(end) = skip_heredoc(script, idx)? {
+                    code_start.get_or_insert(idx);
+                    idx = end;
+                    continue;
+                }
+                code_start.get_or_insert(idx);
+            }
+            b';' => push(&mut code_start, idx),
+            c if !c.is_ascii_whitespace() => {
+                code_start.get_or_insert(idx);
+            }
+            _ => {}
+        }
+
+        idx += 1;
+    }
+
+    push(&mut code_start, bytes.len());
+    Ok(statements)
+}
+
+// Returns the index after the closing quote, doubled quotes are escaped too.
+fn skip_quoted(script: &str, start: usize, quote: u8) -> Result<usize> {
+    let bytes = script.as_bytes();
+    let mut idx = start + 1;
+
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'\\' => idx += 2,
+            c if c == quote && bytes.get(idx + 1) == Some(&quote) => idx += 2,
+            c if c == quote => return Ok(idx + 1),
+            _ => idx += 1,
+        }
+    }
+
+    let what = match quote {
+        b'\'' => "string literal",
+        _ => "quoted identifier",
+    };
+    Err(unterminated(what, script, start))
+}
+
+// Returns the index after the closing `$tag$` if `start` opens a heredoc.
+fn skip_heredoc(script: &str, start: usize) -> Result<Option<usize>> {
+    let rest = &script[start + 1..];
+    let Some(tag_len) = rest.find('$') else {
+        return Ok(None);
+    };
+
+    if !rest[..tag_len]
+        .bytes()
+        .all(|c| c.is_ascii_alphanumeric() || c == b'_')
+    {
+        return Ok(None);
+    }
+
+    let delimiter = &script[start..start + tag_len + 2];
+    let body_start = start + delimiter.len();
+    match script[body_start..].find(delimiter) {
+        Some(pos) => Ok(Some(body_start + pos + delimiter.len())),
+        None => Err(unterminated("heredoc", script, start)),
+    }
+}
+
+fn line_of(script: &str, idx: usize) -> usize {
+    script[..idx].matches('\n').count() + 1
+}
+
+fn unterminated(what: &str, script: &str, start: usize) -> Error {
+    let line = line_of(script, start);
+    Error::InvalidParams(format!("invalid script: unterminated {what} at line {line}").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sqls(script: &str) -> Vec<&str> {
+        split(script).unwrap().into_iter().map(|s| s.sql).collect()
+    }
+
+    #[test]
+    fn it_splits_statements() {
+        assert_eq!(sqls(""), Vec::<&str>::new());
+        assert_eq!(sqls(" ;\n; -- nothing\n/* at all */;"), Vec::<&str>::new());
+        assert_eq!(sqls("SELECT 1; SELECT 2"), ["SELECT 1", "SELECT 2"]);
+        assert_eq!(sqls("SELECT 1;\nSELECT 2;\n"), ["SELECT 1", "SELECT 2"]);
+
+        let script = "
+            -- Creates a table; or not.
+            CREATE TABLE t (s String) /* ; */ ENGINE = Memory;
+
+            INSERT INTO t VALUES ('a;b'), ('c\\';d'), ('e'';f');
+            SELECT `x;y`, \"z;w\" FROM t; # a comment; too
+            #!and this one
+            SELECT 1#2
+        ";
+
+        let statements = split(script).unwrap();
+        assert_eq!(
+            statements,
+            [
+                Statement {
+                    sql: "CREATE TABLE t (s String) /* ; */ ENGINE = Memory",
+                    line: 3,
+                },
+                Statement {
+                    sql: "INSERT INTO t VALUES ('a;b'), ('c\\';d'), ('e'';f')",
+                    line: 5,
+                },
+                Statement {
+                    sql: "SELECT `x;y`, \"z;w\" FROM t",
+                    line: 6,
+                },
+                Statement {
+                    sql: "SELECT 1#2",
+                    line: 8,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn it_splits_heredocs() {
+        assert_eq!(
+            sqls("SELECT $$a;b$$; SELECT $tag$c;$$;d$tag$; SELECT 1 AS `$`"),
+            [
+                "SELECT $$a;b$$",
+                "SELECT $tag$c;$$;d$tag$",
+                "SELECT 1 AS `$`"
+            ]
+        );
+        assert_eq!(sqls("SELECT '$'; SELECT 2"), ["SELECT '$'", "SELECT 2"]);
+    }
+
+    #[test]
+    fn it_fails_on_unterminated() {
+        let error = |script| split(script).unwrap_err().to_string();
+
+        assert!(error("SELECT 1;\nSELECT 'a;").contains("unterminated string literal at line 2"));
+        assert!(error("SELECT `a").contains("unterminated quoted identifier at line 1"));
+        assert!(error("SELECT 1 /* ;").contains("unterminated comment"));
+        assert!(error("SELECT $x$ ;").contains("unterminated heredoc"));
+    }
+}
@@ -5,6 +5,28 @@ use serde::{
     ser::{Serialize, Serializer},
 };
 
+/// Names of newtypes wrapping values of some helpers below to pass their
+/// ClickHouse types through serde, e.g. to write `DateTime64` as text in
+/// [`Query::param()`]. Other serializers, e.g. RowBinary and `Query::bind()`,
+/// see wrapped values only.
+///
+/// [`Query::param()`]: crate::query::Query::param
+#[cfg_attr(not(any(feature = "time", feature = "chrono")), allow(dead_code))]
+pub(crate) mod marker {
+    const PREFIX: &str = "__clickhouse:";
+
+    pub(crate) const IPV4: &str = "__clickhouse:IPv4";
+    pub(crate) const DATETIME64_0: &str = "__clickhouse:DateTime64(0)";
+    pub(crate) const DATETIME64_3: &str = "__clickhouse:DateTime64(3)";
+    pub(crate) const DATETIME64_6: &str = "__clickhouse:DateTime64(6)";
+    pub(crate) const DATETIME64_9: &str = "__clickhouse:DateTime64(9)";
+
+    /// Returns a ClickHouse type if the newtype name is a marker.
+    pub(crate) fn type_name(name: &'static str) -> Option<&'static str> {
+        name.strip_prefix(PREFIX)
+    }
+}
+
 macro_rules! option {
     ($name:ident, $doc:literal) => {
         #[doc = $doc]
@@ -58,7 +80,7 @@ pub mod ipv4 {
     where
         S: Serializer,
     {
-        u32::from(*ipv4).serialize(serializer)
+        serializer.serialize_newtype_struct(marker::IPV4, &u32::from(*ipv4))
     }
 
     pub fn deserialize<'de, D>(deserializer: D) -> Result<Ipv4Addr, D::Error>
@@ -163,7 +185,7 @@ pub mod chrono {
                 S: Serializer,
             {
                 let ts = dt.timestamp();
-                ts.serialize(serializer)
+                serializer.serialize_newtype_struct(marker::DATETIME64_0, &ts)
             }
 
             pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
@@ -191,7 +213,7 @@ pub mod chrono {
                 S: Serializer,
             {
                 let ts = dt.timestamp_millis();
-                ts.serialize(serializer)
+                serializer.serialize_newtype_struct(marker::DATETIME64_3, &ts)
             }
 
             pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
@@ -219,7 +241,7 @@ pub mod chrono {
                 S: Serializer,
             {
                 let ts = dt.timestamp_micros();
-                ts.serialize(serializer)
+                serializer.serialize_newtype_struct(marker::DATETIME64_6, &ts)
             }
 
             pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
@@ -249,7 +271,7 @@ pub mod chrono {
                 let ts = dt.timestamp_nanos_opt().ok_or_else(|| {
                     S::Error::custom(format!("{dt} cannot be represented as DateTime64"))
                 })?;
-                ts.serialize(serializer)
+                serializer.serialize_newtype_struct(marker::DATETIME64_9, &ts)
             }
 
             pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
@@ -404,7 +426,7 @@ pub mod time {
             where
                 S: Serializer,
             {
-                do_serialize(dt, 1_000_000_000, serializer)
+                do_serialize(dt, 1_000_000_000, marker::DATETIME64_0, serializer)
             }
 
             pub fn deserialize<'de, D>(deserializer: D) -> Result<OffsetDateTime, D::Error>
@@ -428,7 +450,7 @@ pub mod time {
             where
                 S: Serializer,
             {
-                do_serialize(dt, 1_000_000, serializer)
+                do_serialize(dt, 1_000_000, marker::DATETIME64_3, serializer)
             }
 
             pub fn deserialize<'de, D>(deserializer: D) -> Result<OffsetDateTime, D::Error>
@@ -452,7 +474,7 @@ pub mod time {
             where
                 S: Serializer,
             {
-                do_serialize(dt, 1_000, serializer)
+                do_serialize(dt, 1_000, marker::DATETIME64_6, serializer)
             }
 
             pub fn deserialize<'de, D>(deserializer: D) -> Result<OffsetDateTime, D::Error>
@@ -476,7 +498,7 @@ pub mod time {
             where
                 S: Serializer,
             {
-                do_serialize(dt, 1, serializer)
+                do_serialize(dt, 1, marker::DATETIME64_9, serializer)
             }
 
             pub fn deserialize<'de, D>(deserializer: D) -> Result<OffsetDateTime, D::Error>
@@ -487,15 +509,21 @@ pub mod time {
             }
         }
 
-        fn do_serialize<S>(dt: &OffsetDateTime, div: i128, serializer: S) -> Result<S::Ok, S::Error>
+        fn do_serialize<S>(
+            dt: &OffsetDateTime,
+            div: i128,
+            marker: &'static str,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error>
         where
             S: Serializer,
         {
             let ts = dt.unix_timestamp_nanos() / div;
+            let ts = i64::try_from(ts).map_err(|_| {
+                S::Error::custom(format!("{dt} cannot be represented as DateTime64"))
+            })?;
 
-            i64::try_from(ts)
-                .map_err(|_| S::Error::custom(format!("{dt} cannot be represented as DateTime64")))?
-                .serialize(serializer)
+            serializer.serialize_newtype_struct(marker, &ts)
         }
 
         fn do_deserialize<'de, D>(deserializer: D, mul: i128) -> Result<OffsetDateTime, D::Error>
@@ -0,0 +1,289 @@
+//! A write-ahead spool of RowBinary rows used by the inserter.
+//!
+//! Every `INSERT` gets its own segment file:
+//! ```text
+//! magic | len: u32 | sql | len: u32 | kind: u8 | token | (len: u32 | row)*
+//! ```
+//! where `kind` is `0` for a literal deduplication token and `1` for a prefix
+//! of a token derived from a hash of rows. All integers are little-endian.
+//! A truncated last record (e.g. after a crash in the middle of writing) is
+//! ignored on reading.
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const MAGIC: &[u8; 8] = b"CHSPOOL1";
+const EXTENSION: &str = "seg";
+
+pub(crate) struct Spool {
+    dir: PathBuf,
+    prefix: String,
+    next_seq: u64,
+    // Segments of `INSERT`s in progress, they aren't replayed.
+    active: Vec<PathBuf>,
+    // Set initially to pick up segments left by previous runs.
+    needs_replay: bool,
+}
+
+pub(crate) struct Segment {
+    path: PathBuf,
+    token: SegmentToken,
+    file: BufWriter<File>,
+}
+
+/// The deduplication token of a segment's `INSERT`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SegmentToken {
+    Literal(String),
+    /// A prefix, the rest is derived from rows.
+    Hash(String),
+}
+
+/// A segment read back from the disk.
+#[derive(Debug)]
+pub(crate) struct Stored {
+    pub(crate) path: PathBuf,
+    pub(crate) token: SegmentToken,
+    pub(crate) sql: String,
+    pub(crate) data: Vec<u8>,
+    pub(crate) rows: u64,
+}
+
+impl Spool {
+    pub(crate) fn new(dir: PathBuf) -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+
+        Self {
+            dir,
+            // Fixed width to keep the creation order of segments.
+            prefix: format!("{nanos:016x}-{:08x}", process::id()),
+            next_seq: 0,
+            active: Vec::new(),
+            needs_replay: true,
+        }
+    }
+
+    pub(crate) fn needs_replay(&self) -> bool {
+        self.needs_replay
+    }
+
+    pub(crate) fn set_needs_replay(&mut self, needs_replay: bool) {
+        self.needs_replay = needs_replay;
+    }
+
+    /// Starts a new segment.
+    ///
+    /// If `token` isn't provided, the unique name of the segment is used.
+    pub(crate) fn begin(&mut self, sql: &str, token: Option<SegmentToken>) -> io::Result<Segment> {
+        fs::create_dir_all(&self.dir)?;
+
+        let name = format!("{}-{:010}", self.prefix, self.next_seq);
+        self.next_seq += 1;
+
+        let path = self.dir.join(&name).with_extension(EXTENSION);
+        let token = token.unwrap_or(SegmentToken::Literal(name));
+
+        let (kind, value) = match &token {
+            SegmentToken::Literal(token) => (0, token),
+            SegmentToken::Hash(prefix) => (1, prefix),
+        };
+
+        let mut file = BufWriter::new(File::create(&path)?);
+        file.write_all(MAGIC)?;
+        write_record(&mut file, sql.as_bytes())?;
+        write_record(&mut file, &[&[kind], value.as_bytes()].concat())?;
+
+        self.active.push(path.clone());
+        Ok(Segment { path, token, file })
+    }
+
+    /// Removes the segment, its rows have been inserted.
+    pub(crate) fn complete(&mut self, segment: Segment) -> io::Result<()> {
+        self.deactivate(&segment.path);
+        drop(segment.file);
+        fs::remove_file(segment.path)
+    }
+
+    /// Leaves the segment on the disk to be replayed later.
+    pub(crate) fn detach(&mut self, mut segment: Segment) {
+        self.deactivate(&segment.path);
+        // The best we can do, rows written so far will be replayed.
+        let _ = segment.file.flush();
+        self.needs_replay = true;
+    }
+
+    fn deactivate(&mut self, path: &Path) {
+        self.active.retain(|active| active != path);
+    }
+
+    /// Returns paths of segments to replay, from the oldest to the newest.
+    pub(crate) fn stored(&self) -> io::Result<Vec<PathBuf>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+
+        let mut paths = Vec::new();
+
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == EXTENSION) && !self.active.contains(&path)
+            {
+                paths.push(path);
+            }
+        }
+
+        paths.sort();
+        Ok(paths)
+    }
+}
+
+impl Segment {
+    pub(crate) fn token(&self) -> &SegmentToken {
+        &self.token
+    }
+
+    pub(crate) fn append(&mut self, row: &[u8]) -> io::Result<()> {
+        write_record(&mut self.file, row)
+    }
+
+    /// Makes all appended rows durable.
+    pub(crate) fn sync(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        self.file.get_ref().sync_data()
+    }
+}
+
+pub(crate) fn read(path: &Path) -> io::Result<Stored> {
+    let invalid = |msg: &str| {
+        let msg = format!("{}: {msg}", path.display());
+        io::Error::new(io::ErrorKind::InvalidData, msg)
+    };
+
+    let content = fs::read(path)?;
+    let mut rest = content
+        .strip_prefix(MAGIC)
+        .ok_or_else(|| invalid("not a segment"))?;
+
+    let sql = read_record(&mut rest).ok_or_else(|| invalid("truncated header"))?;
+    let sql = String::from_utf8(sql.to_vec()).map_err(|_| invalid("invalid header"))?;
+
+    let token = read_record(&mut rest).ok_or_else(|| invalid("truncated header"))?;
+    let value = std::str::from_utf8(token.get(1..).unwrap_or_default())
+        .map_err(|_| invalid("invalid header"))?
+        .to_string();
+    let token = match token.first() {
+        Some(0) => SegmentToken::Literal(value),
+        Some(1) => SegmentToken::Hash(value),
+        _ => return Err(invalid("invalid header")),
+    };
+
+    let mut data = Vec::with_capacity(rest.len());
+    let mut rows = 0;
+    while let Some(row) = read_record(&mut rest) {
+        data.extend_from_slice(row);
+        rows += 1;
+    }
+
+    Ok(Stored {
+        path: path.into(),
+        token,
+        sql,
+        data,
+        rows,
+    })
+}
+
+fn write_record(file: &mut impl Write, record: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(record.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too large row"))?;
+    file.write_all(&len.to_le_bytes())?;
+    file.write_all(record)
+}
+
+fn read_record<'a>(rest: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let len = u32::from_le_bytes(rest.get(..4)?.try_into().unwrap()) as usize;
+    let record = rest.get(4..4 + len)?;
+    *rest = &rest[4 + len..];
+    Some(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("clickhouse-spool-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn it_stores_and_completes_segments() {
+        let dir = temp_dir("complete");
+        let mut spool = Spool::new(dir.clone());
+
+        let mut segment = spool
+            .begin("INSERT INTO t(a) FORMAT RowBinary", None)
+            .unwrap();
+        let token = segment.token().clone();
+        assert!(matches!(&token, SegmentToken::Literal(t) if t.ends_with("-0000000000")));
+        segment.append(&[1, 2]).unwrap();
+        segment.append(&[3]).unwrap();
+        segment.sync().unwrap();
+
+        // Active segments aren't replayed.
+        assert!(spool.stored().unwrap().is_empty());
+
+        spool.detach(segment);
+        let paths = spool.stored().unwrap();
+        assert_eq!(paths.len(), 1);
+
+        let stored = read(&paths[0]).unwrap();
+        assert_eq!(stored.sql, "INSERT INTO t(a) FORMAT RowBinary");
+        assert_eq!(stored.data, [1, 2, 3]);
+        assert_eq!(stored.rows, 2);
+        assert_eq!(stored.token, token);
+
+        let token = SegmentToken::Hash("prefix".into());
+        let segment = spool.begin("INSERT", Some(token.clone())).unwrap();
+        assert_eq!(segment.token(), &token);
+        spool.complete(segment).unwrap();
+        assert_eq!(spool.stored().unwrap(), paths);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn it_ignores_truncated_rows() {
+        let dir = temp_dir("truncated");
+        let mut spool = Spool::new(dir.clone());
+
+        let token = SegmentToken::Literal("token".into());
+        let mut segment = spool.begin("INSERT", Some(token)).unwrap();
+        segment.append(&[1, 2, 3]).unwrap();
+        segment.append(&[4, 5, 6]).unwrap();
+        spool.detach(segment);
+
+        let path = spool.stored().unwrap().remove(0);
+        let content = fs::read(&path).unwrap();
+        fs::write(&path, &content[..content.len() - 1]).unwrap();
+
+        let stored = read(&path).unwrap();
+        assert_eq!(stored.data, [1, 2, 3]);
+        assert_eq!(stored.rows, 1);
+
+        fs::write(&path, b"garbage").unwrap();
+        assert_eq!(read(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -30,3 +30,38 @@ impl Bind for Identifier<'_> {
         escape::identifier(self.0, dst).map_err(|err| err.to_string())
     }
 }
+
+/// Bound the provided string as a partition ID, i.e. `ID '<id>'`.
+/// It can be used in `ALTER TABLE t DROP PARTITION ?`, for instance.
+pub struct PartitionId<'a>(pub &'a str);
+
+#[sealed]
+impl Bind for PartitionId<'_> {
+    #[inline]
+    fn write(&self, dst: &mut impl fmt::Write) -> Result<(), String> {
+        dst.write_str("ID ").map_err(|err| err.to_string())?;
+        escape::string(self.0, dst).map_err(|err| err.to_string())
+    }
+}
+
+/// Bound the provided string as a table name, either `table` or `db.table`.
+#[cfg(any(feature = "migrate", feature = "catalog"))]
+pub(crate) struct TableName<'a>(pub(crate) &'a str);
+
+#[cfg(any(feature = "migrate", feature = "catalog"))]
+#[sealed]
+impl Bind for TableName<'_> {
+    #[inline]
+    fn write(&self, dst: &mut impl fmt::Write) -> Result<(), String> {
+        escape::table(self.0, dst).map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(feature = "qb")]
+#[sealed]
+impl Bind for crate::qb::Expr {
+    #[inline]
+    fn write(&self, dst: &mut impl fmt::Write) -> Result<(), String> {
+        self.write_operand(dst)
+    }
+}
@@ -16,6 +16,18 @@ pub(crate) fn identifier(src: &str, dst: &mut impl fmt::Write) -> fmt::Result {
     dst.write_char('`')
 }
 
+// Both `table` and `database.table` are supported, each part is escaped.
+#[cfg(any(feature = "migrate", feature = "catalog", feature = "qb"))]
+pub(crate) fn table(src: &str, dst: &mut impl fmt::Write) -> fmt::Result {
+    for (i, part) in src.split('.').enumerate() {
+        if i > 0 {
+            dst.write_char('.')?;
+        }
+        identifier(part, dst)?;
+    }
+    Ok(())
+}
+
 pub(crate) fn escape(src: &str, dst: &mut impl fmt::Write) -> fmt::Result {
     const REPLACE: &[char] = &['\\', '\'', '`', '\t', '\n'];
     let mut rest = src;
@@ -42,3 +54,11 @@ fn it_escapes_identifier() {
     identifier(r"f\o`o `` b\`ar`", &mut actual).unwrap();
     assert_eq!(actual, r"`f\\o\`o \`\` b\\\`ar\``");
 }
+
+#[cfg(any(feature = "migrate", feature = "catalog", feature = "qb"))]
+#[test]
+fn it_escapes_table() {
+    let mut actual = String::new();
+    table("db.t?`x", &mut actual).unwrap();
+    assert_eq!(actual, r"`db`.`t?\`x`");
+}
@@ -1,11 +1,15 @@
 use std::fmt::{self, Display, Write};
 
+use clickhouse_placeholders::Placeholder;
+
 use crate::{
     error::{Error, Result},
     row::{self, Row},
 };
 
-pub use bind::{Bind, Identifier};
+#[cfg(any(feature = "migrate", feature = "catalog"))]
+pub(crate) use bind::TableName;
+pub use bind::{Bind, Identifier, PartitionId};
 
 mod bind;
 pub(crate) mod escape;
@@ -21,6 +25,7 @@ pub(crate) enum SqlBuilder {
 pub(crate) enum Part {
     Arg,
     Fields,
+    Named(String),
     Text(String),
 }
 
@@ -33,6 +38,7 @@ impl fmt::Display for SqlBuilder {
                     match part {
                         Part::Arg => f.write_char('?')?,
                         Part::Fields => f.write_str("?fields")?,
+                        Part::Named(name) => write!(f, "?{{{name}}}")?,
                         Part::Text(text) => f.write_str(text)?,
                     }
                 }
@@ -47,34 +53,49 @@ impl fmt::Display for SqlBuilder {
 }
 
 impl SqlBuilder {
+    /// Parses a template with placeholders, see [`clickhouse_placeholders::scan()`].
     pub(crate) fn new(template: &str) -> Self {
         let mut parts = Vec::new();
-        let mut rest = template;
-        while let Some(idx) = rest.find('?') {
-            if rest[idx + 1..].starts_with('?') {
-                parts.push(Part::Text(rest[..idx + 1].to_string()));
-                rest = &rest[idx + 2..];
-                continue;
-            } else if idx != 0 {
-                parts.push(Part::Text(rest[..idx].to_string()));
-            }
+        let mut start = 0; // of the current text
 
-            rest = &rest[idx + 1..];
-            if let Some(restfields) = rest.strip_prefix("fields") {
-                parts.push(Part::Fields);
-                rest = restfields;
-            } else {
-                parts.push(Part::Arg);
+        fn push_text(parts: &mut Vec<Part>, text: &str) {
+            if !text.is_empty() {
+                parts.push(Part::Text(text.to_string()));
             }
         }
 
-        if !rest.is_empty() {
-            parts.push(Part::Text(rest.to_string()));
+        for (range, placeholder) in clickhouse_placeholders::scan(template) {
+            let part = match placeholder {
+                Placeholder::Question => {
+                    // Keep the first `?` as is.
+                    push_text(&mut parts, &template[start..=range.start]);
+                    start = range.end;
+                    continue;
+                }
+                Placeholder::Arg => Part::Arg,
+                Placeholder::Fields => Part::Fields,
+                Placeholder::Named(name) => Part::Named(name.into()),
+                // Sent to the server as is.
+                Placeholder::ServerParam(_) => continue,
+            };
+
+            push_text(&mut parts, &template[start..range.start]);
+            parts.push(part);
+            start = range.end;
         }
 
+        push_text(&mut parts, &template[start..]);
         SqlBuilder::InProgress(parts, None)
     }
 
+    /// Uses already rendered SQL as is, without any placeholders.
+    pub(crate) fn raw(sql: std::result::Result<String, String>) -> Self {
+        match sql {
+            Ok(sql) => Self::InProgress(vec![Part::Text(sql)], None),
+            Err(err) => Self::Failed(format!("invalid SQL: {err}")),
+        }
+    }
+
     pub(crate) fn set_output_format(&mut self, format: impl Into<String>) {
         if let Self::InProgress(_, format_opt) = self {
             *format_opt = Some(format.into());
@@ -99,6 +120,32 @@ impl SqlBuilder {
         }
     }
 
+    pub(crate) fn bind_named(&mut self, name: &str, value: impl Bind) {
+        let Self::InProgress(parts, _) = self else {
+            return;
+        };
+
+        let mut found = parts
+            .iter_mut()
+            .filter(|p| matches!(p, Part::Named(n) if n == name))
+            .peekable();
+
+        if found.peek().is_none() {
+            return self.error(format_args!(
+                "unexpected bind_named(), :{name} isn't in the query or is already bound"
+            ));
+        }
+
+        let mut s = String::new();
+        if let Err(err) = value.write(&mut s) {
+            return self.error(format_args!("invalid argument :{name}: {err}"));
+        }
+
+        for part in found {
+            *part = Part::Text(s.clone());
+        }
+    }
+
     pub(crate) fn bind_fields<T: Row>(&mut self) {
         let Self::InProgress(parts, _) = self else {
             return;
@@ -128,6 +175,11 @@ impl SqlBuilder {
                         self.error("unbound query argument ?fields");
                         break;
                     }
+                    Part::Named(name) => {
+                        let name = name.clone();
+                        self.error(format_args!("unbound named query argument :{name}"));
+                        break;
+                    }
                 }
             }
         }
@@ -148,6 +200,35 @@ impl SqlBuilder {
     }
 }
 
+/// Returns names of server-side parameters `{name: Type}` of the template,
+/// which are recognized only outside of quotes and comments.
+pub(crate) fn server_params(template: &str) -> Vec<String> {
+    let mut params = Vec::<String>::new();
+
+    for (_, placeholder) in clickhouse_placeholders::scan(template) {
+        match placeholder {
+            Placeholder::ServerParam(name) if !params.iter().any(|p| p == name) => {
+                params.push(name.into());
+            }
+            _ => {}
+        }
+    }
+
+    params
+}
+
+/// Returns `ON CLUSTER <cluster>` or an empty string if no cluster is provided.
+#[cfg(feature = "migrate")]
+pub(crate) fn on_cluster(cluster: Option<&str>) -> String {
+    let Some(cluster) = cluster else {
+        return String::new();
+    };
+
+    let mut on_cluster = String::from("ON CLUSTER ");
+    escape::identifier(cluster, &mut on_cluster).expect("impossible");
+    on_cluster
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -233,6 +314,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn server_params() {
+        assert_eq!(
+            super::server_params(
+                "SELECT {a: UInt8}, { b :String}, {a:UInt8}, '{c: UInt8}', {1: x}, {d}, :e"
+            ),
+            ["a", "b"]
+        );
+        assert!(super::server_params("SELECT `{x: UInt8}`, \"\\\"{y: UInt8}\"").is_empty());
+        assert!(super::server_params("SELECT 1 -- {x: UInt8}\n/* {y: UInt8} */").is_empty());
+    }
+
+    #[test]
+    fn partition_id() {
+        let mut sql = SqlBuilder::new("ALTER TABLE t DROP PARTITION ?");
+        sql.bind_arg(PartitionId("20240101-'x"));
+        assert_eq!(
+            sql.finish().unwrap(),
+            r"ALTER TABLE t DROP PARTITION ID '20240101-\'x'"
+        );
+    }
+
     #[test]
     fn question_escape() {
         let sql = SqlBuilder::new("SELECT 1 FROM test WHERE a IN 'a??b'");
@@ -242,6 +345,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn named_args() {
+        let mut sql = SqlBuilder::new(
+            "SELECT ?fields FROM test WHERE a = :a AND b IN ?{b} AND c = ? AND d = :a",
+        );
+        assert_eq!(
+            sql.to_string(),
+            "SELECT ?fields FROM test WHERE a = ?{a} AND b IN ?{b} AND c = ? AND d = ?{a}"
+        );
+
+        sql.bind_named("a", "foo");
+        sql.bind_arg(42);
+        sql.bind_named("b", &[1, 2][..]);
+        sql.bind_fields::<Row>();
+        assert_eq!(
+            sql.finish().unwrap(),
+            r"SELECT `a`,`b` FROM test WHERE a = 'foo' AND b IN [1,2] AND c = 42 AND d = 'foo'"
+        );
+    }
+
+    #[test]
+    fn named_args_ignored() {
+        let template = "SELECT a::UInt8, '12:30:a', {p:String}, x?? FROM test WHERE a = ':a\\':b'";
+        let sql = SqlBuilder::new(template);
+        assert_eq!(
+            sql.finish().unwrap(),
+            "SELECT a::UInt8, '12:30:a', {p:String}, x? FROM test WHERE a = ':a\\':b'"
+        );
+
+        let mut sql = SqlBuilder::new("SELECT :_a1, ?{b}, :2");
+        sql.bind_named("_a1", 1);
+        sql.bind_named("b", 2);
+        assert_eq!(sql.finish().unwrap(), "SELECT 1, 2, :2");
+    }
+
     #[test]
     fn option_as_null() {
         let mut sql = SqlBuilder::new("SELECT 1 FROM test WHERE a = ?");
@@ -279,5 +417,21 @@ mod tests {
         sql.bind_arg(42);
         let err = sql.finish().unwrap_err();
         assert!(err.to_string().contains("unbound query argument ?fields"));
+
+        let mut sql = SqlBuilder::new("SELECT 1 FROM test WHERE a = :a AND b = :b");
+        sql.bind_named("a", 42);
+        let err = sql.finish().unwrap_err();
+        assert!(err.to_string().contains("unbound named query argument :b"));
+
+        let mut sql = SqlBuilder::new("SELECT 1 FROM test WHERE a = :a");
+        sql.bind_named("c", 42);
+        let err = sql.finish().unwrap_err();
+        assert!(err.to_string().contains(":c isn't in the query"));
+
+        let mut sql = SqlBuilder::new("SELECT 1 FROM test WHERE a = :a");
+        sql.bind_named("a", 42);
+        sql.bind_named("a", 43);
+        let err = sql.finish().unwrap_err();
+        assert!(err.to_string().contains("is already bound"));
     }
 }
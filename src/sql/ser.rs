@@ -1,7 +1,10 @@
 use std::fmt::{self, Write};
 
 use serde::{
-    ser::{self, SerializeSeq, SerializeTuple, Serializer},
+    ser::{
+        self, SerializeMap, SerializeSeq, SerializeStruct, SerializeTuple, SerializeTupleStruct,
+        Serializer,
+    },
     Serialize,
 };
 use thiserror::Error;
@@ -35,8 +38,29 @@ impl From<fmt::Error> for SerializerError {
 type Result<T = (), E = SerializerError> = std::result::Result<T, E>;
 type Impossible = ser::Impossible<(), SerializerError>;
 
+/// Where a value is written, it affects escaping of some types.
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    /// An SQL literal, e.g. for `Query::bind()`.
+    Literal,
+    /// A value of `param_<name>`, strings aren't quoted.
+    Param,
+    /// A value inside arrays, tuples and maps of `param_<name>`.
+    NestedParam,
+}
+
+impl Format {
+    fn nested(self) -> Self {
+        match self {
+            Self::Literal => Self::Literal,
+            Self::Param | Self::NestedParam => Self::NestedParam,
+        }
+    }
+}
+
 struct SqlSerializer<'a, W> {
     writer: &'a mut W,
+    format: Format,
 }
 
 macro_rules! unsupported {
@@ -72,19 +96,96 @@ macro_rules! forward_to_display {
     () => {};
 }
 
+impl<'a, W: Write> SqlSerializer<'a, W> {
+    fn list(self, opening_char: char, closing_char: char) -> Result<SqlListSerializer<'a, W>> {
+        self.writer.write_char(opening_char)?;
+        Ok(SqlListSerializer {
+            writer: self.writer,
+            format: self.format.nested(),
+            has_items: false,
+            closing_char,
+        })
+    }
+
+    // Strings are written as is only on the top level of params.
+    fn write_text(self, value: &str) -> Result {
+        if self.format == Format::Param {
+            escape::escape(value, self.writer)?;
+        } else {
+            escape::string(value, self.writer)?;
+        }
+        Ok(())
+    }
+
+    fn write_i128(self, value: impl fmt::Display, type_name: &str) -> Result {
+        if self.format == Format::Literal {
+            write!(self.writer, "{value}::{type_name}")?;
+        } else {
+            write!(self.writer, "{value}")?;
+        }
+        Ok(())
+    }
+
+    // Values marked by `crate::serde` helpers are written as text in params,
+    // which is parsed by ClickHouse according to the param type. SQL literals
+    // keep wrapped values as is, e.g. `DateTime64` as a number.
+    fn write_marked<T: Serialize + ?Sized>(self, type_name: &str, value: &T) -> Result {
+        if self.format == Format::Literal {
+            return value.serialize(self);
+        }
+
+        let mut raw = String::new();
+        value.serialize(SqlSerializer {
+            writer: &mut raw,
+            format: Format::Literal,
+        })?;
+
+        let invalid = || SerializerError::Custom(format!("invalid value of {type_name}: {raw}"));
+
+        let text = if type_name == "IPv4" {
+            let ip = raw.parse::<u32>().map_err(|_| invalid())?;
+            std::net::Ipv4Addr::from(ip).to_string()
+        } else if let Some(precision) = type_name
+            .strip_prefix("DateTime64(")
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            let ts = raw.parse::<i64>().map_err(|_| invalid())?;
+            let precision = precision.parse::<u32>().map_err(|_| invalid())?;
+            format_datetime64(ts, precision)
+        } else {
+            return value.serialize(self);
+        };
+
+        self.write_text(&text)
+    }
+}
+
+// A Unix timestamp with a fractional part, e.g. `1700000000.123`,
+// it's unambiguous unlike a date and time in the server timezone.
+fn format_datetime64(ts: i64, precision: u32) -> String {
+    if precision == 0 {
+        return ts.to_string();
+    }
+
+    let scale = 10u64.pow(precision);
+    let sign = if ts < 0 { "-" } else { "" };
+    let abs = ts.unsigned_abs();
+    let width = precision as usize;
+    format!("{sign}{}.{:0width$}", abs / scale, abs % scale)
+}
+
 impl<'a, W: Write> Serializer for SqlSerializer<'a, W> {
     type Error = SerializerError;
     type Ok = ();
-    type SerializeMap = Impossible;
+    type SerializeMap = SqlMapSerializer<'a, W>;
     type SerializeSeq = SqlListSerializer<'a, W>;
-    type SerializeStruct = Impossible;
+    type SerializeStruct = SqlListSerializer<'a, W>;
     type SerializeStructVariant = Impossible;
     type SerializeTuple = SqlListSerializer<'a, W>;
-    type SerializeTupleStruct = Impossible;
+    type SerializeTupleStruct = SqlListSerializer<'a, W>;
     type SerializeTupleVariant = Impossible;
 
     unsupported!(
-        serialize_map(Option<usize>) -> Result<Impossible>,
         serialize_bytes(&[u8]),
         serialize_unit,
         serialize_unit_struct(&'static str),
@@ -112,39 +213,45 @@ impl<'a, W: Write> Serializer for SqlSerializer<'a, W> {
 
     #[inline]
     fn serialize_i128(self, value: i128) -> Result {
-        write!(self.writer, "{}::Int128", value)?;
-        Ok(())
+        self.write_i128(value, "Int128")
     }
 
     #[inline]
     fn serialize_u128(self, value: u128) -> Result {
-        write!(self.writer, "{}::UInt128", value)?;
-        Ok(())
+        self.write_i128(value, "UInt128")
     }
 
     #[inline]
     fn serialize_str(self, value: &str) -> Result {
-        escape::string(value, self.writer)?;
-        Ok(())
+        self.write_text(value)
     }
 
     #[inline]
     fn serialize_seq(self, _len: Option<usize>) -> Result<SqlListSerializer<'a, W>> {
-        self.writer.write_char('[')?;
-        Ok(SqlListSerializer {
-            writer: self.writer,
-            has_items: false,
-            closing_char: ']',
-        })
+        self.list('[', ']')
     }
 
     #[inline]
     fn serialize_tuple(self, _len: usize) -> Result<SqlListSerializer<'a, W>> {
-        self.writer.write_char('(')?;
-        Ok(SqlListSerializer {
+        self.list('(', ')')
+    }
+
+    #[inline]
+    fn serialize_map(self, _len: Option<usize>) -> Result<SqlMapSerializer<'a, W>> {
+        // Params are parsed as `{k:v}`, while SQL requires `map(k,v)`.
+        let (opening, delimiter, closing) = if self.format == Format::Literal {
+            ("map(", ',', ')')
+        } else {
+            ("{", ':', '}')
+        };
+
+        self.writer.write_str(opening)?;
+        Ok(SqlMapSerializer {
             writer: self.writer,
+            format: self.format.nested(),
             has_items: false,
-            closing_char: ')',
+            delimiter,
+            closing_char: closing,
         })
     }
 
@@ -166,17 +273,19 @@ impl<'a, W: Write> Serializer for SqlSerializer<'a, W> {
         _variant_index: u32,
         variant: &'static str,
     ) -> Result {
-        escape::string(variant, self.writer)?;
-        Ok(())
+        self.write_text(variant)
     }
 
     #[inline]
     fn serialize_newtype_struct<T: Serialize + ?Sized>(
         self,
-        _name: &'static str,
+        name: &'static str,
         value: &T,
     ) -> Result {
-        value.serialize(self)
+        match crate::serde::marker::type_name(name) {
+            Some(type_name) => self.write_marked(type_name, value),
+            None => value.serialize(self),
+        }
     }
 
     #[inline]
@@ -190,9 +299,15 @@ impl<'a, W: Write> Serializer for SqlSerializer<'a, W> {
         Err(SerializerError::Unsupported("serialize_newtype_variant"))
     }
 
+    // Structs and tuple structs are written as tuples, field names are
+    // provided by the `Tuple(name Type, ..)` type on the server side.
     #[inline]
-    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Impossible> {
-        Err(SerializerError::Unsupported("serialize_tuple_struct"))
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<SqlListSerializer<'a, W>> {
+        self.list('(', ')')
     }
 
     #[inline]
@@ -208,7 +323,7 @@ impl<'a, W: Write> Serializer for SqlSerializer<'a, W> {
 
     #[inline]
     fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
-        Err(SerializerError::Unsupported("serialize_struct"))
+        self.list('(', ')')
     }
 
     #[inline]
@@ -232,6 +347,7 @@ impl<'a, W: Write> Serializer for SqlSerializer<'a, W> {
 
 struct SqlListSerializer<'a, W> {
     writer: &'a mut W,
+    format: Format,
     has_items: bool,
     closing_char: char,
 }
@@ -253,6 +369,7 @@ impl<W: Write> SerializeSeq for SqlListSerializer<'_, W> {
 
         value.serialize(SqlSerializer {
             writer: self.writer,
+            format: self.format,
         })
     }
 
@@ -281,156 +398,90 @@ impl<W: Write> SerializeTuple for SqlListSerializer<'_, W> {
     }
 }
 
-// === ParamSerializer ===
-
-struct ParamSerializer<'a, W> {
-    writer: &'a mut W,
-}
-
-impl<'a, W: Write> Serializer for ParamSerializer<'a, W> {
+impl<W: Write> SerializeTupleStruct for SqlListSerializer<'_, W> {
     type Error = SerializerError;
     type Ok = ();
-    type SerializeMap = Impossible;
-    type SerializeSeq = SqlListSerializer<'a, W>;
-    type SerializeStruct = Impossible;
-    type SerializeStructVariant = Impossible;
-    type SerializeTuple = SqlListSerializer<'a, W>;
-    type SerializeTupleStruct = Impossible;
-    type SerializeTupleVariant = Impossible;
-
-    unsupported!(
-        serialize_map(Option<usize>) -> Result<Impossible>,
-        serialize_bytes(&[u8]),
-        serialize_unit,
-        serialize_unit_struct(&'static str),
-    );
-
-    forward_to_display!(
-        serialize_i8(i8),
-        serialize_i16(i16),
-        serialize_i32(i32),
-        serialize_i64(i64),
-        serialize_i128(i128),
-        serialize_u8(u8),
-        serialize_u16(u16),
-        serialize_u32(u32),
-        serialize_u64(u64),
-        serialize_u128(u128),
-        serialize_f32(f32),
-        serialize_f64(f64),
-        serialize_bool(bool),
-    );
 
     #[inline]
-    fn serialize_char(self, value: char) -> Result {
-        let mut tmp = [0u8; 4];
-        self.serialize_str(value.encode_utf8(&mut tmp))
+    fn serialize_field<T>(&mut self, value: &T) -> Result
+    where
+        T: Serialize + ?Sized,
+    {
+        SerializeSeq::serialize_element(self, value)
     }
 
     #[inline]
-    fn serialize_str(self, value: &str) -> Result {
-        // ClickHouse expects strings in params to be unquoted until inside a nested
-        // type nested types go through serialize_seq which'll quote strings
-        Ok(escape::escape(value, self.writer)?)
+    fn end(self) -> Result {
+        SerializeSeq::end(self)
     }
+}
 
-    #[inline]
-    fn serialize_seq(self, _len: Option<usize>) -> Result<SqlListSerializer<'a, W>> {
-        self.writer.write_char('[')?;
-        Ok(SqlListSerializer {
-            writer: self.writer,
-            has_items: false,
-            closing_char: ']',
-        })
-    }
+impl<W: Write> SerializeStruct for SqlListSerializer<'_, W> {
+    type Error = SerializerError;
+    type Ok = ();
 
     #[inline]
-    fn serialize_tuple(self, _len: usize) -> Result<SqlListSerializer<'a, W>> {
-        self.writer.write_char('(')?;
-        Ok(SqlListSerializer {
-            writer: self.writer,
-            has_items: false,
-            closing_char: ')',
-        })
+    fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result
+    where
+        T: Serialize + ?Sized,
+    {
+        SerializeSeq::serialize_element(self, value)
     }
 
     #[inline]
-    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result {
-        _value.serialize(self)
+    fn end(self) -> Result {
+        SerializeSeq::end(self)
     }
+}
 
-    #[inline]
-    fn serialize_none(self) -> std::result::Result<Self::Ok, Self::Error> {
-        self.writer.write_str("NULL")?;
-        Ok(())
-    }
+// === SqlMapSerializer ===
 
-    #[inline]
-    fn serialize_unit_variant(
-        self,
-        _name: &'static str,
-        _variant_index: u32,
-        variant: &'static str,
-    ) -> Result {
-        escape::string(variant, self.writer)?;
-        Ok(())
-    }
+struct SqlMapSerializer<'a, W> {
+    writer: &'a mut W,
+    format: Format,
+    has_items: bool,
+    delimiter: char,
+    closing_char: char,
+}
 
-    #[inline]
-    fn serialize_newtype_struct<T: Serialize + ?Sized>(
-        self,
-        _name: &'static str,
-        value: &T,
-    ) -> Result {
-        value.serialize(self)
-    }
+impl<W: Write> SerializeMap for SqlMapSerializer<'_, W> {
+    type Error = SerializerError;
+    type Ok = ();
 
     #[inline]
-    fn serialize_newtype_variant<T: Serialize + ?Sized>(
-        self,
-        _name: &'static str,
-        _variant_index: u32,
-        _variant: &'static str,
-        _value: &T,
-    ) -> Result {
-        Err(SerializerError::Unsupported("serialize_newtype_variant"))
-    }
+    fn serialize_key<T>(&mut self, key: &T) -> Result
+    where
+        T: Serialize + ?Sized,
+    {
+        if self.has_items {
+            self.writer.write_char(',')?;
+        }
 
-    #[inline]
-    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Impossible> {
-        Err(SerializerError::Unsupported("serialize_tuple_struct"))
-    }
+        self.has_items = true;
 
-    #[inline]
-    fn serialize_tuple_variant(
-        self,
-        _name: &'static str,
-        _variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
-    ) -> Result<Impossible> {
-        Err(SerializerError::Unsupported("serialize_tuple_variant"))
+        key.serialize(SqlSerializer {
+            writer: self.writer,
+            format: self.format,
+        })
     }
 
     #[inline]
-    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
-        Err(SerializerError::Unsupported("serialize_struct"))
-    }
+    fn serialize_value<T>(&mut self, value: &T) -> Result
+    where
+        T: Serialize + ?Sized,
+    {
+        self.writer.write_char(self.delimiter)?;
 
-    #[inline]
-    fn serialize_struct_variant(
-        self,
-        _name: &'static str,
-        _variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
-    ) -> Result<Self::SerializeStructVariant> {
-        Err(SerializerError::Unsupported("serialize_struct_variant"))
+        value.serialize(SqlSerializer {
+            writer: self.writer,
+            format: self.format,
+        })
     }
 
     #[inline]
-    fn is_human_readable(&self) -> bool {
-        true
+    fn end(self) -> Result {
+        self.writer.write_char(self.closing_char)?;
+        Ok(())
     }
 }
 
@@ -438,13 +489,19 @@ impl<'a, W: Write> Serializer for ParamSerializer<'a, W> {
 
 pub(crate) fn write_arg(writer: &mut impl Write, value: &impl Serialize) -> Result<(), String> {
     value
-        .serialize(SqlSerializer { writer })
+        .serialize(SqlSerializer {
+            writer,
+            format: Format::Literal,
+        })
         .map_err(|err| err.to_string())
 }
 
 pub(crate) fn write_param(writer: &mut impl Write, value: &impl Serialize) -> Result<(), String> {
     value
-        .serialize(ParamSerializer { writer })
+        .serialize(SqlSerializer {
+            writer,
+            format: Format::Param,
+        })
         .map_err(|err| err.to_string())
 }
 
@@ -511,6 +568,103 @@ mod tests {
         assert_eq!(check((42, 43)), "(42,43)");
     }
 
+    #[test]
+    fn it_writes_maps() {
+        let map: std::collections::BTreeMap<_, _> = [("a", 1), ("b'", 2)].into_iter().collect();
+        assert_eq!(check(&map), "map('a',1,'b\\'',2)");
+        assert_eq!(check(std::collections::HashMap::<u32, u32>::new()), "map()");
+        assert_eq!(check(vec![map]), "[map('a',1,'b\\'',2)]");
+
+        let mut out = String::new();
+        let map: std::collections::BTreeMap<_, _> = [("a", vec![1])].into_iter().collect();
+        write_param(&mut out, &map).unwrap();
+        assert_eq!(out, "{'a':[1]}");
+    }
+
+    fn check_param(v: impl Serialize) -> String {
+        let mut out = String::new();
+        write_param(&mut out, &v).unwrap();
+        out
+    }
+
+    #[test]
+    fn it_writes_structs_as_tuples() {
+        #[derive(Serialize)]
+        struct Struct<'a> {
+            a: u32,
+            b: &'a str,
+            c: Vec<(u8, Option<i8>)>,
+        }
+        #[derive(Serialize)]
+        struct TupleStruct(u32, u32);
+
+        let value = Struct {
+            a: 42,
+            b: "x'y",
+            c: vec![(1, None), (2, Some(-1))],
+        };
+        assert_eq!(check(&value), "(42,'x\\'y',[(1,NULL),(2,-1)])");
+        assert_eq!(check_param(&value), "(42,'x\\'y',[(1,NULL),(2,-1)])");
+        assert_eq!(check(TupleStruct(1, 2)), "(1,2)");
+        assert_eq!(check_param(TupleStruct(1, 2)), "(1,2)");
+    }
+
+    #[test]
+    fn it_writes_params() {
+        assert_eq!(check_param("a'b"), "a\\'b");
+        assert_eq!(check_param(vec!["a'b"]), "['a\\'b']");
+        assert_eq!(check_param(vec![vec![1, 2], vec![]]), "[[1,2],[]]");
+        assert_eq!(check_param(None::<u32>), "NULL");
+        assert_eq!(check_param(vec![Some(1), None]), "[1,NULL]");
+        assert_eq!(check_param(42u128), "42");
+        assert_eq!(check_param(vec![-42i128]), "[-42]");
+
+        let map: std::collections::BTreeMap<_, _> = [(
+            "a",
+            [("b", 1)]
+                .into_iter()
+                .collect::<std::collections::BTreeMap<_, _>>(),
+        )]
+        .into_iter()
+        .collect();
+        assert_eq!(check_param(&map), "{'a':{'b':1}}");
+        assert_eq!(check(&map), "map('a',map('b',1))");
+
+        #[derive(Serialize)]
+        enum Enum {
+            A,
+        }
+        assert_eq!(check_param(Enum::A), "A");
+        assert_eq!(check_param(vec![Enum::A]), "['A']");
+    }
+
+    #[test]
+    fn it_writes_marked_values() {
+        #[derive(Serialize)]
+        struct Row {
+            #[serde(with = "crate::serde::ipv4")]
+            ip: std::net::Ipv4Addr,
+            #[serde(with = "crate::serde::ipv4::option")]
+            ip_opt: Option<std::net::Ipv4Addr>,
+        }
+
+        let ip = std::net::Ipv4Addr::new(192, 168, 0, 1);
+        let row = Row {
+            ip,
+            ip_opt: Some(ip),
+        };
+        assert_eq!(check(&row), "(3232235521,3232235521)");
+        assert_eq!(check_param(&row), "('192.168.0.1','192.168.0.1')");
+
+        assert_eq!(format_datetime64(1_700_000_000_123, 3), "1700000000.123");
+        assert_eq!(
+            format_datetime64(1_700_000_000_000_001, 6),
+            "1700000000.000001"
+        );
+        assert_eq!(format_datetime64(-1_500, 3), "-1.500");
+        assert_eq!(format_datetime64(42, 0), "42");
+    }
+
     #[test]
     fn it_writes_options() {
         assert_eq!(check(None::<i32>), "NULL");
@@ -521,23 +675,12 @@ mod tests {
     #[test]
     fn it_fails_on_unsupported() {
         let mut out = String::new();
-        assert!(write_arg(&mut out, &std::collections::HashMap::<u32, u32>::new()).is_err());
         assert!(write_arg(&mut out, &()).is_err());
 
         #[derive(Serialize)]
         struct Unit;
         assert!(write_arg(&mut out, &Unit).is_err());
 
-        #[derive(Serialize)]
-        struct Struct {
-            a: u32,
-        }
-        assert!(write_arg(&mut out, &Struct { a: 42 }).is_err());
-
-        #[derive(Serialize)]
-        struct TupleStruct(u32, u32);
-        assert!(write_arg(&mut out, &TupleStruct(42, 42)).is_err());
-
         #[derive(Serialize)]
         enum Enum {
             Newtype(u32),
@@ -0,0 +1,205 @@
+//! Contains [`Table`] and [`Partitions`] to manage partitions of a table.
+//!
+//! Partitions are specified by values implementing [`Bind`]: either by
+//! the partition expression (e.g. `202401` or `("a", 1)` for tuples)
+//! or by [`PartitionId`].
+//!
+//! # Examples
+//! ```
+//! # async fn example() -> clickhouse::error::Result<()> {
+//! use clickhouse::sql::PartitionId;
+//!
+//! let client = clickhouse::Client::default();
+//! let partitions = client.table("events").with_cluster("main").partitions();
+//!
+//! for partition in partitions.list().await? {
+//!     if partition.bytes_on_disk > 1 << 30 {
+//!         partitions.detach(PartitionId(&partition.partition_id)).await?;
+//!     }
+//! }
+//!
+//! partitions.drop(202401).await?;
+//! partitions.move_to_table(202402, "events_archive").await?;
+//! # Ok(()) }
+//! ```
+//!
+//! [`PartitionId`]: crate::sql::PartitionId
+
+use std::time::Duration;
+
+use crate::{
+    catalog::{Catalog, Partition},
+    error::{Error, Result},
+    query::Query,
+    sql::{Bind, Identifier, TableName},
+    Client,
+};
+
+/// A table, see [`Client::table()`].
+#[must_use]
+#[derive(Clone)]
+pub struct Table {
+    client: Client,
+    name: String,
+    cluster: Option<String>,
+}
+
+/// Manages partitions of the table, see [`Table::partitions()`].
+#[must_use]
+#[derive(Clone)]
+pub struct Partitions {
+    table: Table,
+}
+
+impl Table {
+    pub(crate) fn new(client: &Client, name: &str) -> Self {
+        Self {
+            client: client.clone(),
+            name: name.into(),
+            cluster: None,
+        }
+    }
+
+    /// Executes `ALTER` queries `ON CLUSTER`.
+    pub fn with_cluster(mut self, cluster: impl Into<String>) -> Self {
+        self.cluster = Some(cluster.into());
+        self
+    }
+
+    /// The name of the table as provided.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns an API to manage partitions of the table.
+    pub fn partitions(&self) -> Partitions {
+        Partitions {
+            table: self.clone(),
+        }
+    }
+
+    /// Waits until all mutations of the table are done, polling
+    /// `system.mutations` every `poll_interval`, e.g. after
+    /// `ALTER TABLE .. UPDATE` executed without `mutations_sync`.
+    ///
+    /// Fails if any unfinished mutation has failed to process a part.
+    /// Note that such mutations are still retried by the server.
+    pub async fn wait_mutations(&self, poll_interval: Duration) -> Result<()> {
+        let catalog = Catalog::new(&self.client);
+
+        loop {
+            let mutations = catalog.mutations(&self.name).await?;
+            let mut unfinished = mutations.iter().filter(|m| !m.is_done).peekable();
+
+            if unfinished.peek().is_none() {
+                return Ok(());
+            }
+
+            if let Some(failed) = unfinished.find(|m| !m.latest_fail_reason.is_empty()) {
+                return Err(Error::Other(
+                    format!(
+                        "mutation {} of {} has failed: {}",
+                        failed.mutation_id, self.name, failed.latest_fail_reason
+                    )
+                    .into(),
+                ));
+            }
+
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    // The table and the cluster are bound first, so arguments of `command`
+    // follow them.
+    fn alter(&self, command: &str) -> Query {
+        match &self.cluster {
+            Some(cluster) => self
+                .client
+                .query(&format!("ALTER TABLE ? ON CLUSTER ? {command}"))
+                .bind(TableName(&self.name))
+                .bind(Identifier(cluster)),
+            None => self
+                .client
+                .query(&format!("ALTER TABLE ? {command}"))
+                .bind(TableName(&self.name)),
+        }
+    }
+}
+
+impl Partitions {
+    /// Returns partitions with at least one active part and their sizes.
+    pub async fn list(&self) -> Result<Vec<Partition>> {
+        Catalog::new(&self.table.client)
+            .partitions(&self.table.name)
+            .await
+    }
+
+    /// Deletes the partition, `DROP PARTITION`.
+    pub async fn drop(&self, partition: impl Bind) -> Result<()> {
+        self.table
+            .alter("DROP PARTITION ?")
+            .bind(partition)
+            .execute()
+            .await
+    }
+
+    /// Moves the partition to the `detached` directory, `DETACH PARTITION`.
+    pub async fn detach(&self, partition: impl Bind) -> Result<()> {
+        self.table
+            .alter("DETACH PARTITION ?")
+            .bind(partition)
+            .execute()
+            .await
+    }
+
+    /// Adds the partition from the `detached` directory, `ATTACH PARTITION`.
+    pub async fn attach(&self, partition: impl Bind) -> Result<()> {
+        self.table
+            .alter("ATTACH PARTITION ?")
+            .bind(partition)
+            .execute()
+            .await
+    }
+
+    /// Moves the partition to another table with the same structure,
+    /// `MOVE PARTITION .. TO TABLE`.
+    pub async fn move_to_table(&self, partition: impl Bind, table: &str) -> Result<()> {
+        self.table
+            .alter("MOVE PARTITION ? TO TABLE ?")
+            .bind(partition)
+            .bind(TableName(table))
+            .execute()
+            .await
+    }
+
+    /// Replaces the partition with a copy of it from another table,
+    /// `REPLACE PARTITION .. FROM`.
+    pub async fn replace_from(&self, partition: impl Bind, table: &str) -> Result<()> {
+        self.table
+            .alter("REPLACE PARTITION ? FROM ?")
+            .bind(partition)
+            .bind(TableName(table))
+            .execute()
+            .await
+    }
+
+    /// Creates a backup of all partitions in `shadow/<name>`, `FREEZE WITH NAME`.
+    pub async fn freeze(&self, name: &str) -> Result<()> {
+        self.table
+            .alter("FREEZE WITH NAME ?")
+            .bind(name)
+            .execute()
+            .await
+    }
+
+    /// Creates a backup of the partition in `shadow/<name>`,
+    /// `FREEZE PARTITION .. WITH NAME`.
+    pub async fn freeze_partition(&self, partition: impl Bind, name: &str) -> Result<()> {
+        self.table
+            .alter("FREEZE PARTITION ? WITH NAME ?")
+            .bind(partition)
+            .bind(name)
+            .execute()
+            .await
+    }
+}
@@ -1,4 +1,4 @@
-use std::marker::PhantomData;
+use std::{collections::HashMap, marker::PhantomData};
 
 use bytes::Bytes;
 use futures::channel::oneshot;
@@ -51,6 +51,22 @@ where
     Thunk(Response::new(buffer.into()))
 }
 
+// === provide_json ===
+
+#[cfg(feature = "json")]
+#[track_caller]
+pub fn provide_json<T>(rows: impl IntoIterator<Item = T>) -> impl Handler
+where
+    T: Serialize,
+{
+    let mut buffer = Vec::with_capacity(BUFFER_INITIAL_CAPACITY);
+    for row in rows {
+        serde_json::to_writer(&mut buffer, &row).expect("failed to serialize");
+        buffer.push(b'\n');
+    }
+    Thunk(Response::new(buffer.into()))
+}
+
 // === record ===
 
 struct RecordHandler<T>(PhantomData<T>);
@@ -66,8 +82,16 @@ impl<T> super::Handler for RecordHandler<T> {
         let control = RecordControl { rx, marker };
 
         let h = Box::new(move |request: Request<Bytes>| -> Response<Bytes> {
+            let params = request
+                .uri()
+                .query()
+                .map(|query| url::form_urlencoded::parse(query.as_bytes()))
+                .into_iter()
+                .flatten()
+                .map(|(name, value)| (name.into_owned(), value.into_owned()))
+                .collect();
             let body = request.into_body();
-            let _ = tx.send(body);
+            let _ = tx.send((params, body));
             Response::new(<_>::default())
         });
 
@@ -76,7 +100,7 @@ impl<T> super::Handler for RecordHandler<T> {
 }
 
 pub struct RecordControl<T> {
-    rx: oneshot::Receiver<Bytes>,
+    rx: oneshot::Receiver<(HashMap<String, String>, Bytes)>,
     marker: PhantomData<T>,
 }
 
@@ -88,7 +112,16 @@ where
     where
         C: Default + Extend<T>,
     {
-        let bytes = self.rx.await.expect("query canceled");
+        self.collect_with_params().await.0
+    }
+
+    /// Same as [`RecordControl::collect()`], but also returns query
+    /// parameters of the request, including the query and settings.
+    pub async fn collect_with_params<C>(self) -> (C, HashMap<String, String>)
+    where
+        C: Default + Extend<T>,
+    {
+        let (params, bytes) = self.rx.await.expect("query canceled");
         let slice = &mut (&bytes[..]);
         let mut result = C::default();
 
@@ -97,7 +130,7 @@ where
             result.extend(std::iter::once(row));
         }
 
-        result
+        (result, params)
     }
 }
 
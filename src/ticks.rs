@@ -6,10 +6,10 @@ const PERIOD_THRESHOLD: Duration = Duration::from_secs(365 * 24 * 3600);
 
 // More efficient `Instant` based on TSC.
 #[cfg(not(feature = "test-util"))]
-type Instant = quanta::Instant;
+pub(crate) type Instant = quanta::Instant;
 
 #[cfg(feature = "test-util")]
-type Instant = tokio::time::Instant;
+pub(crate) type Instant = tokio::time::Instant;
 
 // === Ticks ===
 
@@ -0,0 +1,526 @@
+use std::fmt;
+
+use serde::{
+    de::{Error as _, SeqAccess},
+    ser::{SerializeTuple, Serializer},
+    Serialize,
+};
+
+// Binary encoding of data types, used by `Dynamic` and `JSON` values.
+// See https://clickhouse.com/docs/en/sql-reference/data-types/data-types-binary-encoding
+mod code {
+    pub(super) const NOTHING: u8 = 0x00;
+    pub(super) const UINT8: u8 = 0x01;
+    pub(super) const UINT16: u8 = 0x02;
+    pub(super) const UINT32: u8 = 0x03;
+    pub(super) const UINT64: u8 = 0x04;
+    pub(super) const UINT128: u8 = 0x05;
+    pub(super) const UINT256: u8 = 0x06;
+    pub(super) const INT8: u8 = 0x07;
+    pub(super) const INT16: u8 = 0x08;
+    pub(super) const INT32: u8 = 0x09;
+    pub(super) const INT64: u8 = 0x0a;
+    pub(super) const INT128: u8 = 0x0b;
+    pub(super) const INT256: u8 = 0x0c;
+    pub(super) const FLOAT32: u8 = 0x0d;
+    pub(super) const FLOAT64: u8 = 0x0e;
+    pub(super) const DATE: u8 = 0x0f;
+    pub(super) const DATE32: u8 = 0x10;
+    pub(super) const DATETIME: u8 = 0x11;
+    pub(super) const DATETIME_TZ: u8 = 0x12;
+    pub(super) const DATETIME64: u8 = 0x13;
+    pub(super) const DATETIME64_TZ: u8 = 0x14;
+    pub(super) const STRING: u8 = 0x15;
+    pub(super) const FIXED_STRING: u8 = 0x16;
+    pub(super) const ENUM8: u8 = 0x17;
+    pub(super) const ENUM16: u8 = 0x18;
+    pub(super) const DECIMAL32: u8 = 0x19;
+    pub(super) const DECIMAL64: u8 = 0x1a;
+    pub(super) const DECIMAL128: u8 = 0x1b;
+    pub(super) const DECIMAL256: u8 = 0x1c;
+    pub(super) const UUID: u8 = 0x1d;
+    pub(super) const ARRAY: u8 = 0x1e;
+    pub(super) const TUPLE: u8 = 0x1f;
+    pub(super) const NAMED_TUPLE: u8 = 0x20;
+    pub(super) const INTERVAL: u8 = 0x22;
+    pub(super) const NULLABLE: u8 = 0x23;
+    pub(super) const LOW_CARDINALITY: u8 = 0x26;
+    pub(super) const MAP: u8 = 0x27;
+    pub(super) const IPV4: u8 = 0x28;
+    pub(super) const IPV6: u8 = 0x29;
+    pub(super) const VARIANT: u8 = 0x2a;
+    pub(super) const DYNAMIC: u8 = 0x2b;
+    pub(super) const CUSTOM: u8 = 0x2c;
+    pub(super) const BOOL: u8 = 0x2d;
+    pub(super) const NESTED: u8 = 0x2f;
+    pub(super) const JSON: u8 = 0x30;
+    pub(super) const BFLOAT16: u8 = 0x31;
+}
+
+// Defaults used by ClickHouse for `Dynamic` and `JSON` without parameters.
+const DEFAULT_MAX_DYNAMIC_TYPES: u8 = 32;
+const DEFAULT_MAX_DYNAMIC_PATHS: u64 = 1024;
+const JSON_SERIALIZATION_VERSION: u8 = 0;
+
+/// A ClickHouse data type, as it's encoded in front of `Dynamic` values.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum DataType {
+    Nothing,
+    Bool,
+    UInt8,
+    UInt16,
+    UInt32,
+    UInt64,
+    UInt128,
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    Int128,
+    Float32,
+    Float64,
+    BFloat16,
+    String,
+    FixedString(usize),
+    Date,
+    Date32,
+    DateTime(Option<String>),
+    DateTime64(u8, Option<String>),
+    Decimal(u8, u8),
+    Uuid,
+    IPv4,
+    IPv6,
+    Enum8(Vec<(String, i8)>),
+    Enum16(Vec<(String, i16)>),
+    Interval,
+    Array(Box<DataType>),
+    Tuple(Vec<DataType>),
+    NamedTuple(Vec<(String, DataType)>),
+    Nullable(Box<DataType>),
+    LowCardinality(Box<DataType>),
+    Map(Box<DataType>, Box<DataType>),
+    Variant(Vec<DataType>),
+    Dynamic,
+    Json(Vec<(String, DataType)>),
+}
+
+impl DataType {
+    /// Returns the narrowest `Decimal*` width (in bytes) for the precision.
+    pub(crate) fn decimal_size(precision: u8) -> usize {
+        match precision {
+            0..=9 => 4,
+            10..=18 => 8,
+            19..=38 => 16,
+            _ => 32,
+        }
+    }
+
+    /// Whether the type can be wrapped into `Nullable`.
+    pub(crate) fn can_be_nullable(&self) -> bool {
+        !matches!(
+            self,
+            Self::Nullable(_)
+                | Self::Array(_)
+                | Self::Tuple(_)
+                | Self::NamedTuple(_)
+                | Self::Map(..)
+                | Self::LowCardinality(_)
+                | Self::Variant(_)
+                | Self::Dynamic
+                | Self::Json(_)
+        )
+    }
+
+    /// Reads a binary encoded type from elements of a sequence.
+    pub(crate) fn decode<'de, A: SeqAccess<'de>>(seq: &mut A) -> Result<Self, A::Error> {
+        let code = next::<u8, A>(seq)?;
+
+        Ok(match code {
+            code::NOTHING => Self::Nothing,
+            code::UINT8 => Self::UInt8,
+            code::UINT16 => Self::UInt16,
+            code::UINT32 => Self::UInt32,
+            code::UINT64 => Self::UInt64,
+            code::UINT128 => Self::UInt128,
+            code::INT8 => Self::Int8,
+            code::INT16 => Self::Int16,
+            code::INT32 => Self::Int32,
+            code::INT64 => Self::Int64,
+            code::INT128 => Self::Int128,
+            code::FLOAT32 => Self::Float32,
+            code::FLOAT64 => Self::Float64,
+            code::BFLOAT16 => Self::BFloat16,
+            code::DATE => Self::Date,
+            code::DATE32 => Self::Date32,
+            code::DATETIME => Self::DateTime(None),
+            code::DATETIME_TZ => Self::DateTime(Some(next(seq)?)),
+            code::DATETIME64 => Self::DateTime64(next(seq)?, None),
+            code::DATETIME64_TZ => Self::DateTime64(next(seq)?, Some(next(seq)?)),
+            code::STRING => Self::String,
+            code::FIXED_STRING => Self::FixedString(read_size(seq)?),
+            code::ENUM8 => Self::Enum8(read_list(seq, |seq| Ok((next(seq)?, next(seq)?)))?),
+            code::ENUM16 => Self::Enum16(read_list(seq, |seq| Ok((next(seq)?, next(seq)?)))?),
+            code::DECIMAL32 | code::DECIMAL64 | code::DECIMAL128 => {
+                Self::Decimal(next(seq)?, next(seq)?)
+            }
+            code::UUID => Self::Uuid,
+            code::ARRAY => Self::Array(Box::new(Self::decode(seq)?)),
+            code::TUPLE => Self::Tuple(read_list(seq, Self::decode)?),
+            code::NAMED_TUPLE | code::NESTED => {
+                let fields = read_list(seq, |seq| Ok((next(seq)?, Self::decode(seq)?)))?;
+                if code == code::NESTED {
+                    Self::Array(Box::new(Self::NamedTuple(fields)))
+                } else {
+                    Self::NamedTuple(fields)
+                }
+            }
+            code::INTERVAL => {
+                let _kind: u8 = next(seq)?;
+                Self::Interval
+            }
+            code::NULLABLE => Self::Nullable(Box::new(Self::decode(seq)?)),
+            code::LOW_CARDINALITY => Self::LowCardinality(Box::new(Self::decode(seq)?)),
+            code::MAP => Self::Map(Box::new(Self::decode(seq)?), Box::new(Self::decode(seq)?)),
+            code::IPV4 => Self::IPv4,
+            code::IPV6 => Self::IPv6,
+            code::VARIANT => Self::Variant(read_list(seq, Self::decode)?),
+            code::DYNAMIC => {
+                let _max_types: u8 = next(seq)?;
+                Self::Dynamic
+            }
+            code::CUSTOM => {
+                let name: String = next(seq)?;
+                Self::custom(&name)
+                    .ok_or_else(|| A::Error::custom(format!("unsupported custom type: {name}")))?
+            }
+            code::BOOL => Self::Bool,
+            code::JSON => {
+                let _version: u8 = next(seq)?;
+                let _max_dynamic_paths = read_leb128(seq)?;
+                let _max_dynamic_types: u8 = next(seq)?;
+                let typed_paths = read_list(seq, |seq| Ok((next(seq)?, Self::decode(seq)?)))?;
+                let _skip_paths = read_list(seq, next::<String, A>)?;
+                let _skip_regexps = read_list(seq, next::<String, A>)?;
+                Self::Json(typed_paths)
+            }
+            code::UINT256 | code::INT256 | code::DECIMAL256 => {
+                return Err(A::Error::custom(format!(
+                    "unsupported 256-bit data type: 0x{code:02x}"
+                )));
+            }
+            _ => return Err(A::Error::custom(format!("unknown data type: 0x{code:02x}"))),
+        })
+    }
+
+    // Geo types are encoded as custom types over their underlying layout.
+    fn custom(name: &str) -> Option<Self> {
+        let point = || Self::Tuple(vec![Self::Float64, Self::Float64]);
+        let array = |inner| Self::Array(Box::new(inner));
+
+        Some(match name {
+            "Point" => point(),
+            "Ring" | "LineString" => array(point()),
+            "Polygon" | "MultiLineString" => array(array(point())),
+            "MultiPolygon" => array(array(array(point()))),
+            _ => return None,
+        })
+    }
+
+    /// Returns a serializable binary encoding of the type.
+    pub(crate) fn encoded(&self) -> Encoded<'_> {
+        Encoded(self)
+    }
+
+    fn encode<S: SerializeTuple>(&self, tuple: &mut S) -> Result<(), S::Error> {
+        let code = match self {
+            Self::Nothing => code::NOTHING,
+            Self::Bool => code::BOOL,
+            Self::UInt8 => code::UINT8,
+            Self::UInt16 => code::UINT16,
+            Self::UInt32 => code::UINT32,
+            Self::UInt64 => code::UINT64,
+            Self::UInt128 => code::UINT128,
+            Self::Int8 => code::INT8,
+            Self::Int16 => code::INT16,
+            Self::Int32 => code::INT32,
+            Self::Int64 => code::INT64,
+            Self::Int128 => code::INT128,
+            Self::Float32 => code::FLOAT32,
+            Self::Float64 => code::FLOAT64,
+            Self::BFloat16 => code::BFLOAT16,
+            Self::String => code::STRING,
+            Self::Date => code::DATE,
+            Self::Date32 => code::DATE32,
+            Self::Uuid => code::UUID,
+            Self::IPv4 => code::IPV4,
+            Self::IPv6 => code::IPV6,
+            Self::FixedString(size) => {
+                tuple.serialize_element(&code::FIXED_STRING)?;
+                return write_leb128(tuple, *size as u64);
+            }
+            Self::DateTime(None) => code::DATETIME,
+            Self::DateTime(Some(tz)) => {
+                tuple.serialize_element(&code::DATETIME_TZ)?;
+                return tuple.serialize_element(tz);
+            }
+            Self::DateTime64(precision, None) => {
+                tuple.serialize_element(&code::DATETIME64)?;
+                return tuple.serialize_element(precision);
+            }
+            Self::DateTime64(precision, Some(tz)) => {
+                tuple.serialize_element(&code::DATETIME64_TZ)?;
+                tuple.serialize_element(precision)?;
+                return tuple.serialize_element(tz);
+            }
+            Self::Decimal(precision, scale) => {
+                let code = match Self::decimal_size(*precision) {
+                    4 => code::DECIMAL32,
+                    8 => code::DECIMAL64,
+                    16 => code::DECIMAL128,
+                    _ => code::DECIMAL256,
+                };
+                tuple.serialize_element(&code)?;
+                tuple.serialize_element(precision)?;
+                return tuple.serialize_element(scale);
+            }
+            Self::Enum8(items) => {
+                tuple.serialize_element(&code::ENUM8)?;
+                write_leb128(tuple, items.len() as u64)?;
+                for (name, value) in items {
+                    tuple.serialize_element(name)?;
+                    tuple.serialize_element(value)?;
+                }
+                return Ok(());
+            }
+            Self::Enum16(items) => {
+                tuple.serialize_element(&code::ENUM16)?;
+                write_leb128(tuple, items.len() as u64)?;
+                for (name, value) in items {
+                    tuple.serialize_element(name)?;
+                    tuple.serialize_element(value)?;
+                }
+                return Ok(());
+            }
+            Self::Interval => {
+                return Err(serde::ser::Error::custom(
+                    "Interval cannot be used in Dynamic values",
+                ))
+            }
+            Self::Array(inner) => {
+                tuple.serialize_element(&code::ARRAY)?;
+                return inner.encode(tuple);
+            }
+            Self::Tuple(items) | Self::Variant(items) => {
+                let code = if matches!(self, Self::Tuple(_)) {
+                    code::TUPLE
+                } else {
+                    code::VARIANT
+                };
+                tuple.serialize_element(&code)?;
+                write_leb128(tuple, items.len() as u64)?;
+                for item in items {
+                    item.encode(tuple)?;
+                }
+                return Ok(());
+            }
+            Self::NamedTuple(fields) => {
+                tuple.serialize_element(&code::NAMED_TUPLE)?;
+                write_leb128(tuple, fields.len() as u64)?;
+                for (name, item) in fields {
+                    tuple.serialize_element(name)?;
+                    item.encode(tuple)?;
+                }
+                return Ok(());
+            }
+            Self::Nullable(inner) | Self::LowCardinality(inner) => {
+                let code = if matches!(self, Self::Nullable(_)) {
+                    code::NULLABLE
+                } else {
+                    code::LOW_CARDINALITY
+                };
+                tuple.serialize_element(&code)?;
+                return inner.encode(tuple);
+            }
+            Self::Map(key, value) => {
+                tuple.serialize_element(&code::MAP)?;
+                key.encode(tuple)?;
+                return value.encode(tuple);
+            }
+            Self::Dynamic => {
+                tuple.serialize_element(&code::DYNAMIC)?;
+                return tuple.serialize_element(&DEFAULT_MAX_DYNAMIC_TYPES);
+            }
+            Self::Json(typed_paths) => {
+                tuple.serialize_element(&code::JSON)?;
+                tuple.serialize_element(&JSON_SERIALIZATION_VERSION)?;
+                write_leb128(tuple, DEFAULT_MAX_DYNAMIC_PATHS)?;
+                tuple.serialize_element(&DEFAULT_MAX_DYNAMIC_TYPES)?;
+                write_leb128(tuple, typed_paths.len() as u64)?;
+                for (path, item) in typed_paths {
+                    tuple.serialize_element(path)?;
+                    item.encode(tuple)?;
+                }
+                write_leb128(tuple, 0)?; // skip paths
+                return write_leb128(tuple, 0); // skip regexps
+            }
+        };
+
+        tuple.serialize_element(&code)
+    }
+}
+
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn list<T>(
+            f: &mut fmt::Formatter<'_>,
+            items: &[T],
+            mut item: impl FnMut(&mut fmt::Formatter<'_>, &T) -> fmt::Result,
+        ) -> fmt::Result {
+            for (idx, it) in items.iter().enumerate() {
+                if idx > 0 {
+                    f.write_str(", ")?;
+                }
+                item(f, it)?;
+            }
+            Ok(())
+        }
+
+        match self {
+            Self::Nothing => f.write_str("Nothing"),
+            Self::Bool => f.write_str("Bool"),
+            Self::UInt8 => f.write_str("UInt8"),
+            Self::UInt16 => f.write_str("UInt16"),
+            Self::UInt32 => f.write_str("UInt32"),
+            Self::UInt64 => f.write_str("UInt64"),
+            Self::UInt128 => f.write_str("UInt128"),
+            Self::Int8 => f.write_str("Int8"),
+            Self::Int16 => f.write_str("Int16"),
+            Self::Int32 => f.write_str("Int32"),
+            Self::Int64 => f.write_str("Int64"),
+            Self::Int128 => f.write_str("Int128"),
+            Self::Float32 => f.write_str("Float32"),
+            Self::Float64 => f.write_str("Float64"),
+            Self::BFloat16 => f.write_str("BFloat16"),
+            Self::String => f.write_str("String"),
+            Self::FixedString(size) => write!(f, "FixedString({size})"),
+            Self::Date => f.write_str("Date"),
+            Self::Date32 => f.write_str("Date32"),
+            Self::DateTime(None) => f.write_str("DateTime"),
+            Self::DateTime(Some(tz)) => write!(f, "DateTime('{tz}')"),
+            Self::DateTime64(precision, None) => write!(f, "DateTime64({precision})"),
+            Self::DateTime64(precision, Some(tz)) => write!(f, "DateTime64({precision}, '{tz}')"),
+            Self::Decimal(precision, scale) => write!(f, "Decimal({precision}, {scale})"),
+            Self::Uuid => f.write_str("UUID"),
+            Self::IPv4 => f.write_str("IPv4"),
+            Self::IPv6 => f.write_str("IPv6"),
+            Self::Enum8(items) => {
+                f.write_str("Enum8(")?;
+                list(f, items, |f, (name, value)| write!(f, "'{name}' = {value}"))?;
+                f.write_str(")")
+            }
+            Self::Enum16(items) => {
+                f.write_str("Enum16(")?;
+                list(f, items, |f, (name, value)| write!(f, "'{name}' = {value}"))?;
+                f.write_str(")")
+            }
+            Self::Interval => f.write_str("Interval"),
+            Self::Array(inner) => write!(f, "Array({inner})"),
+            Self::Tuple(items) => {
+                f.write_str("Tuple(")?;
+                list(f, items, |f, item| write!(f, "{item}"))?;
+                f.write_str(")")
+            }
+            Self::NamedTuple(fields) => {
+                f.write_str("Tuple(")?;
+                list(f, fields, |f, (name, item)| write!(f, "{name} {item}"))?;
+                f.write_str(")")
+            }
+            Self::Nullable(inner) => write!(f, "Nullable({inner})"),
+            Self::LowCardinality(inner) => write!(f, "LowCardinality({inner})"),
+            Self::Map(key, value) => write!(f, "Map({key}, {value})"),
+            Self::Variant(items) => {
+                f.write_str("Variant(")?;
+                list(f, items, |f, item| write!(f, "{item}"))?;
+                f.write_str(")")
+            }
+            Self::Dynamic => f.write_str("Dynamic"),
+            Self::Json(typed_paths) if typed_paths.is_empty() => f.write_str("JSON"),
+            Self::Json(typed_paths) => {
+                f.write_str("JSON(")?;
+                list(f, typed_paths, |f, (path, item)| write!(f, "{path} {item}"))?;
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+/// See [`DataType::encoded()`].
+pub(crate) struct Encoded<'a>(&'a DataType);
+
+impl Serialize for Encoded<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // The length isn't written in RowBinary, any value is fine here.
+        let mut tuple = serializer.serialize_tuple(usize::MAX)?;
+        self.0.encode(&mut tuple)?;
+        tuple.end()
+    }
+}
+
+fn next<'de, T: serde::Deserialize<'de>, A: SeqAccess<'de>>(seq: &mut A) -> Result<T, A::Error> {
+    seq.next_element()?
+        .ok_or_else(|| A::Error::custom("unexpected end of an encoded data type"))
+}
+
+fn read_leb128<'de, A: SeqAccess<'de>>(seq: &mut A) -> Result<u64, A::Error> {
+    let mut value = 0u64;
+    let mut shift = 0;
+
+    loop {
+        let byte: u8 = next(seq)?;
+        value |= (byte as u64 & 0x7f) << shift;
+
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+
+        shift += 7;
+        if shift > 57 {
+            return Err(A::Error::custom("invalid LEB128 in an encoded data type"));
+        }
+    }
+}
+
+fn read_size<'de, A: SeqAccess<'de>>(seq: &mut A) -> Result<usize, A::Error> {
+    let size = read_leb128(seq)?;
+    usize::try_from(size).map_err(|_| A::Error::custom("too large size in an encoded data type"))
+}
+
+fn read_list<'de, A: SeqAccess<'de>, T>(
+    seq: &mut A,
+    mut read: impl FnMut(&mut A) -> Result<T, A::Error>,
+) -> Result<Vec<T>, A::Error> {
+    let len = read_size(seq)?;
+    // Don't trust the length to preallocate memory.
+    let mut items = Vec::with_capacity(len.min(64));
+    for _ in 0..len {
+        items.push(read(seq)?);
+    }
+    Ok(items)
+}
+
+fn write_leb128<S: SerializeTuple>(tuple: &mut S, mut value: u64) -> Result<(), S::Error> {
+    loop {
+        let mut byte = value as u8 & 0x7f;
+        value >>= 7;
+
+        if value != 0 {
+            byte |= 0x80;
+        }
+
+        tuple.serialize_element(&byte)?;
+
+        if value == 0 {
+            return Ok(());
+        }
+    }
+}
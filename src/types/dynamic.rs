@@ -0,0 +1,1013 @@
+use std::{
+    fmt,
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+use serde::{
+    de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor},
+    ser::{SerializeMap, SerializeSeq, SerializeTuple, Serializer},
+    Deserialize, Serialize,
+};
+
+use super::{data_type::DataType, json::JsonSeed, Json};
+
+/// A value of the [`Dynamic`] data type.
+///
+/// In RowBinary, every value is prefixed with its binary encoded data type,
+/// so it can be (de)serialized without knowing the column type in advance.
+/// When inserting, the data type is inferred from the value:
+/// * integers, floats and strings map to the corresponding ClickHouse types;
+/// * `Array` uses the common type of its elements, wrapped into `Nullable`
+///   if some of them are `Null`, or `Dynamic` if the elements differ;
+/// * `Map` does the same for keys and values separately.
+///
+/// Some information is lost when selecting values: `Enum8` and `Enum16` are
+/// returned as strings, `Variant` and `LowCardinality` as their inner types,
+/// named tuples as `Tuple`, and time zones are dropped. `Int256`, `UInt256`
+/// and `Decimal256` aren't supported.
+///
+/// For human-readable formats (e.g. `serde_json` or [`Query::bind`]), the
+/// value is serialized without the data type, as a plain value.
+///
+/// [`Dynamic`]: https://clickhouse.com/docs/en/sql-reference/data-types/dynamic
+/// [`Query::bind`]: crate::query::Query::bind
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Dynamic {
+    Null,
+    Bool(bool),
+    UInt8(u8),
+    UInt16(u16),
+    UInt32(u32),
+    UInt64(u64),
+    UInt128(u128),
+    Int8(i8),
+    Int16(i16),
+    Int32(i32),
+    Int64(i64),
+    Int128(i128),
+    Float32(f32),
+    Float64(f64),
+    String(String),
+    FixedString(Vec<u8>),
+    /// A number of days since `1970-01-01`.
+    Date(u16),
+    /// A number of days since `1970-01-01`.
+    Date32(i32),
+    /// A number of seconds since UNIX epoch.
+    DateTime(u32),
+    /// A number of ticks since UNIX epoch, where a tick is `10^-precision`s.
+    DateTime64 {
+        value: i64,
+        precision: u8,
+    },
+    /// A fixed-point number equal to `value * 10^-scale`.
+    Decimal {
+        value: i128,
+        precision: u8,
+        scale: u8,
+    },
+    /// Bytes of `UUID` in the big-endian order.
+    Uuid([u8; 16]),
+    IPv4(Ipv4Addr),
+    IPv6(Ipv6Addr),
+    Array(Vec<Dynamic>),
+    Tuple(Vec<Dynamic>),
+    Map(Vec<(Dynamic, Dynamic)>),
+    Json(Json),
+}
+
+impl Dynamic {
+    /// Returns `true` if the value is `Null`.
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    /// Returns the value as a boolean, if it's `Bool`.
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Self::Bool(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as `i64`, if it's an integer fitting into `i64`.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Self::UInt8(v) => Some(v.into()),
+            Self::UInt16(v) => Some(v.into()),
+            Self::UInt32(v) => Some(v.into()),
+            Self::UInt64(v) => v.try_into().ok(),
+            Self::UInt128(v) => v.try_into().ok(),
+            Self::Int8(v) => Some(v.into()),
+            Self::Int16(v) => Some(v.into()),
+            Self::Int32(v) => Some(v.into()),
+            Self::Int64(v) => Some(v),
+            Self::Int128(v) => v.try_into().ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as `u64`, if it's an integer fitting into `u64`.
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Self::UInt64(v) => Some(v),
+            Self::UInt128(v) => v.try_into().ok(),
+            Self::Int128(v) => v.try_into().ok(),
+            _ => self.as_i64().and_then(|v| v.try_into().ok()),
+        }
+    }
+
+    /// Returns the value as `f64`, if it's a float or an integer.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Self::Float32(v) => Some(v.into()),
+            Self::Float64(v) => Some(v),
+            Self::UInt64(v) => Some(v as f64),
+            Self::UInt128(v) => Some(v as f64),
+            Self::Int128(v) => Some(v as f64),
+            _ => self.as_i64().map(|v| v as f64),
+        }
+    }
+
+    /// Returns the value as a string slice, if it's `String`.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns elements of `Array` or `Tuple`.
+    pub fn as_slice(&self) -> Option<&[Dynamic]> {
+        match self {
+            Self::Array(v) | Self::Tuple(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as [`Json`], if it's `Json`.
+    pub fn as_json(&self) -> Option<&Json> {
+        match self {
+            Self::Json(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn data_type(&self) -> DataType {
+        match self {
+            Self::Null => DataType::Nothing,
+            Self::Bool(_) => DataType::Bool,
+            Self::UInt8(_) => DataType::UInt8,
+            Self::UInt16(_) => DataType::UInt16,
+            Self::UInt32(_) => DataType::UInt32,
+            Self::UInt64(_) => DataType::UInt64,
+            Self::UInt128(_) => DataType::UInt128,
+            Self::Int8(_) => DataType::Int8,
+            Self::Int16(_) => DataType::Int16,
+            Self::Int32(_) => DataType::Int32,
+            Self::Int64(_) => DataType::Int64,
+            Self::Int128(_) => DataType::Int128,
+            Self::Float32(_) => DataType::Float32,
+            Self::Float64(_) => DataType::Float64,
+            Self::String(_) => DataType::String,
+            Self::FixedString(v) => DataType::FixedString(v.len()),
+            Self::Date(_) => DataType::Date,
+            Self::Date32(_) => DataType::Date32,
+            Self::DateTime(_) => DataType::DateTime(None),
+            Self::DateTime64 { precision, .. } => DataType::DateTime64(*precision, None),
+            Self::Decimal {
+                precision, scale, ..
+            } => DataType::Decimal(*precision, *scale),
+            Self::Uuid(_) => DataType::Uuid,
+            Self::IPv4(_) => DataType::IPv4,
+            Self::IPv6(_) => DataType::IPv6,
+            Self::Array(items) => DataType::Array(Box::new(common_type(items.iter()))),
+            Self::Tuple(items) => DataType::Tuple(
+                items
+                    .iter()
+                    .map(|item| match item {
+                        Self::Null => DataType::Nullable(Box::new(DataType::Nothing)),
+                        item => item.data_type(),
+                    })
+                    .collect(),
+            ),
+            Self::Map(pairs) => DataType::Map(
+                Box::new(common_type(pairs.iter().map(|(k, _)| k))),
+                Box::new(common_type(pairs.iter().map(|(_, v)| v))),
+            ),
+            Self::Json(_) => DataType::Json(Vec::new()),
+        }
+    }
+}
+
+/// Infers a type that can hold all the values, e.g. elements of an array.
+fn common_type<'a>(items: impl Iterator<Item = &'a Dynamic>) -> DataType {
+    let mut common = None;
+    let mut has_nulls = false;
+
+    for item in items {
+        if item.is_null() {
+            has_nulls = true;
+            continue;
+        }
+
+        let ty = item.data_type();
+        match &common {
+            None => common = Some(ty),
+            Some(common) if *common == ty => {}
+            Some(_) => return DataType::Dynamic,
+        }
+    }
+
+    match common {
+        Some(ty) if has_nulls && ty.can_be_nullable() => DataType::Nullable(Box::new(ty)),
+        Some(_) if has_nulls => DataType::Dynamic,
+        Some(ty) => ty,
+        None if has_nulls => DataType::Nullable(Box::new(DataType::Nothing)),
+        None => DataType::Dynamic,
+    }
+}
+
+// === Conversions ===
+
+macro_rules! impl_from {
+    ($($ty:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$ty> for Dynamic {
+                fn from(value: $ty) -> Self {
+                    Self::$variant(value.into())
+                }
+            }
+        )*
+    };
+}
+
+impl_from!(
+    bool => Bool,
+    u8 => UInt8,
+    u16 => UInt16,
+    u32 => UInt32,
+    u64 => UInt64,
+    u128 => UInt128,
+    i8 => Int8,
+    i16 => Int16,
+    i32 => Int32,
+    i64 => Int64,
+    i128 => Int128,
+    f32 => Float32,
+    f64 => Float64,
+    String => String,
+    &str => String,
+    Ipv4Addr => IPv4,
+    Ipv6Addr => IPv6,
+    Json => Json,
+);
+
+impl<T: Into<Dynamic>> From<Option<T>> for Dynamic {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+
+impl<T: Into<Dynamic>> From<Vec<T>> for Dynamic {
+    fn from(value: Vec<T>) -> Self {
+        Self::Array(value.into_iter().map(Into::into).collect())
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl From<::uuid::Uuid> for Dynamic {
+    fn from(value: ::uuid::Uuid) -> Self {
+        Self::Uuid(value.into_bytes())
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<serde_json::Value> for Dynamic {
+    fn from(value: serde_json::Value) -> Self {
+        use serde_json::Value;
+
+        match value {
+            Value::Null => Self::Null,
+            Value::Bool(v) => Self::Bool(v),
+            Value::Number(v) => {
+                if let Some(v) = v.as_i64() {
+                    Self::Int64(v)
+                } else if let Some(v) = v.as_u64() {
+                    Self::UInt64(v)
+                } else {
+                    Self::Float64(v.as_f64().unwrap_or(f64::NAN))
+                }
+            }
+            Value::String(v) => Self::String(v),
+            Value::Array(v) => Self::Array(v.into_iter().map(Into::into).collect()),
+            Value::Object(v) => Self::Json(v.into()),
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<Dynamic> for serde_json::Value {
+    fn from(value: Dynamic) -> Self {
+        use serde_json::{Number, Value};
+
+        fn int<T: TryInto<i64> + TryInto<u64> + ToString + Copy>(v: T) -> Value {
+            if let Ok(v) = TryInto::<i64>::try_into(v) {
+                Value::from(v)
+            } else if let Ok(v) = TryInto::<u64>::try_into(v) {
+                Value::from(v)
+            } else {
+                Value::String(v.to_string())
+            }
+        }
+
+        let float = |v: f64| Number::from_f64(v).map_or(Value::Null, Value::Number);
+
+        match value {
+            Dynamic::Null => Value::Null,
+            Dynamic::Bool(v) => Value::Bool(v),
+            Dynamic::UInt8(v) => v.into(),
+            Dynamic::UInt16(v) => v.into(),
+            Dynamic::UInt32(v) => v.into(),
+            Dynamic::UInt64(v) => v.into(),
+            Dynamic::UInt128(v) => int(v),
+            Dynamic::Int8(v) => v.into(),
+            Dynamic::Int16(v) => v.into(),
+            Dynamic::Int32(v) => v.into(),
+            Dynamic::Int64(v) => v.into(),
+            Dynamic::Int128(v) => int(v),
+            Dynamic::Float32(v) => float(v.into()),
+            Dynamic::Float64(v) => float(v),
+            Dynamic::String(v) => Value::String(v),
+            Dynamic::FixedString(v) => Value::String(String::from_utf8_lossy(&v).into_owned()),
+            Dynamic::Date(v) => v.into(),
+            Dynamic::Date32(v) => v.into(),
+            Dynamic::DateTime(v) => v.into(),
+            Dynamic::DateTime64 { value, .. } => value.into(),
+            v @ (Dynamic::Decimal { .. } | Dynamic::Uuid(_)) => Value::String(v.to_string()),
+            Dynamic::IPv4(v) => Value::String(v.to_string()),
+            Dynamic::IPv6(v) => Value::String(v.to_string()),
+            Dynamic::Array(v) | Dynamic::Tuple(v) => {
+                Value::Array(v.into_iter().map(Into::into).collect())
+            }
+            Dynamic::Map(pairs) if pairs.iter().all(|(k, _)| k.as_str().is_some()) => {
+                let map = pairs.into_iter().map(|(k, v)| match k {
+                    Dynamic::String(k) => (k, v.into()),
+                    _ => unreachable!(),
+                });
+                Value::Object(map.collect())
+            }
+            Dynamic::Map(pairs) => Value::Array(
+                pairs
+                    .into_iter()
+                    .map(|(k, v)| Value::Array(vec![k.into(), v.into()]))
+                    .collect(),
+            ),
+            Dynamic::Json(v) => v.into(),
+        }
+    }
+}
+
+/// Formats `Decimal` and `UUID` values in the same way as ClickHouse does,
+/// other values are formatted using their `Debug` representation.
+impl fmt::Display for Dynamic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Decimal { value, scale, .. } => {
+                let scale = usize::from(*scale);
+                let digits = value.unsigned_abs().to_string();
+                let sign = if *value < 0 { "-" } else { "" };
+
+                if scale == 0 {
+                    write!(f, "{sign}{digits}")
+                } else if digits.len() > scale {
+                    let (int, frac) = digits.split_at(digits.len() - scale);
+                    write!(f, "{sign}{int}.{frac}")
+                } else {
+                    write!(f, "{sign}0.{digits:0>scale$}")
+                }
+            }
+            Self::Uuid(bytes) => {
+                for (idx, byte) in bytes.iter().enumerate() {
+                    if matches!(idx, 4 | 6 | 8 | 10) {
+                        f.write_str("-")?;
+                    }
+                    write!(f, "{byte:02x}")?;
+                }
+                Ok(())
+            }
+            other => fmt::Debug::fmt(other, f),
+        }
+    }
+}
+
+// === Serialize ===
+
+impl Serialize for Dynamic {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return self.serialize_plain(serializer);
+        }
+
+        let ty = self.data_type();
+        let mut tuple = serializer.serialize_tuple(2)?;
+        tuple.serialize_element(&ty.encoded())?;
+        if ty != DataType::Nothing {
+            tuple.serialize_element(&Typed(self, &ty))?;
+        }
+        tuple.end()
+    }
+}
+
+impl Dynamic {
+    fn serialize_plain<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Null => serializer.serialize_none(),
+            Self::Bool(v) => serializer.serialize_bool(*v),
+            Self::UInt8(v) => serializer.serialize_u8(*v),
+            Self::UInt16(v) | Self::Date(v) => serializer.serialize_u16(*v),
+            Self::UInt32(v) | Self::DateTime(v) => serializer.serialize_u32(*v),
+            Self::UInt64(v) => serializer.serialize_u64(*v),
+            Self::UInt128(v) => serializer.serialize_u128(*v),
+            Self::Int8(v) => serializer.serialize_i8(*v),
+            Self::Int16(v) => serializer.serialize_i16(*v),
+            Self::Int32(v) | Self::Date32(v) => serializer.serialize_i32(*v),
+            Self::Int64(v) | Self::DateTime64 { value: v, .. } => serializer.serialize_i64(*v),
+            Self::Int128(v) => serializer.serialize_i128(*v),
+            Self::Float32(v) => serializer.serialize_f32(*v),
+            Self::Float64(v) => serializer.serialize_f64(*v),
+            Self::String(v) => serializer.serialize_str(v),
+            Self::FixedString(v) => serializer.serialize_str(&String::from_utf8_lossy(v)),
+            Self::Decimal { .. } | Self::Uuid(_) => serializer.collect_str(self),
+            Self::IPv4(v) => serializer.collect_str(v),
+            Self::IPv6(v) => serializer.collect_str(v),
+            Self::Array(items) | Self::Tuple(items) => items.serialize(serializer),
+            Self::Map(pairs) if pairs.iter().all(|(k, _)| k.as_str().is_some()) => {
+                let mut map = serializer.serialize_map(Some(pairs.len()))?;
+                for (key, value) in pairs {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+            Self::Map(pairs) => pairs.serialize(serializer),
+            Self::Json(v) => v.serialize(serializer),
+        }
+    }
+
+    fn serialize_value<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::Error;
+
+        match self {
+            Self::Decimal {
+                value, precision, ..
+            } => match DataType::decimal_size(*precision) {
+                4 => i32::try_from(*value)
+                    .map_err(S::Error::custom)?
+                    .serialize(serializer),
+                8 => i64::try_from(*value)
+                    .map_err(S::Error::custom)?
+                    .serialize(serializer),
+                16 => value.serialize(serializer),
+                _ => Err(S::Error::custom("Decimal256 is unsupported")),
+            },
+            Self::Uuid(bytes) => {
+                let value = u128::from_be_bytes(*bytes);
+                ((value >> 64) as u64, value as u64).serialize(serializer)
+            }
+            Self::FixedString(v) => {
+                let mut tuple = serializer.serialize_tuple(v.len())?;
+                for byte in v {
+                    tuple.serialize_element(byte)?;
+                }
+                tuple.end()
+            }
+            Self::IPv4(v) => u32::from(*v).serialize(serializer),
+            Self::IPv6(v) => v.octets().serialize(serializer),
+            // Containers are handled in `Typed`.
+            Self::Array(_) | Self::Tuple(_) | Self::Map(_) | Self::Json(_) | Self::Null => {
+                Err(S::Error::custom("unexpected container in Dynamic"))
+            }
+            other => other.serialize_plain(serializer),
+        }
+    }
+}
+
+/// A value serialized according to the specified (inferred) data type.
+struct Typed<'a>(&'a Dynamic, &'a DataType);
+
+impl Serialize for Typed<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::Error;
+
+        match (self.1, self.0) {
+            (DataType::Dynamic, value) => value.serialize(serializer),
+            (DataType::Nullable(_), Dynamic::Null) => serializer.serialize_none(),
+            (DataType::Nullable(ty), value) => serializer.serialize_some(&Typed(value, ty)),
+            (DataType::Array(ty), Dynamic::Array(items)) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(&Typed(item, ty))?;
+                }
+                seq.end()
+            }
+            (DataType::Tuple(types), Dynamic::Tuple(items)) => {
+                let mut tuple = serializer.serialize_tuple(items.len())?;
+                for (item, ty) in items.iter().zip(types) {
+                    tuple.serialize_element(&Typed(item, ty))?;
+                }
+                tuple.end()
+            }
+            (DataType::Map(key_ty, value_ty), Dynamic::Map(pairs)) => {
+                let mut seq = serializer.serialize_seq(Some(pairs.len()))?;
+                for (key, value) in pairs {
+                    seq.serialize_element(&(Typed(key, key_ty), Typed(value, value_ty)))?;
+                }
+                seq.end()
+            }
+            (DataType::Json(_), Dynamic::Json(json)) => json.serialize(serializer),
+            (ty, value) if *ty == value.data_type() => value.serialize_value(serializer),
+            (ty, _) => Err(S::Error::custom(format!(
+                "cannot serialize a Dynamic value as {ty}"
+            ))),
+        }
+    }
+}
+
+// === Deserialize ===
+
+impl<'de> Deserialize<'de> for Dynamic {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(PlainVisitor)
+        } else {
+            // The length is unknown in advance, but isn't used by RowBinary.
+            deserializer.deserialize_tuple(usize::MAX, TaggedVisitor)
+        }
+    }
+}
+
+struct TaggedVisitor;
+
+impl<'de> Visitor<'de> for TaggedVisitor {
+    type Value = Dynamic;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a Dynamic value")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Dynamic, A::Error> {
+        let ty = DataType::decode(&mut seq)?;
+        if ty == DataType::Nothing {
+            return Ok(Dynamic::Null);
+        }
+
+        seq.next_element_seed(ValueSeed(&ty))?
+            .ok_or_else(|| de::Error::custom("missing a Dynamic value"))
+    }
+}
+
+/// Deserializes a value of the specified data type.
+pub(super) struct ValueSeed<'a>(pub(super) &'a DataType);
+
+impl<'de> DeserializeSeed<'de> for ValueSeed<'_> {
+    type Value = Dynamic;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Dynamic, D::Error> {
+        use de::Error;
+
+        fn de<'de, T: Deserialize<'de>, D: Deserializer<'de>>(d: D) -> Result<T, D::Error> {
+            T::deserialize(d)
+        }
+
+        Ok(match self.0 {
+            DataType::Nothing => Dynamic::Null,
+            DataType::Bool => Dynamic::Bool(de(deserializer)?),
+            DataType::UInt8 => Dynamic::UInt8(de(deserializer)?),
+            DataType::UInt16 => Dynamic::UInt16(de(deserializer)?),
+            DataType::UInt32 => Dynamic::UInt32(de(deserializer)?),
+            DataType::UInt64 => Dynamic::UInt64(de(deserializer)?),
+            DataType::UInt128 => Dynamic::UInt128(de(deserializer)?),
+            DataType::Int8 => Dynamic::Int8(de(deserializer)?),
+            DataType::Int16 => Dynamic::Int16(de(deserializer)?),
+            DataType::Int32 => Dynamic::Int32(de(deserializer)?),
+            DataType::Int64 | DataType::Interval => Dynamic::Int64(de(deserializer)?),
+            DataType::Int128 => Dynamic::Int128(de(deserializer)?),
+            DataType::Float32 => Dynamic::Float32(de(deserializer)?),
+            DataType::Float64 => Dynamic::Float64(de(deserializer)?),
+            DataType::BFloat16 => {
+                let bits: u16 = de(deserializer)?;
+                Dynamic::Float32(f32::from_bits(u32::from(bits) << 16))
+            }
+            DataType::String => Dynamic::String(de(deserializer)?),
+            DataType::FixedString(size) => {
+                Dynamic::FixedString(deserializer.deserialize_tuple(*size, BytesVisitor(*size))?)
+            }
+            DataType::Date => Dynamic::Date(de(deserializer)?),
+            DataType::Date32 => Dynamic::Date32(de(deserializer)?),
+            DataType::DateTime(_) => Dynamic::DateTime(de(deserializer)?),
+            DataType::DateTime64(precision, _) => Dynamic::DateTime64 {
+                value: de(deserializer)?,
+                precision: *precision,
+            },
+            DataType::Decimal(precision, scale) => Dynamic::Decimal {
+                value: match DataType::decimal_size(*precision) {
+                    4 => de::<i32, _>(deserializer)?.into(),
+                    8 => de::<i64, _>(deserializer)?.into(),
+                    _ => de(deserializer)?,
+                },
+                precision: *precision,
+                scale: *scale,
+            },
+            DataType::Uuid => {
+                let (high, low): (u64, u64) = de(deserializer)?;
+                Dynamic::Uuid(((u128::from(high) << 64) | u128::from(low)).to_be_bytes())
+            }
+            DataType::IPv4 => Dynamic::IPv4(Ipv4Addr::from(de::<u32, _>(deserializer)?)),
+            DataType::IPv6 => Dynamic::IPv6(Ipv6Addr::from(de::<[u8; 16], _>(deserializer)?)),
+            DataType::Enum8(items) => {
+                let value: i8 = de(deserializer)?;
+                match items.iter().find(|(_, v)| *v == value) {
+                    Some((name, _)) => Dynamic::String(name.clone()),
+                    None => Dynamic::Int8(value),
+                }
+            }
+            DataType::Enum16(items) => {
+                let value: i16 = de(deserializer)?;
+                match items.iter().find(|(_, v)| *v == value) {
+                    Some((name, _)) => Dynamic::String(name.clone()),
+                    None => Dynamic::Int16(value),
+                }
+            }
+            DataType::Array(ty) => deserializer.deserialize_seq(ArrayVisitor(ty))?,
+            DataType::Tuple(types) => {
+                deserializer.deserialize_tuple(types.len(), TupleVisitor(types.iter()))?
+            }
+            DataType::NamedTuple(fields) => deserializer
+                .deserialize_tuple(fields.len(), TupleVisitor(fields.iter().map(|(_, ty)| ty)))?,
+            DataType::Nullable(ty) => deserializer.deserialize_option(NullableVisitor(ty))?,
+            DataType::LowCardinality(ty) => ValueSeed(ty).deserialize(deserializer)?,
+            DataType::Map(key, value) => deserializer.deserialize_seq(MapVisitor(key, value))?,
+            DataType::Variant(types) => deserializer.deserialize_tuple(2, VariantVisitor(types))?,
+            DataType::Dynamic => Dynamic::deserialize(deserializer)?,
+            DataType::Json(typed_paths) => {
+                Dynamic::Json(JsonSeed(typed_paths).deserialize(deserializer)?)
+            }
+            #[allow(unreachable_patterns)]
+            ty => return Err(D::Error::custom(format!("unsupported data type: {ty}"))),
+        })
+    }
+}
+
+struct BytesVisitor(usize);
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} bytes", self.0)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+        let mut bytes = Vec::with_capacity(self.0);
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+}
+
+struct ArrayVisitor<'a>(&'a DataType);
+
+impl<'de> Visitor<'de> for ArrayVisitor<'_> {
+    type Value = Dynamic;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Array({})", self.0)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Dynamic, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(1024));
+        while let Some(item) = seq.next_element_seed(ValueSeed(self.0))? {
+            items.push(item);
+        }
+        Ok(Dynamic::Array(items))
+    }
+}
+
+struct TupleVisitor<I>(I);
+
+impl<'de, 'a, I> Visitor<'de> for TupleVisitor<I>
+where
+    I: Iterator<Item = &'a DataType>,
+{
+    type Value = Dynamic;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Tuple")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Dynamic, A::Error> {
+        let mut items = Vec::new();
+        for ty in self.0 {
+            let item = seq
+                .next_element_seed(ValueSeed(ty))?
+                .ok_or_else(|| de::Error::custom("missing a Tuple element"))?;
+            items.push(item);
+        }
+        Ok(Dynamic::Tuple(items))
+    }
+}
+
+struct NullableVisitor<'a>(&'a DataType);
+
+impl<'de> Visitor<'de> for NullableVisitor<'_> {
+    type Value = Dynamic;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Nullable({})", self.0)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Dynamic, E> {
+        Ok(Dynamic::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Dynamic, D::Error> {
+        ValueSeed(self.0).deserialize(deserializer)
+    }
+}
+
+struct MapVisitor<'a>(&'a DataType, &'a DataType);
+
+impl<'de> Visitor<'de> for MapVisitor<'_> {
+    type Value = Dynamic;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Map({}, {})", self.0, self.1)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Dynamic, A::Error> {
+        let mut pairs = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(1024));
+        let types = [self.0.clone(), self.1.clone()];
+        while let Some(pair) = seq.next_element_seed(PairSeed(&types))? {
+            pairs.push(pair);
+        }
+        Ok(Dynamic::Map(pairs))
+    }
+}
+
+struct PairSeed<'a>(&'a [DataType; 2]);
+
+impl<'de> DeserializeSeed<'de> for PairSeed<'_> {
+    type Value = (Dynamic, Dynamic);
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        match deserializer.deserialize_tuple(2, TupleVisitor(self.0.iter()))? {
+            Dynamic::Tuple(pair) => {
+                let mut pair = pair.into_iter();
+                Ok((pair.next().unwrap(), pair.next().unwrap()))
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+struct VariantVisitor<'a>(&'a [DataType]);
+
+impl<'de> Visitor<'de> for VariantVisitor<'_> {
+    type Value = Dynamic;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Variant")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Dynamic, A::Error> {
+        const NULL_DISCRIMINATOR: u8 = 255;
+
+        let discriminator: u8 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::custom("missing a Variant discriminator"))?;
+
+        if discriminator == NULL_DISCRIMINATOR {
+            return Ok(Dynamic::Null);
+        }
+
+        let ty = self.0.get(usize::from(discriminator)).ok_or_else(|| {
+            de::Error::custom(format!("invalid Variant discriminator: {discriminator}"))
+        })?;
+
+        seq.next_element_seed(ValueSeed(ty))?
+            .ok_or_else(|| de::Error::custom("missing a Variant value"))
+    }
+}
+
+/// Builds values from self-describing formats, e.g. JSON.
+struct PlainVisitor;
+
+impl<'de> Visitor<'de> for PlainVisitor {
+    type Value = Dynamic;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Dynamic, E> {
+        Ok(Dynamic::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Dynamic, E> {
+        Ok(Dynamic::Int64(v))
+    }
+
+    fn visit_i128<E>(self, v: i128) -> Result<Dynamic, E> {
+        Ok(Dynamic::Int128(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Dynamic, E> {
+        Ok(i64::try_from(v).map_or(Dynamic::UInt64(v), Dynamic::Int64))
+    }
+
+    fn visit_u128<E>(self, v: u128) -> Result<Dynamic, E> {
+        Ok(Dynamic::UInt128(v))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Dynamic, E> {
+        Ok(Dynamic::Float64(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Dynamic, E> {
+        Ok(Dynamic::String(v.into()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Dynamic, E> {
+        Ok(Dynamic::String(v))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Dynamic, E> {
+        Ok(Dynamic::String(String::from_utf8_lossy(v).into_owned()))
+    }
+
+    fn visit_unit<E>(self) -> Result<Dynamic, E> {
+        Ok(Dynamic::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Dynamic, E> {
+        Ok(Dynamic::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Dynamic, D::Error> {
+        Dynamic::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Dynamic, A::Error> {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Dynamic::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Dynamic, A::Error> {
+        Json::from_map_access(map).map(Dynamic::Json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rowbinary;
+
+    fn roundtrip(value: &Dynamic) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        rowbinary::serialize_into(&mut buffer, value).unwrap();
+        let actual: Dynamic = rowbinary::deserialize_from(&mut &buffer[..]).unwrap();
+        assert_eq!(&actual, value);
+        buffer
+    }
+
+    #[test]
+    fn it_encodes_scalars() {
+        assert_eq!(roundtrip(&Dynamic::Null), [0x00]);
+        assert_eq!(roundtrip(&Dynamic::Bool(true)), [0x2d, 0x01]);
+        assert_eq!(
+            roundtrip(&Dynamic::Int64(-2)),
+            [0x0a, 0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+        );
+        assert_eq!(roundtrip(&Dynamic::UInt16(513)), [0x02, 0x01, 0x02]);
+        assert_eq!(roundtrip(&"ab".into()), [0x15, 0x02, b'a', b'b']);
+        assert_eq!(
+            roundtrip(&Dynamic::FixedString(b"xyz".to_vec())),
+            [0x16, 0x03, b'x', b'y', b'z']
+        );
+        assert_eq!(
+            roundtrip(&Dynamic::DateTime64 {
+                value: 1,
+                precision: 3
+            }),
+            [0x13, 0x03, 0x01, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            roundtrip(&Dynamic::Decimal {
+                value: -1234,
+                precision: 9,
+                scale: 2
+            }),
+            [0x19, 0x09, 0x02, 0x2e, 0xfb, 0xff, 0xff]
+        );
+        assert_eq!(
+            roundtrip(&Dynamic::IPv4(Ipv4Addr::new(127, 0, 0, 1))),
+            [0x28, 0x01, 0x00, 0x00, 0x7f]
+        );
+
+        let uuid = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+        assert_eq!(
+            roundtrip(&Dynamic::Uuid(uuid)),
+            [0x1d, 8, 7, 6, 5, 4, 3, 2, 1, 16, 15, 14, 13, 12, 11, 10, 9]
+        );
+    }
+
+    #[test]
+    fn it_encodes_containers() {
+        // Array(Nullable(Int64))
+        assert_eq!(
+            roundtrip(&Dynamic::from(vec![Some(1i64), None])),
+            [0x1e, 0x23, 0x0a, 0x02, 0x00, 1, 0, 0, 0, 0, 0, 0, 0, 0x01]
+        );
+
+        // Array(Dynamic)
+        assert_eq!(
+            roundtrip(&Dynamic::Array(vec![1u8.into(), "a".into()])),
+            [0x1e, 0x2b, 0x20, 0x02, 0x01, 0x01, 0x15, 0x01, b'a']
+        );
+
+        // Tuple(UInt8, String)
+        assert_eq!(
+            roundtrip(&Dynamic::Tuple(vec![1u8.into(), "a".into()])),
+            [0x1f, 0x02, 0x01, 0x15, 0x01, 0x01, b'a']
+        );
+
+        // Map(String, UInt8)
+        assert_eq!(
+            roundtrip(&Dynamic::Map(vec![("a".into(), 1u8.into())])),
+            [0x27, 0x15, 0x01, 0x01, 0x01, b'a', 0x01]
+        );
+
+        // Nested arrays.
+        roundtrip(&Dynamic::from(vec![vec![1u32, 2], vec![]]));
+    }
+
+    #[test]
+    fn it_decodes_unsupported_by_inference() {
+        fn decode(input: &[u8]) -> Dynamic {
+            rowbinary::deserialize_from(&mut &input[..]).unwrap()
+        }
+
+        // LowCardinality(String)
+        assert_eq!(decode(&[0x26, 0x15, 0x01, b'a']), "a".into());
+        // Enum8('a' = 1, 'b' = 2)
+        assert_eq!(
+            decode(&[0x17, 0x02, 0x01, b'a', 0x01, 0x01, b'b', 0x02, 0x02]),
+            "b".into()
+        );
+        // Variant(String, UInt8)
+        assert_eq!(decode(&[0x2a, 0x02, 0x15, 0x01, 0x01, 0x07]), 7u8.into());
+        assert_eq!(decode(&[0x2a, 0x02, 0x15, 0x01, 0xff]), Dynamic::Null);
+        // Point
+        assert_eq!(
+            decode(&[
+                0x2c, 0x05, b'P', b'o', b'i', b'n', b't', //
+                0, 0, 0, 0, 0, 0, 0xf0, 0x3f, //
+                0, 0, 0, 0, 0, 0, 0, 0x40,
+            ]),
+            Dynamic::Tuple(vec![1.0f64.into(), 2.0f64.into()])
+        );
+    }
+
+    #[test]
+    fn it_reports_not_enough_data() {
+        let mut buffer = Vec::new();
+        rowbinary::serialize_into(&mut buffer, &Dynamic::from(vec!["abc", "def"])).unwrap();
+
+        for len in 0..buffer.len() {
+            let res: Result<Dynamic, _> = rowbinary::deserialize_from(&mut &buffer[..len]);
+            assert!(
+                matches!(res, Err(crate::error::Error::NotEnoughData)),
+                "{len}: {res:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn it_formats_decimals() {
+        let decimal = |value, scale| Dynamic::Decimal {
+            value,
+            precision: 18,
+            scale,
+        };
+
+        assert_eq!(decimal(12345, 2).to_string(), "123.45");
+        assert_eq!(decimal(-12345, 2).to_string(), "-123.45");
+        assert_eq!(decimal(5, 3).to_string(), "0.005");
+        assert_eq!(decimal(-5, 0).to_string(), "-5");
+    }
+}
@@ -0,0 +1,269 @@
+//! Types for [geo data types].
+//!
+//! All of them map to the underlying RowBinary layout, e.g. `Polygon` is
+//! encoded as `Array(Array(Tuple(Float64, Float64)))`, so they can be used
+//! both in rows and in [`Query::bind`] to pass geometries to functions like
+//! `pointInPolygon`.
+//!
+//! With the `geo-types` feature, they can be converted from/to types of
+//! the [`geo-types`](https://docs.rs/geo-types) crate.
+//!
+//! [geo data types]: https://clickhouse.com/docs/en/sql-reference/data-types/geo
+//! [`Query::bind`]: crate::query::Query::bind
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// `Point`, encoded as `Tuple(Float64, Float64)`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+}
+
+impl From<(f64, f64)> for Point {
+    fn from((x, y): (f64, f64)) -> Self {
+        Self { x, y }
+    }
+}
+
+impl From<Point> for (f64, f64) {
+    fn from(point: Point) -> Self {
+        (point.x, point.y)
+    }
+}
+
+/// `Ring`, a closed shape without holes, encoded as `Array(Point)`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Ring(pub Vec<Point>);
+
+/// `LineString`, encoded as `Array(Point)`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LineString(pub Vec<Point>);
+
+/// `MultiLineString`, encoded as `Array(LineString)`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MultiLineString(pub Vec<LineString>);
+
+/// `Polygon`, encoded as `Array(Ring)`.
+///
+/// The first ring is the outer one, others are holes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Polygon(pub Vec<Ring>);
+
+/// `MultiPolygon`, encoded as `Array(Polygon)`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MultiPolygon(pub Vec<Polygon>);
+
+impl Serialize for Point {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (self.x, self.y).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Point {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <(f64, f64)>::deserialize(deserializer).map(Into::into)
+    }
+}
+
+macro_rules! impl_collection {
+    ($($ty:ident($item:ty)),*) => {
+        $(
+            impl Serialize for $ty {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    self.0.serialize(serializer)
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $ty {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    Vec::deserialize(deserializer).map(Self)
+                }
+            }
+
+            impl<T: Into<$item>> FromIterator<T> for $ty {
+                fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+                    Self(iter.into_iter().map(Into::into).collect())
+                }
+            }
+        )*
+    };
+}
+
+impl_collection!(
+    Ring(Point),
+    LineString(Point),
+    MultiLineString(LineString),
+    Polygon(Ring),
+    MultiPolygon(Polygon)
+);
+
+#[cfg(feature = "geo-types")]
+mod geo_types_impls {
+    use super::*;
+
+    impl From<geo_types::Coord<f64>> for Point {
+        fn from(coord: geo_types::Coord<f64>) -> Self {
+            Self::new(coord.x, coord.y)
+        }
+    }
+
+    impl From<Point> for geo_types::Coord<f64> {
+        fn from(point: Point) -> Self {
+            Self {
+                x: point.x,
+                y: point.y,
+            }
+        }
+    }
+
+    impl From<geo_types::Point<f64>> for Point {
+        fn from(point: geo_types::Point<f64>) -> Self {
+            point.0.into()
+        }
+    }
+
+    impl From<Point> for geo_types::Point<f64> {
+        fn from(point: Point) -> Self {
+            Self(point.into())
+        }
+    }
+
+    // `geo-types` has no separate type for rings, closed line strings are used instead.
+    impl From<geo_types::LineString<f64>> for Ring {
+        fn from(line: geo_types::LineString<f64>) -> Self {
+            line.0.into_iter().collect()
+        }
+    }
+
+    impl From<Ring> for geo_types::LineString<f64> {
+        fn from(ring: Ring) -> Self {
+            ring.0.into_iter().map(geo_types::Coord::from).collect()
+        }
+    }
+
+    impl From<geo_types::LineString<f64>> for LineString {
+        fn from(line: geo_types::LineString<f64>) -> Self {
+            line.0.into_iter().collect()
+        }
+    }
+
+    impl From<LineString> for geo_types::LineString<f64> {
+        fn from(line: LineString) -> Self {
+            line.0.into_iter().map(geo_types::Coord::from).collect()
+        }
+    }
+
+    impl From<geo_types::MultiLineString<f64>> for MultiLineString {
+        fn from(lines: geo_types::MultiLineString<f64>) -> Self {
+            lines.0.into_iter().collect()
+        }
+    }
+
+    impl From<MultiLineString> for geo_types::MultiLineString<f64> {
+        fn from(lines: MultiLineString) -> Self {
+            Self(lines.0.into_iter().map(Into::into).collect())
+        }
+    }
+
+    impl From<geo_types::Polygon<f64>> for Polygon {
+        fn from(polygon: geo_types::Polygon<f64>) -> Self {
+            let (exterior, interiors) = polygon.into_inner();
+            std::iter::once(exterior).chain(interiors).collect()
+        }
+    }
+
+    /// An empty polygon is converted to a polygon with an empty exterior.
+    impl From<Polygon> for geo_types::Polygon<f64> {
+        fn from(polygon: Polygon) -> Self {
+            let mut rings = polygon.0.into_iter().map(geo_types::LineString::from);
+            let exterior = rings
+                .next()
+                .unwrap_or_else(|| geo_types::LineString(vec![]));
+            Self::new(exterior, rings.collect())
+        }
+    }
+
+    impl From<geo_types::MultiPolygon<f64>> for MultiPolygon {
+        fn from(polygons: geo_types::MultiPolygon<f64>) -> Self {
+            polygons.0.into_iter().collect()
+        }
+    }
+
+    impl From<MultiPolygon> for geo_types::MultiPolygon<f64> {
+        fn from(polygons: MultiPolygon) -> Self {
+            Self(polygons.0.into_iter().map(Into::into).collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rowbinary, sql::Bind};
+
+    fn polygon() -> Polygon {
+        [vec![(0., 0.), (1., 0.), (1., 1.)], vec![(0.5, 0.5)]]
+            .into_iter()
+            .map(|ring| ring.into_iter().collect::<Ring>())
+            .collect()
+    }
+
+    #[test]
+    fn it_encodes_rowbinary() {
+        let mut buffer = Vec::new();
+        rowbinary::serialize_into(&mut buffer, &Point::new(1., 2.)).unwrap();
+        assert_eq!(buffer, [1f64.to_le_bytes(), 2f64.to_le_bytes()].concat());
+
+        // `Array(Array(Tuple(Float64, Float64)))`
+        let mut buffer = Vec::new();
+        rowbinary::serialize_into(&mut buffer, &polygon()).unwrap();
+        assert_eq!(buffer.len(), 1 + 1 + 3 * 16 + 1 + 16);
+        assert_eq!(&buffer[..2], [2, 3]);
+
+        let actual: Polygon = rowbinary::deserialize_from(&mut &buffer[..]).unwrap();
+        assert_eq!(actual, polygon());
+    }
+
+    #[test]
+    fn it_binds_literals() {
+        let mut out = String::new();
+        Point::new(1.5, -2.).write(&mut out).unwrap();
+        assert_eq!(out, "(1.5,-2)");
+
+        let mut out = String::new();
+        polygon().write(&mut out).unwrap();
+        assert_eq!(out, "[[(0,0),(1,0),(1,1)],[(0.5,0.5)]]");
+    }
+
+    #[cfg(feature = "geo-types")]
+    #[test]
+    fn it_converts_polygons() {
+        use geo_types::{coord, LineString as GeoLineString, Polygon as GeoPolygon};
+
+        let exterior = GeoLineString(vec![
+            coord! { x: 0., y: 0. },
+            coord! { x: 10., y: 0. },
+            coord! { x: 10., y: 10. },
+            coord! { x: 0., y: 0. },
+        ]);
+        let interior = GeoLineString(vec![
+            coord! { x: 1., y: 1. },
+            coord! { x: 2., y: 1. },
+            coord! { x: 2., y: 2. },
+            coord! { x: 1., y: 1. },
+        ]);
+        let geo = GeoPolygon::new(exterior, vec![interior]);
+
+        let polygon = Polygon::from(geo.clone());
+        assert_eq!(polygon.0.len(), 2);
+        assert_eq!(polygon.0[1].0[1], Point::new(2., 1.));
+        assert_eq!(GeoPolygon::from(polygon), geo);
+    }
+}
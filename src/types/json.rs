@@ -0,0 +1,362 @@
+use std::{collections::BTreeMap, fmt};
+
+use serde::{
+    de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor},
+    ser::{SerializeMap, SerializeSeq, Serializer},
+    Deserialize, Serialize,
+};
+
+use super::{data_type::DataType, dynamic::ValueSeed, Dynamic};
+
+/// A value of the [`JSON`] data type.
+///
+/// ClickHouse stores JSON objects as a set of paths (e.g. `a.b.c`) with
+/// values of the `Dynamic` type, so `Json` is a flat map from dotted paths
+/// to [`Dynamic`] values. Nested objects are flattened when converting
+/// from `serde_json` or deserializing from human-readable formats, and
+/// restored when serializing to them.
+///
+/// Paths with `Null` values are skipped when inserting, as ClickHouse does.
+/// Typed paths (e.g. `JSON(a.b UInt32)`) are supported when the type of the
+/// column is provided by the server, i.e. inside `Dynamic` values; columns
+/// of the `JSON` type with typed paths cannot be (de)serialized directly.
+///
+/// [`JSON`]: https://clickhouse.com/docs/en/sql-reference/data-types/newjson
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Json(BTreeMap<String, Dynamic>);
+
+impl Json {
+    /// Creates an empty object.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a value by its dotted path, e.g. `a.b.c`.
+    pub fn get(&self, path: &str) -> Option<&Dynamic> {
+        self.0.get(path)
+    }
+
+    /// Inserts a value by its dotted path, returning the previous value.
+    pub fn insert(
+        &mut self,
+        path: impl Into<String>,
+        value: impl Into<Dynamic>,
+    ) -> Option<Dynamic> {
+        self.0.insert(path.into(), value.into())
+    }
+
+    /// Removes a value by its dotted path.
+    pub fn remove(&mut self, path: &str) -> Option<Dynamic> {
+        self.0.remove(path)
+    }
+
+    /// Returns the number of paths.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if there are no paths.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterates over paths and their values, ordered by paths.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Dynamic)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    pub(super) fn from_map_access<'de, A: MapAccess<'de>>(mut map: A) -> Result<Self, A::Error> {
+        let mut json = Self::new();
+        while let Some((key, value)) = map.next_entry::<String, Dynamic>()? {
+            json.insert_flattened(key, value);
+        }
+        Ok(json)
+    }
+
+    fn insert_flattened(&mut self, path: String, value: Dynamic) {
+        match value {
+            Dynamic::Json(nested) => {
+                for (subpath, value) in nested.0 {
+                    self.0.insert(format!("{path}.{subpath}"), value);
+                }
+            }
+            value => {
+                self.0.insert(path, value);
+            }
+        }
+    }
+
+    /// Restores nested objects from dotted paths.
+    fn unflatten(&self) -> Node<'_> {
+        let mut root = BTreeMap::new();
+
+        'paths: for (path, value) in &self.0 {
+            let mut object = &mut root;
+            let mut rest = path.as_str();
+
+            while let Some((head, tail)) = rest.split_once('.') {
+                // Both `a` and `a.b` are present, keep the path as is.
+                if let Some(Node::Leaf(_)) = object.get(head) {
+                    object.insert(rest, Node::Leaf(value));
+                    continue 'paths;
+                }
+
+                let node = object
+                    .entry(head)
+                    .or_insert_with(|| Node::Object(BTreeMap::new()));
+
+                match node {
+                    Node::Object(nested) => object = nested,
+                    Node::Leaf(_) => unreachable!(),
+                }
+
+                rest = tail;
+            }
+
+            object.insert(rest, Node::Leaf(value));
+        }
+
+        Node::Object(root)
+    }
+}
+
+enum Node<'a> {
+    Leaf(&'a Dynamic),
+    Object(BTreeMap<&'a str, Node<'a>>),
+}
+
+impl Serialize for Node<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Leaf(value) => value.serialize(serializer),
+            Self::Object(object) => {
+                let mut map = serializer.serialize_map(Some(object.len()))?;
+                for (key, node) in object {
+                    map.serialize_entry(key, node)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+impl<K: Into<String>, V: Into<Dynamic>> FromIterator<(K, V)> for Json {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self(
+            iter.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+}
+
+impl IntoIterator for Json {
+    type Item = (String, Dynamic);
+    type IntoIter = std::collections::btree_map::IntoIter<String, Dynamic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<serde_json::Map<String, serde_json::Value>> for Json {
+    fn from(map: serde_json::Map<String, serde_json::Value>) -> Self {
+        let mut json = Self::new();
+        for (key, value) in map {
+            json.insert_flattened(key, value.into());
+        }
+        json
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<Json> for serde_json::Value {
+    fn from(json: Json) -> Self {
+        fn convert(node: Node<'_>) -> serde_json::Value {
+            match node {
+                Node::Leaf(value) => value.clone().into(),
+                Node::Object(object) => serde_json::Value::Object(
+                    object
+                        .into_iter()
+                        .map(|(k, v)| (k.to_owned(), convert(v)))
+                        .collect(),
+                ),
+            }
+        }
+
+        convert(json.unflatten())
+    }
+}
+
+// === Serialize ===
+
+impl Serialize for Json {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return self.unflatten().serialize(serializer);
+        }
+
+        // RowBinary: the number of paths, then pairs of a path and a value.
+        let paths = self.0.iter().filter(|(_, v)| !v.is_null());
+        let mut seq = serializer.serialize_seq(Some(paths.clone().count()))?;
+        for entry in paths {
+            seq.serialize_element(&entry)?;
+        }
+        seq.end()
+    }
+}
+
+// === Deserialize ===
+
+impl<'de> Deserialize<'de> for Json {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_map(ObjectVisitor)
+        } else {
+            JsonSeed(&[]).deserialize(deserializer)
+        }
+    }
+}
+
+struct ObjectVisitor;
+
+impl<'de> Visitor<'de> for ObjectVisitor {
+    type Value = Json;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a JSON object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Json, A::Error> {
+        Json::from_map_access(map)
+    }
+}
+
+/// Deserializes `Json` in RowBinary, using types of typed paths if known.
+pub(super) struct JsonSeed<'a>(pub(super) &'a [(String, DataType)]);
+
+impl<'de> DeserializeSeed<'de> for JsonSeed<'_> {
+    type Value = Json;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Json, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for JsonSeed<'_> {
+    type Value = Json;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a JSON object")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Json, A::Error> {
+        let mut json = Json::new();
+        while let Some((path, value)) = seq.next_element_seed(PathSeed(self.0))? {
+            json.0.insert(path, value);
+        }
+        Ok(json)
+    }
+}
+
+struct PathSeed<'a>(&'a [(String, DataType)]);
+
+impl<'de> DeserializeSeed<'de> for PathSeed<'_> {
+    type Value = (String, Dynamic);
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_tuple(2, self)
+    }
+}
+
+impl<'de> Visitor<'de> for PathSeed<'_> {
+    type Value = (String, Dynamic);
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a JSON path and its value")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let path: String = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::custom("missing a JSON path"))?;
+
+        let value = match self.0.iter().find(|(p, _)| *p == path) {
+            Some((_, ty)) => seq.next_element_seed(ValueSeed(ty))?,
+            None => seq.next_element()?,
+        };
+
+        let value = value.ok_or_else(|| de::Error::custom("missing a JSON value"))?;
+        Ok((path, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rowbinary;
+
+    fn sample() -> Json {
+        [
+            ("a.b", Dynamic::from(1u8)),
+            ("a.c", Dynamic::from("x")),
+            ("d", Dynamic::Null),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn it_encodes_rowbinary() {
+        let mut buffer = Vec::new();
+        rowbinary::serialize_into(&mut buffer, &sample()).unwrap();
+        assert_eq!(
+            buffer,
+            [
+                0x02, // number of paths
+                0x03, b'a', b'.', b'b', 0x01, 0x01, // a.b: UInt8
+                0x03, b'a', b'.', b'c', 0x15, 0x01, b'x', // a.c: String
+            ]
+        );
+
+        let actual: Json = rowbinary::deserialize_from(&mut &buffer[..]).unwrap();
+        let mut expected = sample();
+        expected.remove("d");
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn it_decodes_typed_paths() {
+        // JSON(a UInt8) inside Dynamic.
+        let ty = DataType::Json(vec![("a".into(), DataType::UInt8)]);
+        let mut buffer = Vec::new();
+        rowbinary::serialize_into(&mut buffer, &ty.encoded()).unwrap();
+        buffer.extend([0x02, 0x01, b'a', 0x07, 0x01, b'b', 0x15, 0x01, b'x']);
+
+        let actual: Dynamic = rowbinary::deserialize_from(&mut &buffer[..]).unwrap();
+        let expected: Json = [("a", Dynamic::UInt8(7)), ("b", "x".into())]
+            .into_iter()
+            .collect();
+        assert_eq!(actual, Dynamic::Json(expected));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn it_converts_serde_json() {
+        let value = serde_json::json!({"a": {"b": 1, "c": "x"}, "d": [1.5, null]});
+        let json = match Dynamic::from(value.clone()) {
+            Dynamic::Json(json) => json,
+            other => panic!("unexpected {other:?}"),
+        };
+
+        assert_eq!(json.get("a.b"), Some(&Dynamic::Int64(1)));
+        assert_eq!(
+            json.get("d"),
+            Some(&Dynamic::Array(vec![1.5f64.into(), Dynamic::Null]))
+        );
+        assert_eq!(serde_json::to_value(&json).unwrap(), value);
+        assert_eq!(serde_json::Value::from(json.clone()), value);
+        assert_eq!(serde_json::from_value::<Json>(value).unwrap(), json);
+    }
+}
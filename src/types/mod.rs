@@ -0,0 +1,9 @@
+//! Types for ClickHouse data types that have no natural Rust counterpart.
+
+pub use self::{dynamic::Dynamic, json::Json};
+
+pub mod geo;
+
+mod data_type;
+mod dynamic;
+mod json;
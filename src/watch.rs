@@ -202,7 +202,16 @@ impl<T> RowCursor<T> {
 #[allow(clippy::large_enum_variant)]
 enum CursorWithInit<T> {
     Preparing(Client, WatchParams),
-    Fetching(JsonCursor<T>),
+    Fetching(JsonCursor<JsonRow<T>>),
+}
+
+// We use `JSONEachRowWithProgress` to avoid infinite HTTP connections.
+// See https://github.com/ClickHouse/ClickHouse/issues/22996 for details.
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum JsonRow<T> {
+    Row(T),
+    Progress {},
 }
 
 struct WatchParams {
@@ -223,15 +232,22 @@ impl<T> CursorWithInit<T> {
             *self = Self::Fetching(cursor);
         }
 
-        match self {
-            Self::Fetching(cursor) => cursor.next().await,
-            Self::Preparing(..) => unreachable!(),
+        let Self::Fetching(cursor) = self else {
+            unreachable!()
+        };
+
+        loop {
+            match cursor.next().await? {
+                Some(JsonRow::Row(value)) => return Ok(Some(value)),
+                Some(JsonRow::Progress { .. }) => continue,
+                None => return Ok(None),
+            }
         }
     }
 }
 
 #[cold]
-async fn init_cursor<T>(client: &Client, params: &WatchParams) -> Result<JsonCursor<T>> {
+async fn init_cursor<T>(client: &Client, params: &WatchParams) -> Result<JsonCursor<JsonRow<T>>> {
     if let Some(sql) = &params.sql {
         let refresh_sql = params
             .refresh
@@ -255,7 +271,7 @@ async fn init_cursor<T>(client: &Client, params: &WatchParams) -> Result<JsonCur
     watch_sql.push_str(" FORMAT JSONEachRowWithProgress");
 
     let response = client.query(&watch_sql).do_execute(true)?;
-    Ok(JsonCursor::new(response))
+    Ok(JsonCursor::new(response, client.limits.max_row_size))
 }
 
 fn is_table_name(sql: &str) -> bool {
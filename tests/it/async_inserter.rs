@@ -0,0 +1,74 @@
+#![cfg(feature = "inserter")]
+
+use crate::{create_simple_table, fetch_rows, SimpleRow};
+
+#[tokio::test]
+async fn write_all() {
+    let client = prepare_database!();
+    create_simple_table(&client, "test").await;
+
+    let inserter = client.async_inserter::<SimpleRow>("test");
+    let rows = [SimpleRow::new(1, "one"), SimpleRow::new(2, "two")];
+
+    let ack = inserter.write_all(&rows).await.unwrap().unwrap();
+    assert_eq!(ack.rows, 2);
+    assert_ne!(ack.bytes, 0);
+
+    let ack2 = inserter.write(&SimpleRow::new(3, "three")).await.unwrap();
+    assert_ne!(ack2.query_id, ack.query_id);
+
+    // Data is flushed before responding, `wait_for_async_insert=1` by default.
+    let mut actual = fetch_rows::<SimpleRow>(&client, "test").await;
+    actual.sort_by_key(|row| row.id);
+    assert_eq!(
+        actual,
+        [
+            SimpleRow::new(1, "one"),
+            SimpleRow::new(2, "two"),
+            SimpleRow::new(3, "three"),
+        ]
+    );
+}
+
+#[cfg(feature = "test-util")]
+mod mock {
+    use std::time::Duration;
+
+    use clickhouse::{test, Client};
+
+    use crate::SimpleRow;
+
+    #[tokio::test]
+    async fn smoke() {
+        let mock = test::Mock::new();
+        let client = Client::default().with_url(mock.url());
+        let inserter = client
+            .async_inserter::<SimpleRow>("some")
+            .with_wait_for_async_insert(false)
+            .with_busy_timeout(Duration::from_millis(500))
+            .with_max_data_size(1_000_000);
+
+        let recording = mock.add(test::handlers::record());
+        let rows = [SimpleRow::new(1, "one"), SimpleRow::new(2, "two")];
+        let ack = inserter.write_all(&rows).await.unwrap().unwrap();
+        assert_eq!(ack.rows, 2);
+
+        let (actual, params): (Vec<SimpleRow>, _) = recording.collect_with_params().await;
+        assert_eq!(actual, rows);
+        assert_eq!(params["async_insert"], "1");
+        assert_eq!(params["wait_for_async_insert"], "0");
+        assert_eq!(params["async_insert_busy_timeout_ms"], "500");
+        assert_eq!(params["async_insert_max_data_size"], "1000000");
+        assert_eq!(params["query_id"], ack.query_id);
+
+        // Every write is a separate `INSERT`.
+        let recording = mock.add(test::handlers::record());
+        let ack2 = inserter.write(&SimpleRow::new(3, "three")).await.unwrap();
+        assert_ne!(ack2.query_id, ack.query_id);
+        let actual: Vec<SimpleRow> = recording.collect().await;
+        assert_eq!(actual, [SimpleRow::new(3, "three")]);
+
+        // No `INSERT` is sent for empty input, the mock has no handlers.
+        assert!(inserter.write_all(&[]).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,117 @@
+#![cfg(feature = "inserter")]
+
+#[cfg(feature = "test-util")]
+mod mock {
+    use clickhouse::{test, Client};
+
+    use crate::SimpleRow;
+
+    #[tokio::test]
+    async fn smoke() {
+        use clickhouse::error::Error;
+
+        let mock = test::Mock::new();
+        let client = Client::default().with_url(mock.url());
+        let handle = client
+            .batcher::<SimpleRow>("some")
+            .unwrap()
+            .with_max_rows(4)
+            .with_capacity(1)
+            .spawn();
+
+        // Rows from multiple producers are inserted together.
+        let recording = mock.add(test::handlers::record());
+        let producers = (0..2).map(|i| {
+            let handle = handle.clone();
+            tokio::spawn(async move {
+                for j in 0..2 {
+                    handle.send(&SimpleRow::new(i * 2 + j, "x")).await.unwrap();
+                }
+            })
+        });
+        for producer in producers.collect::<Vec<_>>() {
+            producer.await.unwrap();
+        }
+
+        let mut rows: Vec<SimpleRow> = recording.collect().await;
+        rows.sort_by_key(|row| row.id);
+        assert_eq!(
+            rows,
+            (0..4).map(|id| SimpleRow::new(id, "x")).collect::<Vec<_>>()
+        );
+
+        // Nothing to flush, but waits for the previous `INSERT`.
+        assert_eq!(handle.flush().await.unwrap().rows, 0);
+        let stats = handle.stats();
+        assert_eq!(stats.inserted.rows, 4);
+        assert_eq!(stats.inserted.transactions, 1);
+        assert_eq!(stats.pending.rows, 0);
+
+        // The rest is flushed on shutdown.
+        let recording = mock.add(test::handlers::record());
+        let other = handle.clone();
+        handle.send(&SimpleRow::new(4, "y")).await.unwrap();
+        assert_eq!(handle.shutdown().await.unwrap().rows, 1);
+
+        let rows: Vec<SimpleRow> = recording.collect().await;
+        assert_eq!(rows, [SimpleRow::new(4, "y")]);
+
+        let err = other.send(&SimpleRow::new(5, "z")).await.unwrap_err();
+        assert!(matches!(err, Error::BatcherClosed));
+    }
+
+    #[tokio::test]
+    async fn background_error() {
+        let mock = test::Mock::new();
+        let client = Client::default().with_url(mock.url());
+        let handle = client
+            .batcher::<SimpleRow>("some")
+            .unwrap()
+            .with_max_rows(1)
+            .spawn();
+
+        // The `INSERT` is ended in the background, the error is kept.
+        mock.add(test::handlers::failure(test::status::SERVICE_UNAVAILABLE));
+        handle.send(&SimpleRow::new(1, "one")).await.unwrap();
+        assert!(handle.flush().await.is_err());
+
+        let stats = handle.stats();
+        assert_eq!(stats.failed.rows, 1);
+        assert!(stats.last_error.is_some());
+
+        // Returned only once.
+        let recording = mock.add(test::handlers::record());
+        handle.send(&SimpleRow::new(2, "two")).await.unwrap();
+        assert_eq!(handle.flush().await.unwrap().rows, 0);
+        assert_eq!(handle.stats().inserted.rows, 1);
+
+        let rows: Vec<SimpleRow> = recording.collect().await;
+        assert_eq!(rows, [SimpleRow::new(2, "two")]);
+    }
+
+    #[tokio::test]
+    async fn parallelism_errors() {
+        let mock = test::Mock::new();
+        let client = Client::default().with_url(mock.url());
+        let handle = client
+            .batcher::<SimpleRow>("some")
+            .unwrap()
+            .with_parallelism(2)
+            .spawn();
+
+        // Only data of the failed shard is counted as failed.
+        let recording = mock.add(test::handlers::record());
+        mock.add(test::handlers::failure(test::status::SERVICE_UNAVAILABLE));
+        handle.send(&SimpleRow::new(1, "one")).await.unwrap();
+        handle.send(&SimpleRow::new(2, "two")).await.unwrap();
+        assert!(handle.flush().await.is_err());
+
+        let stats = handle.stats();
+        assert_eq!(stats.inserted.rows, 1);
+        assert_eq!(stats.failed.rows, 1);
+        assert_eq!(stats.pending.rows, 0);
+
+        let rows: Vec<SimpleRow> = recording.collect().await;
+        assert_eq!(rows.len(), 1);
+    }
+}
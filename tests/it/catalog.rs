@@ -0,0 +1,127 @@
+#![cfg(feature = "catalog")]
+
+#[tokio::test]
+async fn smoke() {
+    let client = prepare_database!();
+    let database = client
+        .query("SELECT currentDatabase()")
+        .fetch_one::<String>()
+        .await
+        .unwrap();
+
+    client
+        .query(
+            "CREATE TABLE test(no UInt32, name String COMMENT 'a name')
+             ENGINE = MergeTree PARTITION BY no % 2 ORDER BY no",
+        )
+        .execute()
+        .await
+        .unwrap();
+
+    client
+        .query("INSERT INTO test SELECT number, toString(number) FROM system.numbers LIMIT 10")
+        .execute()
+        .await
+        .unwrap();
+
+    let catalog = client.catalog();
+
+    let databases = catalog.databases().await.unwrap();
+    assert!(databases.iter().any(|db| db.name == database));
+
+    let tables = catalog.tables(&database).await.unwrap();
+    assert_eq!(tables.len(), 1);
+    assert_eq!(tables[0].name, "test");
+    assert_eq!(tables[0].engine, "MergeTree");
+    assert_eq!(tables[0].partition_key, "no % 2");
+    assert_eq!(tables[0].total_rows, Some(10));
+
+    let columns = catalog.columns(&database, "test").await.unwrap();
+    let names = columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["no", "name"]);
+    assert_eq!(columns[0].data_type, "UInt32");
+    assert!(columns[0].is_in_sorting_key);
+    assert_eq!(columns[1].comment, "a name");
+
+    let partitions = catalog.partitions("test").await.unwrap();
+    assert_eq!(partitions.len(), 2);
+    assert_eq!(partitions[0].partition, "0");
+    assert_eq!(partitions[0].rows, 5);
+    assert_eq!(partitions[1].rows, 5);
+
+    let qualified = catalog
+        .partitions(&format!("{database}.test"))
+        .await
+        .unwrap();
+    assert_eq!(qualified, partitions);
+
+    let parts = catalog.parts("test").await.unwrap();
+    assert_eq!(parts.iter().map(|p| p.rows).sum::<u64>(), 10);
+    assert!(parts.iter().all(|p| p.active));
+
+    client
+        .query("ALTER TABLE test DELETE WHERE no = 1")
+        .with_option("mutations_sync", "1")
+        .execute()
+        .await
+        .unwrap();
+
+    let mutations = catalog.mutations("test").await.unwrap();
+    assert_eq!(mutations.len(), 1);
+    assert!(mutations[0].is_done);
+    assert_eq!(mutations[0].parts_to_do, 0);
+
+    catalog.replicas().await.unwrap();
+
+    let processes = catalog.processes().await.unwrap();
+    assert!(processes
+        .iter()
+        .any(|p| p.query.contains("system.processes")));
+}
+
+#[cfg(feature = "test-util")]
+mod mock {
+    use clickhouse::{test, Client};
+
+    #[tokio::test]
+    async fn smoke() {
+        let mock = test::Mock::new();
+        let client = Client::default().with_url(mock.url());
+        let catalog = client.catalog();
+
+        let recording = mock.add(test::handlers::record::<u8>());
+        assert!(catalog.parts("db.events").await.unwrap().is_empty());
+        let query = recorded_query(recording).await;
+        assert!(
+            query.starts_with("SELECT `database`,`table`,`partition`,"),
+            "{query}"
+        );
+        assert!(
+            query.contains(
+                "WHERE active AND database = coalesce('db', currentDatabase()) AND table = 'events'"
+            ),
+            "{query}"
+        );
+
+        let recording = mock.add(test::handlers::record::<u8>());
+        catalog.mutations("events").await.unwrap();
+        let query = recorded_query(recording).await;
+        assert!(
+            query.contains(
+                "WHERE database = coalesce(NULL, currentDatabase()) AND table = 'events'"
+            ),
+            "{query}"
+        );
+
+        let recording = mock.add(test::handlers::record::<u8>());
+        catalog.columns("db", "events").await.unwrap();
+        let query = recorded_query(recording).await;
+        assert!(query.contains("`name`,`type`,`position`"), "{query}");
+    }
+
+    // Short SELECTs are sent using GET, so the query is in the URL.
+    async fn recorded_query(recording: test::handlers::RecordControl<u8>) -> String {
+        let (_, params) = recording.collect_with_params::<Vec<u8>>().await;
+        params["query"].clone()
+    }
+}
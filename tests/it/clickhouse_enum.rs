@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+use clickhouse::{error::Error, ClickHouseEnum, Row};
+
+#[derive(Debug, Clone, Copy, PartialEq, ClickHouseEnum)]
+enum Color {
+    #[ch(name = "red")]
+    Red = -1,
+    #[ch(name = "green")]
+    Green,
+    #[ch(value = 1000, name = "blue's")]
+    Blue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ClickHouseEnum)]
+enum Shape {
+    Circle,
+    Square,
+    Unknown(i16),
+}
+
+#[derive(Debug, PartialEq, Row, Serialize, Deserialize)]
+struct MyRow {
+    color: Color,
+    shape: Shape,
+}
+
+#[test]
+fn data_types() {
+    assert_eq!(
+        Color::DATA_TYPE,
+        "Enum16('red' = -1, 'green' = 0, 'blue\\'s' = 1000)"
+    );
+    assert_eq!(Shape::DATA_TYPE, "Enum8('Circle' = 1, 'Square' = 2)");
+    assert_eq!(Color::Blue.name(), Some("blue's"));
+    assert_eq!(Shape::from_name("Square"), Some(Shape::Square));
+    assert_eq!(Shape::Unknown(5).name(), None);
+}
+
+#[tokio::test]
+async fn enums() {
+    let client = prepare_database!();
+
+    client
+        .query(&format!(
+            "CREATE TABLE test(color {}, shape Enum8('Circle' = 1, 'Square' = 2, 'Triangle' = 3))
+             ENGINE = MergeTree ORDER BY color",
+            Color::DATA_TYPE
+        ))
+        .execute()
+        .await
+        .unwrap();
+
+    let rows = [
+        MyRow {
+            color: Color::Red,
+            shape: Shape::Circle,
+        },
+        MyRow {
+            color: Color::Blue,
+            shape: Shape::Unknown(3),
+        },
+    ];
+
+    let mut insert = client.insert("test").unwrap();
+    for row in &rows {
+        insert.write(row).await.unwrap();
+    }
+    insert.end().await.unwrap();
+
+    let actual = client
+        .query("SELECT ?fields FROM test ORDER BY color")
+        .fetch_all::<MyRow>()
+        .await
+        .unwrap();
+
+    assert_eq!(actual, rows);
+
+    // Names are used in `bind()`.
+    let shape = client
+        .query("SELECT shape FROM test WHERE color = ?")
+        .bind(Color::Blue)
+        .fetch_one::<Shape>()
+        .await
+        .unwrap();
+
+    assert_eq!(shape, Shape::Unknown(3));
+
+    // Unknown values without a catch-all variant are rejected.
+    let err = client
+        .query("SELECT CAST('yellow', 'Enum16(\\'red\\' = -1, \\'yellow\\' = 1)')")
+        .fetch_one::<Color>()
+        .await
+        .unwrap_err();
+
+    assert!(
+        matches!(&err, Error::Custom(msg) if msg.contains("integer `1`")),
+        "{err:?}"
+    );
+}
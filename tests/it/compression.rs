@@ -1,3 +1,5 @@
+#[allow(unused_imports)] // unused without HTTP compression features
+use clickhouse::HttpCompression;
 use clickhouse::{Client, Compression};
 
 use crate::{create_simple_table, SimpleRow};
@@ -35,3 +37,31 @@ async fn lz4() {
     let client = prepare_database!().with_compression(Compression::Lz4);
     check(client).await;
 }
+
+#[cfg(feature = "zstd")]
+#[tokio::test]
+async fn zstd() {
+    let client = prepare_database!().with_compression(Compression::Zstd(3));
+    check(client).await;
+}
+
+#[cfg(feature = "gzip")]
+#[tokio::test]
+async fn http_gzip() {
+    let client = prepare_database!().with_http_compression(HttpCompression::Gzip(6));
+    check(client).await;
+}
+
+#[cfg(feature = "brotli")]
+#[tokio::test]
+async fn http_brotli() {
+    let client = prepare_database!().with_http_compression(HttpCompression::Brotli(6));
+    check(client).await;
+}
+
+#[cfg(feature = "zstd")]
+#[tokio::test]
+async fn http_zstd() {
+    let client = prepare_database!().with_http_compression(HttpCompression::Zstd(3));
+    check(client).await;
+}
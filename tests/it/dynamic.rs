@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+use clickhouse::{
+    types::{Dynamic, Json},
+    Row,
+};
+
+// See also: https://clickhouse.com/docs/en/sql-reference/data-types/dynamic
+
+#[tokio::test]
+async fn dynamic_data_type() {
+    let client = prepare_database!();
+
+    #[derive(Debug, PartialEq, Row, Serialize, Deserialize)]
+    struct MyRow {
+        id: u32,
+        value: Dynamic,
+    }
+
+    client
+        .query(
+            "
+            CREATE TABLE test_dynamic(id UInt32, value Dynamic)
+            ENGINE = MergeTree ORDER BY id",
+        )
+        .with_option("allow_experimental_dynamic_type", "1")
+        .execute()
+        .await
+        .unwrap();
+
+    let values = [
+        Dynamic::Null,
+        Dynamic::Bool(true),
+        Dynamic::Int64(-42),
+        Dynamic::UInt128(u128::MAX),
+        Dynamic::Float64(1.5),
+        Dynamic::from("foo"),
+        Dynamic::Decimal {
+            value: 12345,
+            precision: 10,
+            scale: 2,
+        },
+        Dynamic::from(vec![Some(1u8), None]),
+        Dynamic::Array(vec![1u8.into(), "bar".into()]),
+        Dynamic::Tuple(vec![1u16.into(), "baz".into()]),
+        Dynamic::Map(vec![("a".into(), 1i32.into()), ("b".into(), 2i32.into())]),
+    ];
+
+    let mut insert = client.insert("test_dynamic").unwrap();
+    for (id, value) in values.iter().enumerate() {
+        let row = MyRow {
+            id: id as u32,
+            value: value.clone(),
+        };
+        insert.write(&row).await.unwrap();
+    }
+    insert.end().await.unwrap();
+
+    let rows = client
+        .query("SELECT ?fields FROM test_dynamic ORDER BY id")
+        .fetch_all::<MyRow>()
+        .await
+        .unwrap();
+
+    let actual = rows.into_iter().map(|row| row.value).collect::<Vec<_>>();
+    assert_eq!(actual, values);
+
+    // Types that are returned in a different form.
+    let (enum_value, low_cardinality) = client
+        .query(
+            "SELECT
+                 CAST('b', 'Enum8(\\'a\\' = 1, \\'b\\' = 2)')::Dynamic,
+                 toLowCardinality('c')::Dynamic",
+        )
+        .fetch_one::<(Dynamic, Dynamic)>()
+        .await
+        .unwrap();
+
+    assert_eq!(enum_value, Dynamic::from("b"));
+    assert_eq!(low_cardinality, Dynamic::from("c"));
+}
+
+// See also: https://clickhouse.com/docs/en/sql-reference/data-types/newjson
+
+#[tokio::test]
+async fn json_data_type() {
+    let client = prepare_database!();
+
+    #[derive(Debug, PartialEq, Row, Serialize, Deserialize)]
+    struct MyRow {
+        id: u32,
+        data: Json,
+    }
+
+    client
+        .query(
+            "
+            CREATE TABLE test_json(id UInt32, data JSON)
+            ENGINE = MergeTree ORDER BY id",
+        )
+        .with_option("allow_experimental_json_type", "1")
+        .execute()
+        .await
+        .unwrap();
+
+    let rows = [
+        MyRow {
+            id: 1,
+            data: [
+                ("a.b", Dynamic::Int64(1)),
+                ("a.c", "foo".into()),
+                ("d", Dynamic::from(vec![1.5f64, 2.5])),
+            ]
+            .into_iter()
+            .collect(),
+        },
+        MyRow {
+            id: 2,
+            data: Json::new(),
+        },
+    ];
+
+    let mut insert = client.insert("test_json").unwrap();
+    for row in &rows {
+        insert.write(row).await.unwrap();
+    }
+    insert.end().await.unwrap();
+
+    let actual = client
+        .query("SELECT ?fields FROM test_json ORDER BY id")
+        .fetch_all::<MyRow>()
+        .await
+        .unwrap();
+
+    assert_eq!(actual, rows);
+
+    // JSON inside Dynamic, with typed paths.
+    let value = client
+        .query("SELECT '{\"a\": 1, \"b\": \"x\"}'::JSON(a UInt8)::Dynamic")
+        .with_option("allow_experimental_json_type", "1")
+        .fetch_one::<Dynamic>()
+        .await
+        .unwrap();
+
+    let expected: Json = [("a", Dynamic::UInt8(1)), ("b", "x".into())]
+        .into_iter()
+        .collect();
+    assert_eq!(value, Dynamic::Json(expected));
+}
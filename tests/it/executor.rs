@@ -0,0 +1,29 @@
+#[cfg(feature = "test-util")]
+mod mock {
+    use clickhouse::{test, Client};
+
+    use crate::SimpleRow;
+
+    #[tokio::test]
+    async fn tracking_executor() {
+        use clickhouse::executor::TrackingExecutor;
+
+        let mock = test::Mock::new();
+        let executor = TrackingExecutor::default();
+        let client = Client::default()
+            .with_url(mock.url())
+            .with_executor(executor.clone());
+        let recording = mock.add(test::handlers::record());
+
+        let mut insert = client.insert::<SimpleRow>("some").unwrap();
+        insert.write(&SimpleRow::new(1, "one")).await.unwrap();
+        assert_eq!(executor.tasks(), ["insert"]);
+
+        insert.end().await.unwrap();
+        executor.wait().await;
+        assert!(executor.tasks().is_empty());
+
+        let rows: Vec<SimpleRow> = recording.collect().await;
+        assert_eq!(rows, [SimpleRow::new(1, "one")]);
+    }
+}
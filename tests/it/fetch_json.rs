@@ -0,0 +1,114 @@
+#![cfg(feature = "json")]
+
+use serde::{Deserialize, Serialize};
+
+use clickhouse::{error::Error, Row};
+
+#[tokio::test]
+async fn smoke() {
+    let client = prepare_database!();
+
+    #[derive(Debug, PartialEq, Row, Serialize, Deserialize)]
+    struct MyRow {
+        no: u64,
+        name: String,
+        tags: Vec<String>,
+    }
+
+    client
+        .query("CREATE TABLE test(no UInt64, name String, tags Array(String)) ENGINE = MergeTree ORDER BY no")
+        .execute()
+        .await
+        .unwrap();
+
+    let mut insert = client.insert("test").unwrap();
+    for no in 0..10 {
+        let row = MyRow {
+            no,
+            name: format!("name_{no}"),
+            tags: vec!["a".into(), "b\"c".into()],
+        };
+        insert.write(&row).await.unwrap();
+    }
+    insert.end().await.unwrap();
+
+    let mut cursor = client
+        .query("SELECT ?fields FROM test WHERE no >= ? ORDER BY no")
+        .bind(5)
+        .fetch_json::<MyRow>()
+        .unwrap();
+
+    let mut expected_no = 5;
+    while let Some(row) = cursor.next().await.unwrap() {
+        assert_eq!(row.no, expected_no);
+        assert_eq!(row.name, format!("name_{expected_no}"));
+        assert_eq!(row.tags, ["a", "b\"c"]);
+        expected_no += 1;
+    }
+
+    assert_eq!(expected_no, 10);
+    assert!(cursor.decoded_bytes() > 0);
+}
+
+#[tokio::test]
+async fn compact() {
+    let client = prepare_database!();
+
+    let mut cursor = client
+        .query("SELECT number, toString(number) FROM system.numbers LIMIT 3")
+        .fetch_json_compact::<(u64, String)>()
+        .unwrap();
+
+    let mut actual = Vec::new();
+    while let Some(row) = cursor.next().await.unwrap() {
+        actual.push(row);
+    }
+
+    assert_eq!(actual, [(0, "0".into()), (1, "1".into()), (2, "2".into())]);
+}
+
+#[tokio::test]
+async fn dynamic_values() {
+    let client = prepare_database!();
+
+    #[derive(Debug, Row, Deserialize)]
+    struct MyRow {
+        id: u64,
+        value: serde_json::Value,
+    }
+
+    let mut cursor = client
+        .query("SELECT number AS id, map('key', number) AS value FROM system.numbers LIMIT 2")
+        .fetch_json::<MyRow>()
+        .unwrap();
+
+    let row = cursor.next().await.unwrap().unwrap();
+    assert_eq!(row.id, 0);
+    assert_eq!(row.value, serde_json::json!({ "key": 0 }));
+
+    let row = cursor.next().await.unwrap().unwrap();
+    assert_eq!(row.id, 1);
+    assert_eq!(row.value, serde_json::json!({ "key": 1 }));
+
+    assert!(cursor.next().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn schema_mismatch() {
+    let client = prepare_database!();
+
+    #[derive(Debug, Row, Deserialize)]
+    struct MyRow {
+        #[allow(dead_code)]
+        number: u8,
+    }
+
+    let mut cursor = client
+        .query("SELECT ?fields FROM system.numbers WHERE number > 1000 LIMIT 1")
+        .fetch_json::<MyRow>()
+        .unwrap();
+
+    let err = cursor.next().await.unwrap_err();
+    assert!(matches!(err, Error::BadResponse(_)));
+    assert!(err.to_string().contains("1001"), "{err}");
+}
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+use clickhouse::{
+    types::geo::{MultiPolygon, Point, Polygon, Ring},
+    Row,
+};
+
+// See also: https://clickhouse.com/docs/en/sql-reference/data-types/geo
+
+#[tokio::test]
+async fn geo_data_types() {
+    let client = prepare_database!();
+
+    #[derive(Debug, PartialEq, Row, Serialize, Deserialize)]
+    struct Zone {
+        id: u32,
+        center: Point,
+        border: Ring,
+        area: Polygon,
+        areas: MultiPolygon,
+    }
+
+    client
+        .query(
+            "
+            CREATE TABLE test_geo(
+                id     UInt32,
+                center Point,
+                border Ring,
+                area   Polygon,
+                areas  MultiPolygon
+            )
+            ENGINE = MergeTree ORDER BY id",
+        )
+        .execute()
+        .await
+        .unwrap();
+
+    let square: Ring = [(0., 0.), (10., 0.), (10., 10.), (0., 10.)]
+        .into_iter()
+        .collect();
+    let hole: Ring = [(4., 4.), (6., 4.), (6., 6.), (4., 6.)]
+        .into_iter()
+        .collect();
+    let area = Polygon(vec![square.clone(), hole]);
+
+    let zone = Zone {
+        id: 1,
+        center: Point::new(5., 5.),
+        border: square,
+        area: area.clone(),
+        areas: MultiPolygon(vec![area.clone(), Polygon::default()]),
+    };
+
+    let mut insert = client.insert("test_geo").unwrap();
+    insert.write(&zone).await.unwrap();
+    insert.end().await.unwrap();
+
+    let actual = client
+        .query("SELECT ?fields FROM test_geo")
+        .fetch_one::<Zone>()
+        .await
+        .unwrap();
+
+    assert_eq!(actual, zone);
+
+    // Geo types can be bound as literals.
+    let inside = |point: Point| {
+        client
+            .query("SELECT pointInPolygon(?, ?)")
+            .bind(point)
+            .bind(&area)
+            .fetch_one::<u8>()
+    };
+
+    assert_eq!(inside(Point::new(1., 1.)).await.unwrap(), 1);
+    assert_eq!(inside(Point::new(5., 5.)).await.unwrap(), 0);
+    assert_eq!(inside(Point::new(11., 1.)).await.unwrap(), 0);
+}
@@ -169,3 +169,100 @@ async fn rename_insert() {
     let rows = fetch_rows::<RenameRow>(&client, table_name).await;
     assert_eq!(rows, vec!(row))
 }
+
+#[tokio::test]
+async fn insert_columns() {
+    #[derive(Debug, Row, Serialize, Deserialize, PartialEq)]
+    struct Data {
+        id: u64,
+        data: String,
+        extra: u32,
+    }
+
+    let table_name = "insert_columns";
+    let client = prepare_database!();
+    client
+        .query("CREATE TABLE ?(id UInt64, data String DEFAULT 'default', extra UInt32 DEFAULT id * 2) ENGINE = MergeTree ORDER BY id")
+        .bind(Identifier(table_name))
+        .execute()
+        .await
+        .unwrap();
+
+    let row = |id| Data {
+        id,
+        data: "foo".into(),
+        extra: 42,
+    };
+
+    // The order of columns doesn't matter.
+    let mut insert = client
+        .insert(table_name)
+        .unwrap()
+        .columns(&["data", "id"])
+        .unwrap();
+    insert.write(&row(1)).await.unwrap();
+    insert.end().await.unwrap();
+
+    let mut insert = client.insert(table_name).unwrap().columns(&["id"]).unwrap();
+    insert.write(&row(2)).await.unwrap();
+    insert.end().await.unwrap();
+
+    let mut rows = fetch_rows::<Data>(&client, table_name).await;
+    rows.sort_by_key(|row| row.id);
+    assert_eq!(
+        rows,
+        vec![
+            Data {
+                id: 1,
+                data: "foo".into(),
+                extra: 2,
+            },
+            Data {
+                id: 2,
+                data: "default".into(),
+                extra: 4,
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn insert_invalid_columns() {
+    let client = Client::default();
+
+    for columns in [&[][..], &["id", "unknown"], &["id", "data", "id"]] {
+        let err = client
+            .insert::<SimpleRow>("some")
+            .unwrap()
+            .columns(columns)
+            .err()
+            .unwrap();
+        assert!(matches!(err, clickhouse::error::Error::InvalidParams(_)));
+    }
+}
+
+#[cfg(feature = "test-util")]
+mod mock {
+    use clickhouse::{test, Client};
+
+    use crate::SimpleRow;
+
+    #[tokio::test]
+    async fn columns() {
+        let mock = test::Mock::new();
+        let client = Client::default().with_url(mock.url());
+        let recording = mock.add(test::handlers::record::<u64>());
+
+        let mut insert = client
+            .insert::<SimpleRow>("some")
+            .unwrap()
+            .columns(&["id"])
+            .unwrap();
+        insert.write(&SimpleRow::new(42, "foo")).await.unwrap();
+        insert.end().await.unwrap();
+
+        let (ids, params): (Vec<u64>, _) = recording.collect_with_params().await;
+        assert_eq!(ids, [42]);
+        assert_eq!(params["query"], "INSERT INTO some(`id`) FORMAT RowBinary");
+    }
+}
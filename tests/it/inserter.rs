@@ -288,3 +288,224 @@ async fn overrides_client_options() {
     let rows = fetch_rows::<SimpleRow>(&client, table_name).await;
     assert_eq!(rows, vec!(row))
 }
+
+#[tokio::test]
+async fn parallelism() {
+    let client = prepare_database!();
+    create_table(&client).await;
+
+    let mut inserter = client.inserter("test").unwrap().with_parallelism(3);
+    let rows = 100;
+
+    for i in 1..=rows {
+        inserter.write(&MyRow::new(i)).unwrap();
+
+        if i % 30 == 0 {
+            // Commits are asynchronous, data of the previous one is returned.
+            let quantities = inserter.force_commit().await.unwrap();
+            let expected = if i == 30 { (0, 0) } else { (30, 1) };
+            assert_eq!((quantities.rows, quantities.transactions), expected);
+        }
+    }
+
+    let quantities = inserter.end().await.unwrap();
+    assert_eq!((quantities.rows, quantities.transactions), (40, 2));
+
+    let (count, sum) = client
+        .query("SELECT count(), sum(toUInt64(data)) FROM test")
+        .fetch_one::<(u64, u64)>()
+        .await
+        .unwrap();
+
+    assert_eq!(count, rows);
+    assert_eq!(sum, (1..=rows).sum::<u64>());
+}
+
+#[cfg(feature = "test-util")]
+mod mock {
+    use clickhouse::{test, Client};
+
+    use crate::SimpleRow;
+
+    #[tokio::test]
+    async fn spool() {
+        let dir =
+            std::env::temp_dir().join(format!("clickhouse-mock-spool-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let segments = || std::fs::read_dir(&dir).unwrap().count();
+
+        let mock = test::Mock::new();
+        let client = Client::default().with_url(mock.url());
+        let mut inserter = client
+            .inserter::<SimpleRow>("some")
+            .unwrap()
+            .with_spool(&dir);
+
+        // The failed `INSERT` is kept in the spool.
+        mock.add(test::handlers::failure(test::status::SERVICE_UNAVAILABLE));
+        inserter.write(&SimpleRow::new(1, "one")).unwrap();
+        inserter.write(&SimpleRow::new(2, "two")).unwrap();
+        assert!(inserter.force_commit().await.is_err());
+        assert_eq!(segments(), 1);
+
+        // It's replayed after the next successful one.
+        let current = mock.add(test::handlers::record());
+        let replayed = mock.add(test::handlers::record());
+        inserter.write(&SimpleRow::new(3, "three")).unwrap();
+        let quantities = inserter.force_commit().await.unwrap();
+        assert_eq!(quantities.rows, 3);
+        assert_eq!(quantities.transactions, 2);
+
+        let rows: Vec<SimpleRow> = current.collect().await;
+        assert_eq!(rows, [SimpleRow::new(3, "three")]);
+        let rows: Vec<SimpleRow> = replayed.collect().await;
+        assert_eq!(rows, [SimpleRow::new(1, "one"), SimpleRow::new(2, "two")]);
+        assert_eq!(segments(), 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn parallelism() {
+        use clickhouse::inserter::Quantities;
+
+        let mock = test::Mock::new();
+        let client = Client::default().with_url(mock.url());
+        let mut inserter = client
+            .inserter::<SimpleRow>("some")
+            .unwrap()
+            .with_parallelism(2);
+
+        let recordings = [
+            mock.add(test::handlers::record()),
+            mock.add(test::handlers::record()),
+        ];
+        for id in 0..5 {
+            inserter.write(&SimpleRow::new(id, "x")).unwrap();
+        }
+        assert_eq!(inserter.pending().rows, 5);
+
+        // Responses are awaited by the next commit.
+        let quantities = inserter.force_commit().await.unwrap();
+        assert_eq!(quantities, Quantities::ZERO);
+        assert_eq!(inserter.pending().rows, 0);
+
+        // New rows keep flowing into new `INSERT`s meanwhile.
+        let next = mock.add(test::handlers::record());
+        inserter.write(&SimpleRow::new(5, "x")).unwrap();
+
+        // Shards are ended together, it's one transaction.
+        let quantities = inserter.force_commit().await.unwrap();
+        assert_eq!((quantities.rows, quantities.transactions), (5, 1));
+
+        let shards = inserter.shard_quantities();
+        assert_eq!((shards[0].rows, shards[1].rows), (3, 2));
+
+        let mut rows = Vec::new();
+        for recording in recordings {
+            let shard: Vec<SimpleRow> = recording.collect().await;
+            // Ordering is preserved inside a shard.
+            assert!(shard.windows(2).all(|w| w[0].id < w[1].id));
+            rows.extend(shard);
+        }
+        rows.sort_by_key(|row| row.id);
+        assert_eq!(
+            rows,
+            (0..5).map(|id| SimpleRow::new(id, "x")).collect::<Vec<_>>()
+        );
+
+        // `end()` waits for all `INSERT`s.
+        let quantities = inserter.end().await.unwrap();
+        assert_eq!((quantities.rows, quantities.transactions), (1, 1));
+        let rows: Vec<SimpleRow> = next.collect().await;
+        assert_eq!(rows, [SimpleRow::new(5, "x")]);
+    }
+
+    #[tokio::test]
+    async fn parallelism_errors() {
+        use clickhouse::inserter::Quantities;
+
+        let mock = test::Mock::new();
+        let client = Client::default().with_url(mock.url());
+        let mut inserter = client
+            .inserter::<SimpleRow>("some")
+            .unwrap()
+            .with_parallelism(2);
+
+        // Errors are related to the previous commit too.
+        mock.add(test::handlers::failure(test::status::SERVICE_UNAVAILABLE));
+        inserter.write(&SimpleRow::new(0, "x")).unwrap();
+        assert_eq!(inserter.force_commit().await.unwrap(), Quantities::ZERO);
+        assert!(inserter.force_commit().await.is_err());
+        assert_eq!(inserter.end().await.unwrap(), Quantities::ZERO);
+
+        // `end()` reports errors of in-flight `INSERT`s.
+        let mut inserter = client
+            .inserter::<SimpleRow>("some")
+            .unwrap()
+            .with_parallelism(2);
+
+        mock.add(test::handlers::failure(test::status::SERVICE_UNAVAILABLE));
+        inserter.write(&SimpleRow::new(1, "x")).unwrap();
+        assert_eq!(inserter.force_commit().await.unwrap(), Quantities::ZERO);
+        assert!(inserter.end().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn async_insert() {
+        let mock = test::Mock::new();
+        let client = Client::default().with_url(mock.url());
+        let mut inserter = client
+            .inserter::<SimpleRow>("some")
+            .unwrap()
+            .with_max_rows(100)
+            .with_option("async_insert", "1");
+
+        // Limits aren't reached, but the server batches rows itself.
+        let recording = mock.add(test::handlers::record());
+        inserter.write(&SimpleRow::new(1, "one")).unwrap();
+        assert_eq!(inserter.commit().await.unwrap().rows, 1);
+
+        let (actual, params): (Vec<SimpleRow>, _) = recording.collect_with_params().await;
+        assert_eq!(actual, [SimpleRow::new(1, "one")]);
+        assert_eq!(params["async_insert"], "1");
+    }
+
+    #[tokio::test]
+    async fn deduplication_token() {
+        use clickhouse::inserter::DeduplicationToken;
+
+        async fn tokens(token: DeduplicationToken, batches: &[&[u64]]) -> Vec<String> {
+            let mock = test::Mock::new();
+            let client = Client::default().with_url(mock.url());
+            let mut inserter = client
+                .inserter::<SimpleRow>("some")
+                .unwrap()
+                .with_deduplication_token(token);
+
+            let mut tokens = Vec::new();
+            for batch in batches {
+                let recording = mock.add(test::handlers::record());
+                for &id in *batch {
+                    inserter.write(&SimpleRow::new(id, "x")).unwrap();
+                }
+                inserter.force_commit().await.unwrap();
+
+                let (rows, mut params): (Vec<SimpleRow>, _) = recording.collect_with_params().await;
+                assert_eq!(rows.len(), batch.len());
+                tokens.extend(params.remove("insert_deduplication_token"));
+            }
+            tokens
+        }
+
+        let actual = tokens(DeduplicationToken::sequence("src", 7), &[&[1], &[2]]).await;
+        assert_eq!(actual, ["src-7", "src-8"]);
+
+        // The same batch gets the same token.
+        let actual = tokens(DeduplicationToken::hash("src"), &[&[1, 2], &[3], &[1, 2]]).await;
+        assert_eq!(actual.len(), 3);
+        assert!(actual[0].starts_with("src-"));
+        assert_ne!(actual[0], actual[1]);
+        assert_eq!(actual[0], actual[2]);
+    }
+}
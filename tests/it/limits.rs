@@ -0,0 +1,44 @@
+#[cfg(feature = "test-util")]
+mod mock {
+    use clickhouse::{test, Client};
+
+    use crate::SimpleRow;
+
+    #[tokio::test]
+    async fn smoke() {
+        use clickhouse::error::Error;
+
+        let mock = test::Mock::new();
+        let client = Client::default().with_url(mock.url());
+
+        // Error bodies.
+        mock.add(test::handlers::failure(test::status::SERVICE_UNAVAILABLE));
+        let err = client.query("SELECT 1").execute().await.unwrap_err();
+        assert!(matches!(err, Error::BadResponse(_)), "{err:?}");
+
+        mock.add(test::handlers::failure(test::status::SERVICE_UNAVAILABLE));
+        let err = client
+            .clone()
+            .with_max_error_body_size(8)
+            .query("SELECT 1")
+            .execute()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::LimitExceeded(_)), "{err:?}");
+
+        // Rows.
+        let client = client.with_max_row_size(64);
+        let short = || SimpleRow::new(1, "a".repeat(32));
+        let long = || SimpleRow::new(2, "b".repeat(128));
+
+        mock.add(test::handlers::provide([short(), short()]));
+        let rows = crate::fetch_rows::<SimpleRow>(&client, "doesn't matter").await;
+        assert_eq!(rows, [short(), short()]);
+
+        mock.add(test::handlers::provide([short(), long()]));
+        let mut cursor = client.query("doesn't matter").fetch::<SimpleRow>().unwrap();
+        assert_eq!(cursor.next().await.unwrap(), Some(short()));
+        let err = cursor.next().await.unwrap_err();
+        assert!(matches!(err, Error::LimitExceeded(_)), "{err:?}");
+    }
+}
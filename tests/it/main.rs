@@ -113,19 +113,34 @@ async fn flush_query_log(client: &Client) {
     client.query("SYSTEM FLUSH LOGS").execute().await.unwrap();
 }
 
+mod async_inserter;
+mod batcher;
+mod catalog;
 mod chrono;
+mod clickhouse_enum;
 mod cloud_jwt;
 mod compression;
 mod cursor_error;
 mod cursor_stats;
+mod dynamic;
+mod executor;
 mod fetch_bytes;
+mod fetch_json;
+mod geo;
 mod insert;
 mod inserter;
 mod int128;
 mod ip;
+mod limits;
+mod map;
+mod migrate;
 mod mock;
 mod nested;
+mod qb;
 mod query;
+mod routing_inserter;
+mod script;
+mod table;
 mod time;
 mod user_agent;
 mod uuid;
@@ -0,0 +1,90 @@
+use std::collections::{BTreeMap, HashMap};
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use clickhouse::Row;
+
+#[tokio::test]
+async fn map_data_type() {
+    let client = prepare_database!();
+
+    #[derive(Debug, PartialEq, Row, Serialize, Deserialize)]
+    struct MyRow {
+        id: u32,
+        hash: HashMap<String, u64>,
+        btree: BTreeMap<u16, Vec<String>>,
+        index: IndexMap<String, Option<i32>>,
+    }
+
+    client
+        .query(
+            "
+            CREATE TABLE test_map(
+                id    UInt32,
+                hash  Map(String, UInt64),
+                btree Map(UInt16, Array(String)),
+                index Map(String, Nullable(Int32))
+            )
+            ENGINE = MergeTree ORDER BY id",
+        )
+        .execute()
+        .await
+        .unwrap();
+
+    let rows = [
+        MyRow {
+            id: 1,
+            hash: [("a".into(), 1), ("b".into(), 2)].into_iter().collect(),
+            btree: [(1, vec!["x".into()]), (2, vec![])].into_iter().collect(),
+            // The order of keys is preserved by ClickHouse.
+            index: [("z".into(), Some(1)), ("a".into(), None)]
+                .into_iter()
+                .collect(),
+        },
+        MyRow {
+            id: 2,
+            hash: HashMap::new(),
+            btree: BTreeMap::new(),
+            index: IndexMap::new(),
+        },
+    ];
+
+    let mut insert = client.insert("test_map").unwrap();
+    for row in &rows {
+        insert.write(row).await.unwrap();
+    }
+    insert.end().await.unwrap();
+
+    let actual = client
+        .query("SELECT ?fields FROM test_map ORDER BY id")
+        .fetch_all::<MyRow>()
+        .await
+        .unwrap();
+
+    assert_eq!(actual, rows);
+    assert_eq!(
+        actual[0].index.keys().collect::<Vec<_>>(),
+        rows[0].index.keys().collect::<Vec<_>>()
+    );
+
+    // Maps can be bound as well.
+    let bound = client
+        .query("SELECT count() FROM test_map WHERE hash = ?")
+        .bind(&rows[0].hash)
+        .fetch_one::<u64>()
+        .await
+        .unwrap();
+
+    assert_eq!(bound, 1);
+
+    // And used as server-side parameters.
+    let by_param = client
+        .query("SELECT count() FROM test_map WHERE btree = {btree:Map(UInt16, Array(String))}")
+        .param("btree", &rows[0].btree)
+        .fetch_one::<u64>()
+        .await
+        .unwrap();
+
+    assert_eq!(by_param, 1);
+}
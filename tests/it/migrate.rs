@@ -0,0 +1,213 @@
+#![cfg(feature = "migrate")]
+
+use clickhouse::migrate::Migration;
+
+#[tokio::test]
+async fn concurrent_runners() {
+    let client = prepare_database!();
+
+    let migrator = |owner: &str| {
+        client
+            .migrator()
+            .with_migrations([
+                Migration::new(
+                    1,
+                    "create",
+                    "CREATE TABLE events (id UInt64) ENGINE = MergeTree ORDER BY id",
+                ),
+                Migration::new(2, "add_name", "ALTER TABLE events ADD COLUMN name String"),
+            ])
+            // Needs quoting.
+            .with_table("schema-migrations")
+            .with_owner(owner)
+    };
+
+    let (a, b) = (migrator("a"), migrator("b"));
+    let (a, b) = tokio::join!(a.run(), b.run());
+
+    // Either one runner has failed on the lock, or it has come later and
+    // found nothing to apply. In any case, migrations are applied once.
+    let mut applied = Vec::new();
+    for result in [a, b] {
+        match result {
+            Ok(versions) => applied.extend(versions),
+            Err(err) => assert!(err.to_string().contains("locked by"), "{err}"),
+        }
+    }
+    assert_eq!(applied, [1, 2]);
+
+    assert!(migrator("c").pending().await.unwrap().is_empty());
+    assert_eq!(migrator("c").run().await.unwrap(), Vec::<u64>::new());
+
+    let columns = client
+        .query("SELECT name FROM system.columns WHERE database = currentDatabase() AND table = 'events'")
+        .fetch_all::<String>()
+        .await
+        .unwrap();
+    assert_eq!(columns, ["id", "name"]);
+}
+
+#[tokio::test]
+async fn same_owner_again() {
+    let client = prepare_database!();
+
+    let create = Migration::new(
+        1,
+        "create",
+        "CREATE TABLE events (id UInt64) ENGINE = MergeTree ORDER BY id",
+    );
+    let alter = Migration::new(2, "add_name", "ALTER TABLE events ADD COLUMN name String");
+
+    // The released lock of the owner must not prevent it from locking again.
+    let migrator = client.migrator().with_owner("fixed");
+    let migrator = migrator.with_migration(create);
+    assert_eq!(migrator.run().await.unwrap(), [1]);
+    let migrator = migrator.with_migration(alter);
+    assert_eq!(migrator.run().await.unwrap(), [2]);
+    assert_eq!(migrator.run().await.unwrap(), Vec::<u64>::new());
+}
+
+#[cfg(feature = "test-util")]
+mod mock {
+    use clickhouse::{test, Client};
+
+    #[tokio::test]
+    async fn smoke() {
+        use clickhouse::migrate::Migration;
+
+        let mock = test::Mock::new();
+        let client = Client::default().with_url(mock.url());
+
+        let first = Migration::parse(
+            "0001_create.sql",
+            "CREATE TABLE t (x UInt8) ENGINE = Memory",
+        )
+        .unwrap();
+        let second = Migration::new(
+            2,
+            "alter",
+            "ALTER TABLE t {on_cluster} ADD COLUMN y UInt8; ALTER TABLE t {on_cluster} ADD COLUMN z UInt8;",
+        );
+
+        let migrator = client
+            .migrator()
+            .with_migrations([second.clone(), first.clone()])
+            .with_cluster("main")
+            .with_owner("me");
+
+        // Dry run doesn't create anything.
+        mock.add(test::handlers::provide([1u8]));
+        mock.add(test::handlers::provide([(1u64, first.checksum())]));
+        assert_eq!(
+            migrator.dry_run().await.unwrap(),
+            "-- 0002_alter.sql\n\
+             ALTER TABLE t ON CLUSTER `main` ADD COLUMN y UInt8;\n\
+             ALTER TABLE t ON CLUSTER `main` ADD COLUMN z UInt8;\n\n"
+        );
+
+        // Applies the second migration under the lock.
+        let create = mock.add(test::handlers::record_ddl());
+        mock.add(test::handlers::record_ddl());
+        let lock = mock.add(test::handlers::record_ddl());
+        mock.add(test::handlers::provide(["me".to_string()]));
+        mock.add(test::handlers::provide([(1u64, first.checksum())]));
+        let renew = mock.add(test::handlers::record_ddl());
+        mock.add(test::handlers::provide(["me".to_string()]));
+        let alter = mock.add(test::handlers::record_ddl());
+        mock.add(test::handlers::record_ddl());
+        let record = mock.add(test::handlers::record_ddl());
+        let unlock = mock.add(test::handlers::record_ddl());
+
+        assert_eq!(migrator.run().await.unwrap(), [2]);
+        assert!(create
+            .query()
+            .await
+            .starts_with("CREATE TABLE IF NOT EXISTS `_migrations` ON CLUSTER `main` ("));
+        assert!(lock.query().await.contains("VALUES ('me', now64(6) + "));
+        assert!(renew
+            .query()
+            .await
+            .contains("SELECT owner, acquired_at, now64(6) + toIntervalMillisecond(600000)"));
+        assert_eq!(
+            alter.query().await,
+            "ALTER TABLE t ON CLUSTER `main` ADD COLUMN y UInt8"
+        );
+        assert!(record
+            .query()
+            .await
+            .ends_with(&format!("VALUES (2, 'alter', '{}')", second.checksum())));
+        assert!(unlock.query().await.contains("('me', now64(6), 1)"));
+
+        // Held by another runner.
+        mock.add(test::handlers::record_ddl());
+        mock.add(test::handlers::record_ddl());
+        mock.add(test::handlers::record_ddl());
+        mock.add(test::handlers::provide(["other".to_string()]));
+        let unlock = mock.add(test::handlers::record_ddl());
+
+        let err = migrator.run().await.unwrap_err();
+        assert!(err.to_string().contains("locked by `other`"), "{err}");
+        assert!(unlock.query().await.contains("('me', now64(6), 1)"));
+    }
+
+    #[tokio::test]
+    async fn quoted_table() {
+        let mock = test::Mock::new();
+        let client = Client::default().with_url(mock.url());
+        let migrator = client.migrator().with_table("db.mig?").with_owner("me");
+
+        let create = mock.add(test::handlers::record_ddl());
+        let create_lock = mock.add(test::handlers::record_ddl());
+        let lock = mock.add(test::handlers::record_ddl());
+        mock.add(test::handlers::provide(["me".to_string()]));
+        mock.add(test::handlers::provide(Vec::<(u64, String)>::new()));
+        let unlock = mock.add(test::handlers::record_ddl());
+
+        assert_eq!(migrator.run().await.unwrap(), Vec::<u64>::new());
+        assert!(create
+            .query()
+            .await
+            .starts_with("CREATE TABLE IF NOT EXISTS `db`.`mig?`  ("));
+        assert!(create_lock
+            .query()
+            .await
+            .starts_with("CREATE TABLE IF NOT EXISTS `db`.`mig?_lock`  ("));
+        assert!(lock
+            .query()
+            .await
+            .starts_with("INSERT INTO `db`.`mig?_lock` (owner, expires_at) VALUES ('me', "));
+        assert!(unlock.query().await.starts_with(
+            "INSERT INTO `db`.`mig?_lock` (owner, expires_at, released) VALUES ('me', "
+        ));
+    }
+
+    #[tokio::test]
+    async fn expired_lock() {
+        use clickhouse::migrate::Migration;
+
+        let mock = test::Mock::new();
+        let client = Client::default().with_url(mock.url());
+        let migrator = client
+            .migrator()
+            .with_migration(Migration::new(1, "create", "CREATE TABLE t (x UInt8)"))
+            .with_owner("me");
+
+        mock.add(test::handlers::record_ddl());
+        mock.add(test::handlers::record_ddl());
+        mock.add(test::handlers::record_ddl());
+        mock.add(test::handlers::provide(["me".to_string()]));
+        mock.add(test::handlers::provide(Vec::<(u64, String)>::new()));
+        // The lease has expired before renewal, so nobody holds the lock.
+        mock.add(test::handlers::record_ddl());
+        mock.add(test::handlers::provide(Vec::<String>::new()));
+        // Errors of releasing the lock don't hide the reason.
+        mock.add(test::handlers::failure(test::status::SERVICE_UNAVAILABLE));
+
+        let err = migrator.run().await.unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("the migration lock of `me` has expired"),
+            "{err}"
+        );
+    }
+}
@@ -25,3 +25,23 @@ async fn provide() {
     tokio::time::advance(Duration::from_secs(100_000)).await;
     test_provide().await;
 }
+
+#[cfg(feature = "json")]
+#[tokio::test]
+async fn provide_json() {
+    let mock = test::Mock::new();
+    let client = Client::default().with_url(mock.url());
+    let expected = vec![SimpleRow::new(1, "one"), SimpleRow::new(2, "two")];
+    mock.add(test::handlers::provide_json(&expected));
+
+    let mut cursor = client
+        .query("SELECT ?fields FROM doesnt_matter")
+        .fetch_json::<SimpleRow>()
+        .unwrap();
+
+    let mut actual = Vec::new();
+    while let Some(row) = cursor.next().await.unwrap() {
+        actual.push(row);
+    }
+    assert_eq!(actual, expected);
+}
@@ -0,0 +1,116 @@
+#![cfg(feature = "qb")]
+
+use serde::{Deserialize, Serialize};
+
+use clickhouse::{
+    qb::{self, col, func},
+    Row,
+};
+
+#[tokio::test]
+async fn smoke() {
+    let client = prepare_database!();
+
+    #[derive(Debug, PartialEq, Row, Serialize, Deserialize)]
+    struct MyRow {
+        no: u64,
+        name: String,
+        tags: Vec<String>,
+    }
+
+    client
+        .query("CREATE TABLE test(no UInt64, name String, tags Array(String)) ENGINE = MergeTree ORDER BY no")
+        .execute()
+        .await
+        .unwrap();
+
+    let mut insert = client.insert("test").unwrap();
+    for no in 0..10 {
+        let name = if no % 2 == 0 { "even?" } else { "odd:" };
+        let tags = vec![format!("t{no}"), "all".into()];
+        insert
+            .write(&MyRow {
+                no,
+                name: name.into(),
+                tags,
+            })
+            .await
+            .unwrap();
+    }
+    insert.end().await.unwrap();
+
+    // SELECT
+    let rows = qb::select::<MyRow>()
+        .from("test")
+        .prewhere(col("no").ge(2))
+        .filter(col("name").eq("even?"))
+        .order_by(col("no").desc())
+        .limit(2)
+        .query(&client)
+        .fetch_all::<MyRow>()
+        .await
+        .unwrap();
+
+    let nos = rows.iter().map(|row| row.no).collect::<Vec<_>>();
+    assert_eq!(nos, [8, 6]);
+
+    // ARRAY JOIN, GROUP BY and LIMIT BY
+    let counts = qb::select::<(String, u64)>()
+        .column(col("tags"))
+        .column(func("count", []))
+        .from("test")
+        .array_join(col("tags"))
+        .filter(col("no").is_in(&[1, 2, 3][..]))
+        .group_by(col("tags"))
+        .order_by(col("tags"))
+        .limit_by(1, [col("tags")])
+        .query(&client)
+        .fetch_all::<(String, u64)>()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        counts,
+        [
+            ("all".to_string(), 3),
+            ("t1".to_string(), 1),
+            ("t2".to_string(), 1),
+            ("t3".to_string(), 1),
+        ]
+    );
+
+    // INSERT SELECT
+    client
+        .query("CREATE TABLE copy AS test")
+        .execute()
+        .await
+        .unwrap();
+
+    qb::insert_into::<MyRow>("copy")
+        .select(
+            qb::select::<MyRow>()
+                .from("test")
+                .filter(col("name").eq("odd:")),
+        )
+        .query(&client)
+        .execute()
+        .await
+        .unwrap();
+
+    // ALTER
+    qb::alter_table("copy")
+        .delete()
+        .filter(col("no").gt(4))
+        .settings("mutations_sync", 1)
+        .query(&client)
+        .execute()
+        .await
+        .unwrap();
+
+    let count = client
+        .query("SELECT count() FROM copy")
+        .fetch_one::<u64>()
+        .await
+        .unwrap();
+    assert_eq!(count, 2);
+}
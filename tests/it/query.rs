@@ -123,6 +123,182 @@ async fn server_side_param() {
     assert_eq!(result, &["a", "bc"]);
 }
 
+#[tokio::test]
+async fn external_tables() {
+    let client = prepare_database!();
+
+    #[derive(Row, Serialize)]
+    struct User<'a> {
+        id: u64,
+        name: &'a str,
+        tags: Vec<&'a str>,
+        parent: Option<u64>,
+    }
+
+    let users = [
+        User {
+            id: 1,
+            name: "a",
+            tags: vec![],
+            parent: None,
+        },
+        User {
+            id: 2,
+            name: "b",
+            tags: vec!["x", "y"],
+            parent: Some(1),
+        },
+    ];
+    let ids = [2u64, 3];
+
+    #[derive(Debug, PartialEq, Row, Deserialize)]
+    struct Found {
+        name: String,
+        tags: u64,
+        parent: Option<u64>,
+    }
+
+    let found = client
+        .query(
+            "SELECT name, length(tags) AS tags, parent FROM users
+             WHERE id IN ids OR id = 1 ORDER BY id",
+        )
+        .with_external_table("users", &users)
+        .with_external_table("ids", &ids)
+        .fetch_all::<Found>()
+        .await
+        .unwrap();
+
+    let expected = [
+        Found {
+            name: "a".into(),
+            tags: 0,
+            parent: None,
+        },
+        Found {
+            name: "b".into(),
+            tags: 2,
+            parent: Some(1),
+        },
+    ];
+    assert_eq!(found, expected);
+
+    let days = client
+        .query("SELECT toString(day) FROM days")
+        .with_external_table_structure("days", "day Date", &[19000u16])
+        .fetch_all::<String>()
+        .await
+        .unwrap();
+    assert_eq!(days, ["2022-01-08"]);
+
+    let err = client
+        .query("SELECT 1")
+        .with_external_table::<u64>("empty", &[])
+        .execute()
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidParams(_)));
+}
+
+#[tokio::test]
+async fn query_macro() {
+    let client = prepare_database!();
+
+    #[derive(Debug, PartialEq, Row, Deserialize)]
+    struct MyRow {
+        a: u32,
+        b: String,
+    }
+
+    let rows: Vec<MyRow> = clickhouse::query!(
+        client,
+        "SELECT ?fields FROM (SELECT number AS a, toString(a) AS b FROM numbers(?))
+         WHERE a >= :min AND b != ?",
+        5,
+        "3",
+        min = 2,
+    )
+    .fetch_all()
+    .await
+    .unwrap();
+
+    let expected = [
+        MyRow {
+            a: 2,
+            b: "2".into(),
+        },
+        MyRow {
+            a: 4,
+            b: "4".into(),
+        },
+    ];
+    assert_eq!(rows, expected);
+}
+
+// `{p: Type}` must be substituted the same way as `?` is bound.
+async fn check_param_as_bind(client: &clickhouse::Client, ty: &str, value: impl Serialize) {
+    let sql = format!("SELECT toString({{p: {ty}}}), toString(CAST(? AS {ty}))");
+    let (param, bound) = client
+        .query(&sql)
+        .bind(&value)
+        .param("p", &value)
+        .fetch_one::<(String, String)>()
+        .await
+        .unwrap_or_else(|err| panic!("{ty}: {err}"));
+    assert_eq!(param, bound, "{ty}");
+}
+
+#[tokio::test]
+async fn server_side_param_types() {
+    let client = prepare_database!();
+
+    #[derive(Serialize)]
+    struct Point<'a> {
+        x: i32,
+        name: &'a str,
+        tags: Vec<Option<&'a str>>,
+    }
+
+    #[derive(Serialize)]
+    struct Ip(#[serde(with = "clickhouse::serde::ipv4")] std::net::Ipv4Addr);
+
+    let map: std::collections::BTreeMap<_, _> =
+        [("a'", vec![1u8, 2]), ("b", vec![])].into_iter().collect();
+    let point = Point {
+        x: -1,
+        name: "a'\\b",
+        tags: vec![Some("t"), None],
+    };
+
+    check_param_as_bind(&client, "String", "a'\\b\t").await;
+    check_param_as_bind(&client, "Nullable(UInt8)", None::<u8>).await;
+    check_param_as_bind(&client, "Nullable(UInt8)", Some(1u8)).await;
+    check_param_as_bind(
+        &client,
+        "Array(Array(Nullable(Int8)))",
+        vec![vec![Some(1i8), None], vec![]],
+    )
+    .await;
+    check_param_as_bind(&client, "Map(String, Array(UInt8))", &map).await;
+    check_param_as_bind(
+        &client,
+        "Tuple(x Int32, name String, tags Array(Nullable(String)))",
+        &point,
+    )
+    .await;
+    check_param_as_bind(&client, "Int128", -42i128).await;
+    check_param_as_bind(&client, "UInt128", 42u128).await;
+    check_param_as_bind(&client, "UUID", uuid::Uuid::new_v4()).await;
+    check_param_as_bind(&client, "IPv4", Ip("192.168.0.1".parse().unwrap())).await;
+    check_param_as_bind(
+        &client,
+        "IPv6",
+        "2001:db8::1".parse::<std::net::Ipv6Addr>().unwrap(),
+    )
+    .await;
+    check_param_as_bind(&client, "Decimal(10, 3)", "-12.345").await;
+}
+
 // See #19.
 #[tokio::test]
 async fn long_query() {
@@ -263,3 +439,45 @@ async fn prints_query() {
         "SELECT ?fields FROM test WHERE a = ? AND b < ?"
     );
 }
+
+#[cfg(feature = "test-util")]
+mod mock {
+    use clickhouse::{test, Client};
+
+    #[tokio::test]
+    async fn prepared() {
+        let mock = test::Mock::new();
+        let client = Client::default().with_url(mock.url());
+
+        let prepared = client.prepare("ALTER TABLE t DELETE WHERE id = ? AND ts < {ts: UInt32}");
+        assert_eq!(prepared.server_params().collect::<Vec<_>>(), ["ts"]);
+
+        for id in [1, 2] {
+            let recording = mock.add(test::handlers::record_ddl());
+            prepared.bind(id).param("ts", 5).execute().await.unwrap();
+            assert_eq!(
+                recording.query().await,
+                format!("ALTER TABLE t DELETE WHERE id = {id} AND ts < {{ts: UInt32}}")
+            );
+        }
+
+        // Caught before sending, thus no handler is needed.
+        let err = prepared.bind(3).execute().await.unwrap_err();
+        assert!(matches!(err, clickhouse::error::Error::InvalidParams(_)));
+        assert!(err.to_string().contains("{ts: ..}"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn server_params_in_comments() {
+        let mock = test::Mock::new();
+        let client = Client::default().with_url(mock.url());
+
+        let sql = "ALTER TABLE t DELETE WHERE id = ? -- or {id: UInt32}\n/* {ts: UInt32} */";
+        let recording = mock.add(test::handlers::record_ddl());
+        client.query(sql).bind(1).execute().await.unwrap();
+        assert!(recording
+            .query()
+            .await
+            .starts_with("ALTER TABLE t DELETE WHERE id = 1 --"));
+    }
+}
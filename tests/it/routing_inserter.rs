@@ -0,0 +1,101 @@
+#![cfg(feature = "inserter")]
+
+use std::borrow::Cow;
+
+use crate::{create_simple_table, fetch_rows, SimpleRow};
+
+#[tokio::test]
+async fn routes() {
+    let client = prepare_database!();
+    create_simple_table(&client, "events_a").await;
+    create_simple_table(&client, "events_b").await;
+
+    let mut inserter = client
+        .routing_inserter(|row: &SimpleRow| Cow::Owned(format!("events_{}", row.data)))
+        .with_max_rows(3);
+
+    for id in 0..10 {
+        let table = if id % 3 == 0 { "a" } else { "b" };
+        inserter.write(&SimpleRow::new(id, table)).unwrap();
+        inserter.commit().await.unwrap();
+    }
+
+    let quantities = inserter.end().await.unwrap();
+    assert_ne!(quantities.rows, 0);
+
+    // Parts aren't merged yet, so the order isn't guaranteed.
+    let ids = |rows: Vec<SimpleRow>| {
+        let mut ids = rows.into_iter().map(|row| row.id).collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids
+    };
+    assert_eq!(ids(fetch_rows(&client, "events_a").await), [0, 3, 6, 9]);
+    assert_eq!(
+        ids(fetch_rows(&client, "events_b").await),
+        [1, 2, 4, 5, 7, 8]
+    );
+}
+
+#[cfg(feature = "test-util")]
+mod mock {
+    use std::time::Duration;
+
+    use clickhouse::{test, Client};
+
+    use crate::SimpleRow;
+
+    #[tokio::test]
+    async fn smoke() {
+        use std::{borrow::Cow, collections::HashMap};
+
+        let mock = test::Mock::new();
+        let client = Client::default().with_url(mock.url());
+        let mut inserter = client
+            .routing_inserter(|row: &SimpleRow| Cow::Borrowed(&*row.data))
+            .with_total_max_rows(3)
+            .with_idle_timeout(Some(Duration::from_millis(100)));
+
+        let recordings = [
+            mock.add(test::handlers::record()),
+            mock.add(test::handlers::record()),
+        ];
+        for (id, table) in [(0, "a"), (1, "b")] {
+            inserter.write(&SimpleRow::new(id, table)).unwrap();
+        }
+
+        // Limits aren't reached.
+        assert_eq!(inserter.commit().await.unwrap().rows, 0);
+        assert_eq!(inserter.pending().rows, 2);
+
+        inserter.write(&SimpleRow::new(2, "a")).unwrap();
+
+        // The total limit is reached, all routes are committed.
+        let quantities = inserter.commit().await.unwrap();
+        assert_eq!((quantities.rows, quantities.transactions), (3, 3));
+
+        let mut tables = HashMap::new();
+        for recording in recordings {
+            let (rows, params): (Vec<SimpleRow>, _) = recording.collect_with_params().await;
+            let table = rows[0].data.clone();
+            assert!(params["query"].contains(&format!("INSERT INTO {table}")));
+            tables.insert(table, rows.len());
+        }
+        assert_eq!(tables, HashMap::from([("a".into(), 2), ("b".into(), 1)]));
+
+        // Idle routes are evicted.
+        let recording = mock.add(test::handlers::record());
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        inserter.write(&SimpleRow::new(3, "b")).unwrap();
+        assert_eq!(inserter.commit().await.unwrap().rows, 0);
+
+        let mut routes = inserter.routes().collect::<Vec<_>>();
+        routes.sort_by_key(|(table, _)| *table);
+        assert_eq!(routes.len(), 1);
+        assert_eq!((routes[0].0, routes[0].1.rows), ("b", 1));
+
+        let quantities = inserter.end().await.unwrap();
+        assert_eq!(quantities.rows, 1);
+        let rows: Vec<SimpleRow> = recording.collect().await;
+        assert_eq!(rows, vec![SimpleRow::new(3, "b")]);
+    }
+}
@@ -0,0 +1,50 @@
+#[cfg(feature = "test-util")]
+mod mock {
+    use clickhouse::{test, Client};
+
+    #[tokio::test]
+    async fn smoke() {
+        let mock = test::Mock::new();
+        let client = Client::default().with_url(mock.url());
+
+        let script = "
+            CREATE TABLE t (s String) ENGINE = Memory; -- the first one
+            INSERT INTO t VALUES ('?;');
+            DROP TABLE t;
+        ";
+
+        // Stops on the first error.
+        let create = mock.add(test::handlers::record_ddl());
+        mock.add(test::handlers::failure(test::status::BAD_REQUEST));
+
+        let report = client.execute_script(script).await.unwrap();
+        assert!(!report.is_ok());
+        assert_eq!(report.statements.len(), 2);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(
+            create.query().await,
+            "CREATE TABLE t (s String) ENGINE = Memory"
+        );
+
+        let failed = report.first_error().unwrap();
+        assert_eq!(failed.sql, "INSERT INTO t VALUES ('?;')");
+        assert_eq!(failed.line, 3);
+        assert!(report.into_result().is_err());
+
+        // Executes all statements.
+        mock.add(test::handlers::record_ddl());
+        mock.add(test::handlers::failure(test::status::BAD_REQUEST));
+        let drop = mock.add(test::handlers::record_ddl());
+
+        let report = client
+            .script(script)
+            .with_stop_on_error(false)
+            .execute()
+            .await
+            .unwrap();
+        assert_eq!(report.statements.len(), 3);
+        assert_eq!(report.skipped, 0);
+        assert!(report.statements[2].result.is_ok());
+        assert_eq!(drop.query().await, "DROP TABLE t");
+    }
+}
@@ -0,0 +1,184 @@
+#![cfg(feature = "catalog")]
+
+use clickhouse::sql::PartitionId;
+
+#[tokio::test]
+async fn partitions() {
+    let client = prepare_database!();
+
+    for table in ["test", "archive"] {
+        client
+            .query(&format!(
+                "CREATE TABLE {table}(no UInt32) ENGINE = MergeTree PARTITION BY no % 3 ORDER BY no"
+            ))
+            .execute()
+            .await
+            .unwrap();
+    }
+
+    client
+        .query("INSERT INTO test SELECT number FROM system.numbers LIMIT 9")
+        .execute()
+        .await
+        .unwrap();
+
+    let partitions = client.table("test").partitions();
+    let list = partitions.list().await.unwrap();
+    assert_eq!(list.len(), 3);
+    assert!(list.iter().all(|p| p.rows == 3 && p.bytes_on_disk > 0));
+
+    partitions.drop(0).await.unwrap();
+    partitions
+        .detach(PartitionId(&list[1].partition_id))
+        .await
+        .unwrap();
+    assert_eq!(partitions.list().await.unwrap().len(), 1);
+
+    partitions.attach(1).await.unwrap();
+    partitions.move_to_table(2, "archive").await.unwrap();
+    let ids = |list: Vec<clickhouse::catalog::Partition>| {
+        list.into_iter().map(|p| p.partition).collect::<Vec<_>>()
+    };
+    assert_eq!(ids(partitions.list().await.unwrap()), ["1"]);
+
+    let archive = client.table("archive").partitions();
+    assert_eq!(ids(archive.list().await.unwrap()), ["2"]);
+
+    partitions.replace_from(2, "archive").await.unwrap();
+    assert_eq!(ids(partitions.list().await.unwrap()), ["1", "2"]);
+
+    partitions.freeze_partition(1, "backup").await.unwrap();
+
+    client
+        .query("ALTER TABLE test UPDATE no = no WHERE 1")
+        .execute()
+        .await
+        .unwrap();
+    client
+        .table("test")
+        .wait_mutations(std::time::Duration::from_millis(100))
+        .await
+        .unwrap();
+}
+
+#[cfg(feature = "test-util")]
+mod mock {
+    use std::time::Duration;
+
+    use clickhouse::{test, Client};
+
+    #[tokio::test]
+    async fn partitions() {
+        use clickhouse::sql::PartitionId;
+
+        let mock = test::Mock::new();
+        let client = Client::default().with_url(mock.url());
+        let partitions = client.table("db.events").partitions();
+
+        let recording = mock.add(test::handlers::record_ddl());
+        partitions.drop(PartitionId("2024'01")).await.unwrap();
+        assert_eq!(
+            recording.query().await,
+            r"ALTER TABLE `db`.`events` DROP PARTITION ID '2024\'01'"
+        );
+
+        let recording = mock.add(test::handlers::record_ddl());
+        partitions.detach(("a", 1)).await.unwrap();
+        assert_eq!(
+            recording.query().await,
+            "ALTER TABLE `db`.`events` DETACH PARTITION ('a',1)"
+        );
+
+        let recording = mock.add(test::handlers::record_ddl());
+        partitions.replace_from(202401, "db.staging").await.unwrap();
+        assert_eq!(
+            recording.query().await,
+            "ALTER TABLE `db`.`events` REPLACE PARTITION 202401 FROM `db`.`staging`"
+        );
+
+        let partitions = client.table("events").with_cluster("main").partitions();
+
+        let recording = mock.add(test::handlers::record_ddl());
+        partitions.move_to_table("x", "archive").await.unwrap();
+        assert_eq!(
+            recording.query().await,
+            "ALTER TABLE `events` ON CLUSTER `main` MOVE PARTITION 'x' TO TABLE `archive`"
+        );
+
+        let recording = mock.add(test::handlers::record_ddl());
+        partitions.freeze_partition(1, "backup").await.unwrap();
+        assert_eq!(
+            recording.query().await,
+            "ALTER TABLE `events` ON CLUSTER `main` FREEZE PARTITION 1 WITH NAME 'backup'"
+        );
+
+        // Names are escaped, so they can't be confused with placeholders.
+        let partitions = client.table("my-events?").partitions();
+
+        let recording = mock.add(test::handlers::record_ddl());
+        partitions.move_to_table(1, "db.my`archive").await.unwrap();
+        assert_eq!(
+            recording.query().await,
+            r"ALTER TABLE `my-events?` MOVE PARTITION 1 TO TABLE `db`.`my\`archive`"
+        );
+
+        let partitions = client.table("events").with_cluster("main?").partitions();
+
+        let recording = mock.add(test::handlers::record_ddl());
+        partitions.detach(1).await.unwrap();
+        assert_eq!(
+            recording.query().await,
+            "ALTER TABLE `events` ON CLUSTER `main?` DETACH PARTITION 1"
+        );
+    }
+
+    #[tokio::test]
+    async fn wait_mutations() {
+        use serde::Serialize;
+
+        #[derive(Serialize)]
+        struct Mutation {
+            database: &'static str,
+            table: &'static str,
+            mutation_id: &'static str,
+            command: &'static str,
+            create_time: u32,
+            parts_to_do: i64,
+            is_done: bool,
+            latest_failed_part: &'static str,
+            latest_fail_time: u32,
+            latest_fail_reason: &'static str,
+        }
+
+        let mutation = |is_done, latest_fail_reason| Mutation {
+            database: "default",
+            table: "events",
+            mutation_id: "mutation_1.txt",
+            command: "DELETE WHERE 1",
+            create_time: 0,
+            parts_to_do: if is_done { 0 } else { 1 },
+            is_done,
+            latest_failed_part: "",
+            latest_fail_time: 0,
+            latest_fail_reason,
+        };
+
+        let mock = test::Mock::new();
+        let client = Client::default().with_url(mock.url());
+        let table = client.table("events");
+        let poll_interval = Duration::from_millis(1);
+
+        mock.add(test::handlers::provide([mutation(false, "")]));
+        mock.add(test::handlers::provide([mutation(false, "")]));
+        mock.add(test::handlers::provide([mutation(true, "")]));
+        table.wait_mutations(poll_interval).await.unwrap();
+
+        mock.add(test::handlers::provide([mutation(false, "oops")]));
+        let err = table.wait_mutations(poll_interval).await.unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("mutation_1.txt of events has failed: oops"),
+            "{err}"
+        );
+    }
+}
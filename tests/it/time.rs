@@ -113,6 +113,38 @@ async fn datetime() {
     assert_eq!(row_str.dt64ns, &original_row.dt64ns.to_string()[..29]);
 }
 
+#[tokio::test]
+async fn datetime64_param() {
+    let client = prepare_database!();
+
+    #[derive(Serialize)]
+    struct Millis(#[serde(with = "clickhouse::serde::time::datetime64::millis")] OffsetDateTime);
+    #[derive(Serialize)]
+    struct Nanos(#[serde(with = "clickhouse::serde::time::datetime64::nanos")] OffsetDateTime);
+
+    let dt = datetime!(2022-11-13 15:27:42.123456789 UTC);
+
+    let (param, bound) = client
+        .query("SELECT {p: DateTime64(3, 'UTC')}, fromUnixTimestamp64Milli(toInt64(?), 'UTC')")
+        .bind(Millis(dt))
+        .param("p", Millis(dt))
+        .fetch_one::<(i64, i64)>()
+        .await
+        .unwrap();
+    assert_eq!(param, 1_668_353_262_123);
+    assert_eq!(bound, param);
+
+    let (param, bound) = client
+        .query("SELECT {p: DateTime64(9)}, fromUnixTimestamp64Nano(toInt64(?))")
+        .bind(Nanos(dt))
+        .param("p", Nanos(dt))
+        .fetch_one::<(i64, i64)>()
+        .await
+        .unwrap();
+    assert_eq!(param, 1_668_353_262_123_456_789);
+    assert_eq!(bound, param);
+}
+
 #[tokio::test]
 async fn date() {
     let client = prepare_database!();